@@ -0,0 +1,232 @@
+// allowlist.rs
+// 下载来源的安全限制：只允许 HTTPS、只允许列表中的主机
+//
+// 管理员策略和环境配置都可以给出允许的主机，两者都不为空时必须同时满足。
+// 主机写法：`maven.example.com` 只匹配该主机，`*.example.com` 匹配其所有子域名。
+// 仅 HTTPS 默认在存在管理员策略时开启，策略中设置了 `https_only` 时以策略为准且不能修改。
+// 重定向的每一跳都按同样的规则检查。
+
+use crate::config::SyncProfile;
+use crate::policy;
+use crate::redact::redact;
+
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct SourceRules {
+    pub https_only: bool,
+    /// 环境配置中的主机列表
+    pub allowed_hosts: Vec<String>,
+    /// 管理员策略中的主机列表
+    pub policy_hosts: Vec<String>,
+}
+
+/// 环境未单独设置时是否只允许 HTTPS
+pub fn https_only_default() -> bool {
+    policy::current().exists()
+}
+
+/// 策略锁定仅 HTTPS 时的说明
+pub fn https_only_lock() -> Option<String> {
+    let policy = policy::current();
+    policy.https_only.map(|_| policy.reason())
+}
+
+/// 主机是否匹配 `host` 或 `*.domain` 写法
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().trim_end_matches('.').to_lowercase();
+    let host = host.trim_end_matches('.').to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host.ends_with(&format!(".{}", domain)),
+        None => host == pattern,
+    }
+}
+
+/// 检查主机写法，只接受主机名（可带 `*.` 前缀），不接受协议、端口和路径
+pub fn validate_host_pattern(pattern: &str) -> Result<(), String> {
+    let host = pattern.trim().strip_prefix("*.").unwrap_or(pattern.trim());
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("允许的主机格式不正确: {}（应为 maven.example.com 或 *.example.com）", pattern))
+    }
+}
+
+impl SourceRules {
+    pub fn for_profile(profile: &SyncProfile) -> Self {
+        let policy = policy::current();
+        Self {
+            https_only: policy
+                .https_only
+                .unwrap_or_else(|| profile.https_only.unwrap_or_else(https_only_default)),
+            allowed_hosts: profile.allowed_hosts.clone(),
+            policy_hosts: policy.allowed_hosts.clone(),
+        }
+    }
+
+    /// 检查地址是否允许访问，不允许时返回给用户看的安全错误
+    pub fn check(&self, url: &str) -> Result<(), String> {
+        let parsed = reqwest::Url::parse(url.trim()).map_err(|_| format!("下载地址无效: {}", redact(url)))?;
+        match parsed.scheme() {
+            "https" => {}
+            "http" if !self.https_only => {}
+            "http" => {
+                return Err(format!(
+                    "安全限制：只允许 HTTPS 下载，已拒绝 {}",
+                    redact(url)
+                ))
+            }
+            scheme => return Err(format!("安全限制：不支持的协议 {}://", scheme)),
+        }
+
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| format!("无法解析下载地址的主机: {}", redact(url)))?;
+        if !self.policy_hosts.is_empty() && !self.policy_hosts.iter().any(|p| host_matches(p, host)) {
+            return Err(format!("安全限制：管理员策略不允许从 {} 下载", host));
+        }
+        if !self.allowed_hosts.is_empty() && !self.allowed_hosts.iter().any(|p| host_matches(p, host)) {
+            return Err(format!("安全限制：{} 不在允许的主机列表中", host));
+        }
+        Ok(())
+    }
+
+    /// 按同样规则检查每一跳的重定向策略，跟随和拒绝的重定向都会记录
+    pub fn redirect_policy(self) -> reqwest::redirect::Policy {
+        reqwest::redirect::Policy::custom(move |attempt| {
+            let from = attempt
+                .previous()
+                .last()
+                .map(|url| redact(url.as_str()))
+                .unwrap_or_default();
+            let to = redact(attempt.url().as_str());
+            if attempt.previous().len() > MAX_REDIRECTS {
+                log::warn!("Too many redirects, stopped at {}", to);
+                return attempt.error(format!("重定向次数过多（超过 {} 次）", MAX_REDIRECTS));
+            }
+            match self.check(attempt.url().as_str()) {
+                Ok(_) => {
+                    log::debug!("Following redirect {} -> {}", from, to);
+                    attempt.follow()
+                }
+                Err(e) => {
+                    log::warn!("Blocked redirect {} -> {}: {}", from, to, e);
+                    attempt.error(format!("重定向被拒绝（{} → {}）: {}", from, to, e))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn rules(https_only: bool, allowed_hosts: &[&str], policy_hosts: &[&str]) -> SourceRules {
+        SourceRules {
+            https_only,
+            allowed_hosts: allowed_hosts.iter().map(|h| h.to_string()).collect(),
+            policy_hosts: policy_hosts.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn wildcard_matches_only_subdomains() {
+        assert!(host_matches("*.corp.com", "repo.corp.com"));
+        assert!(host_matches("*.corp.com", "a.b.CORP.com."));
+        assert!(!host_matches("*.corp.com", "corp.com"));
+        assert!(!host_matches("*.corp.com", "evilcorp.com"));
+        assert!(!host_matches("*.corp.com", "corp.com.evil.io"));
+        assert!(host_matches("Maven.Example.com", "maven.example.com"));
+        assert!(!host_matches("maven.example.com", "maven.example.com.evil.io"));
+    }
+
+    #[test]
+    fn https_only_rejects_http() {
+        let url = "http://repo.corp.com/settings.xml";
+        assert!(rules(true, &[], &[]).check(url).unwrap_err().contains("只允许 HTTPS"));
+        assert!(rules(false, &[], &[]).check(url).is_ok());
+        assert!(rules(true, &[], &[]).check("https://repo.corp.com/settings.xml").is_ok());
+        assert!(rules(false, &[], &[]).check("ftp://repo.corp.com/settings.xml").is_err());
+    }
+
+    #[test]
+    fn host_must_pass_both_lists() {
+        let rules = rules(true, &["repo.corp.com", "mirror.other.io"], &["*.corp.com"]);
+        assert!(rules.check("https://repo.corp.com/settings.xml").is_ok());
+        // 只在环境列表中
+        assert!(rules.check("https://mirror.other.io/settings.xml").unwrap_err().contains("管理员策略"));
+        // 只在策略列表中
+        assert!(rules.check("https://files.corp.com/settings.xml").unwrap_err().contains("不在允许的主机列表中"));
+        assert!(rules.check("https://evilcorp.com/settings.xml").is_err());
+    }
+
+    /// 在本机启动只响应一次的 HTTP 服务，返回给定的响应头
+    fn serve_once(response: String) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        port
+    }
+
+    fn error_chain(error: reqwest::Error) -> String {
+        let mut message = error.to_string();
+        let mut source = std::error::Error::source(&error);
+        while let Some(cause) = source {
+            message.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+        message
+    }
+
+    #[test]
+    fn redirect_to_disallowed_host_is_refused() {
+        let port = serve_once(
+            "HTTP/1.1 302 Found\r\nLocation: http://evil.example.com/settings.xml\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        );
+        let client = reqwest::blocking::Client::builder()
+            .no_proxy()
+            .redirect(rules(false, &["127.0.0.1"], &[]).redirect_policy())
+            .build()
+            .unwrap();
+        let error = client
+            .get(format!("http://127.0.0.1:{}/settings.xml", port))
+            .send()
+            .err()
+            .unwrap();
+        let message = error_chain(error);
+        assert!(message.contains("重定向被拒绝"), "{}", message);
+        assert!(message.contains("evil.example.com 不在允许的主机列表中"), "{}", message);
+    }
+
+    #[test]
+    fn redirect_to_allowed_host_is_followed() {
+        let target = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok".to_string());
+        let port = serve_once(format!(
+            "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{}/settings.xml\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            target
+        ));
+        let client = reqwest::blocking::Client::builder()
+            .no_proxy()
+            .redirect(rules(false, &["127.0.0.1"], &[]).redirect_policy())
+            .build()
+            .unwrap();
+        let response = client.get(format!("http://127.0.0.1:{}/", port)).send().unwrap();
+        assert_eq!(response.text().unwrap(), "ok");
+    }
+}
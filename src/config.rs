@@ -0,0 +1,1064 @@
+// config.rs
+// 应用配置：多个命名的同步环境（profile）以及全局开关
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::overrides::Overrides;
+use crate::policy::Policy;
+use crate::{credentials, paths, permissions, policy};
+
+/// 当前配置文件格式版本，格式变化时递增并在 MIGRATIONS 中添加迁移
+pub const CONFIG_VERSION: u32 = 2;
+
+pub const DEFAULT_URL: &str = "https://devcloud.greenvoltis.com/maven/settings.xml";
+pub const DEFAULT_INTERVAL_HOURS: u64 = 10;
+const DEFAULT_PROFILE_ID: &str = "default";
+
+/// 下载 settings.xml 时使用的认证方式
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceAuth {
+    #[default]
+    None,
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+impl SourceAuth {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SourceAuth::None => "无",
+            SourceAuth::Basic { .. } => "用户名/密码",
+            SourceAuth::Bearer { .. } => "Bearer Token",
+        }
+    }
+
+    /// 去掉密码和令牌，保留认证方式和用户名，用于分享配置
+    pub fn without_secrets(&self) -> Self {
+        match self {
+            SourceAuth::None => SourceAuth::None,
+            SourceAuth::Basic { username, .. } => SourceAuth::Basic {
+                username: username.clone(),
+                password: String::new(),
+            },
+            SourceAuth::Bearer { .. } => SourceAuth::Bearer { token: String::new() },
+        }
+    }
+
+    /// 导入的认证缺少密码或令牌时，沿用本机同一认证方式已有的值
+    pub fn fill_secrets_from(&mut self, existing: &SourceAuth) {
+        match (self, existing) {
+            (SourceAuth::Basic { username, password }, SourceAuth::Basic { username: old_username, password: old_password })
+                if password.is_empty() && username == old_username =>
+            {
+                *password = old_password.clone();
+            }
+            (SourceAuth::Bearer { token }, SourceAuth::Bearer { token: old_token }) if token.is_empty() => {
+                *token = old_token.clone();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 写入前对下载内容的校验规则
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TargetValidation {
+    /// 不校验
+    #[default]
+    None,
+    /// 内容不能为空
+    NonEmpty,
+    /// 必须是格式正确的 XML，可要求根元素名称
+    Xml { root: Option<String> },
+}
+
+/// 目标文件被本地修改（与最近一次同步的版本不一致）时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Enforcement {
+    /// 不检查
+    Off,
+    /// 提示用户，由用户决定保留或恢复
+    #[default]
+    Warn,
+    /// 立即恢复为最近一次同步的版本
+    Restore,
+}
+
+impl Enforcement {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Enforcement::Off => "不检查",
+            Enforcement::Warn => "提示",
+            Enforcement::Restore => "自动恢复",
+        }
+    }
+}
+
+/// 写入前对下载的 settings.xml 做的本地调整，按顺序执行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Transform {
+    /// 设置元素的文本，路径相对于 `<settings>`，如 `localRepository`，不存在时创建
+    SetElement { path: String, value: String },
+    /// 按 `<id>` 删除 `mirrors`、`servers`、`proxies` 或 `profiles` 中的一项
+    RemoveById { section: String, id: String },
+    /// 在 `<activeProfiles>` 中添加一个 profile
+    AddActiveProfile { id: String },
+    /// 添加或替换同 id 的 `<proxy>`
+    SetProxy {
+        id: String,
+        #[serde(default = "default_proxy_protocol")]
+        protocol: String,
+        host: String,
+        port: u16,
+        #[serde(default)]
+        non_proxy_hosts: String,
+        #[serde(default = "default_true")]
+        active: bool,
+    },
+}
+
+fn default_proxy_protocol() -> String {
+    "http".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// `RemoveById` 支持的列表及其中的元素名
+pub const TRANSFORM_SECTIONS: [(&str, &str); 4] =
+    [("mirrors", "mirror"), ("servers", "server"), ("proxies", "proxy"), ("profiles", "profile")];
+
+impl Transform {
+    pub fn kind_label(&self) -> &'static str {
+        match self {
+            Transform::SetElement { .. } => "设置元素",
+            Transform::RemoveById { .. } => "按 id 删除",
+            Transform::AddActiveProfile { .. } => "激活 profile",
+            Transform::SetProxy { .. } => "设置代理",
+        }
+    }
+
+    /// 一行说明，用于界面和历史记录
+    pub fn describe(&self) -> String {
+        match self {
+            Transform::SetElement { path, value } => format!("设置 {} = {}", path, value),
+            Transform::RemoveById { section, id } => format!("删除 {} 中的 {}", section, id),
+            Transform::AddActiveProfile { id } => format!("激活 profile {}", id),
+            Transform::SetProxy { id, protocol, host, port, .. } => {
+                format!("设置代理 {} = {}://{}:{}", id, protocol, host, port)
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Transform::SetElement { path, .. } => validate_element_path(path)?,
+            Transform::RemoveById { section, id } => {
+                if !TRANSFORM_SECTIONS.iter().any(|(name, _)| name == section) {
+                    return Err(format!("不支持从 {} 中删除，只支持 mirrors、servers、proxies、profiles", section));
+                }
+                if id.trim().is_empty() {
+                    return Err("删除规则缺少 id".to_string());
+                }
+            }
+            Transform::AddActiveProfile { id } => {
+                if id.trim().is_empty() {
+                    return Err("激活 profile 规则缺少 id".to_string());
+                }
+            }
+            Transform::SetProxy { id, host, .. } => {
+                if id.trim().is_empty() || host.trim().is_empty() {
+                    return Err("代理规则需要 id 和主机".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 写入 settings.xml 前是否检查其中的镜像和仓库地址
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorCheck {
+    /// 不检查
+    Off,
+    /// 检查并记录结果，不影响写入
+    #[default]
+    Report,
+    /// 主镜像不可访问时拒绝写入
+    Require,
+}
+
+impl MirrorCheck {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MirrorCheck::Off => "不检查",
+            MirrorCheck::Report => "仅记录",
+            MirrorCheck::Require => "主镜像不可用时拒绝",
+        }
+    }
+}
+
+/// 是否把 settings.xml 中的镜像和凭据同步给 Gradle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GradleOutput {
+    #[default]
+    Off,
+    /// init.d/msettings.gradle
+    Groovy,
+    /// init.d/msettings.gradle.kts
+    Kotlin,
+}
+
+impl GradleOutput {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GradleOutput::Off => "不同步",
+            GradleOutput::Groovy => "Groovy 脚本（msettings.gradle）",
+            GradleOutput::Kotlin => "Kotlin 脚本（msettings.gradle.kts）",
+        }
+    }
+}
+
+/// 一个需要同步的文件：从来源地址下载后写入目标路径
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncTarget {
+    pub id: String,
+    pub name: String,
+    /// 来源地址，为空时使用环境的下载地址
+    #[serde(default)]
+    pub url: String,
+    /// 目标路径，支持 `~` 和 `${ENV}` 形式的环境变量
+    pub path: String,
+    #[serde(default)]
+    pub validation: TargetValidation,
+    /// 保留的备份份数，0 表示不备份
+    #[serde(default = "default_backup_count")]
+    pub backup_count: usize,
+    /// 期望的 SHA-256 校验和（十六进制），本地文件一致时跳过下载
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// 被本地修改时的处理方式
+    #[serde(default)]
+    pub enforcement: Enforcement,
+    /// settings.xml 写入前的镜像检查
+    #[serde(default)]
+    pub mirror_check: MirrorCheck,
+    /// toolchains.xml 写入前合并本机检测到的 JDK
+    #[serde(default)]
+    pub merge_jdks: bool,
+}
+
+impl SyncTarget {
+    /// 默认的 ~/.m2/settings.xml
+    pub fn maven_settings() -> Self {
+        Self {
+            id: "settings".to_string(),
+            name: "settings.xml".to_string(),
+            url: String::new(),
+            path: "~/.m2/settings.xml".to_string(),
+            validation: TargetValidation::Xml { root: Some("settings".to_string()) },
+            backup_count: 1,
+            sha256: None,
+            enforcement: Enforcement::Warn,
+            mirror_check: MirrorCheck::Report,
+            merge_jdks: false,
+        }
+    }
+
+    /// 添加文件时可选的常用目标
+    pub fn presets() -> Vec<SyncTarget> {
+        let preset = |id: &str, name: &str, path: &str, validation: TargetValidation| SyncTarget {
+            id: id.to_string(),
+            name: name.to_string(),
+            url: String::new(),
+            path: path.to_string(),
+            validation,
+            backup_count: 1,
+            sha256: None,
+            enforcement: Enforcement::Warn,
+            mirror_check: MirrorCheck::Report,
+            merge_jdks: false,
+        };
+        let mut toolchains = preset(
+            "toolchains",
+            "toolchains.xml",
+            "~/.m2/toolchains.xml",
+            TargetValidation::Xml { root: Some("toolchains".to_string()) },
+        );
+        toolchains.merge_jdks = true;
+        vec![
+            preset(
+                "settings-security",
+                "settings-security.xml",
+                "~/.m2/settings-security.xml",
+                TargetValidation::Xml { root: Some("settingsSecurity".to_string()) },
+            ),
+            toolchains,
+            preset(
+                "maven-config",
+                ".mvn/maven.config",
+                "/path/to/project/.mvn/maven.config",
+                TargetValidation::NonEmpty,
+            ),
+            preset(
+                "maven-home-settings",
+                "MAVEN_HOME settings.xml",
+                "${MAVEN_HOME}/conf/settings.xml",
+                TargetValidation::Xml { root: Some("settings".to_string()) },
+            ),
+        ]
+    }
+
+    /// 检查 id、来源地址、目标路径和备份份数
+    ///
+    /// id 会作为环境数据目录和下载缓存中的文件名，不能包含路径分隔符或 `..`。
+    pub fn validate(&self) -> Result<(), String> {
+        let id = self.id.trim();
+        if id.is_empty() {
+            return Err(format!("文件 {} 缺少 id", self.name));
+        }
+        if id == "." || id.contains("..") || id.contains(['/', '\\', ':']) {
+            return Err(format!("文件 {} 的 id 无效: {}（不能包含路径分隔符或 ..）", self.name, self.id));
+        }
+        if !self.url.is_empty() && !is_http(&self.url) {
+            return Err(format!("文件 {} 的来源地址无效: {}", self.name, self.url));
+        }
+        crate::targets::expand_path(&self.path).map_err(|e| format!("文件 {}: {}", self.name, e))?;
+        if self.backup_count > MAX_BACKUP_COUNT {
+            return Err(format!("文件 {} 的备份份数不能超过 {}", self.name, MAX_BACKUP_COUNT));
+        }
+        Ok(())
+    }
+}
+
+/// 每个文件最多保留的备份份数
+pub const MAX_BACKUP_COUNT: usize = 20;
+
+fn is_http(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn default_backup_count() -> usize {
+    1
+}
+
+/// 默认始终保留本机值的元素：本地仓库位置、离线模式、交互模式
+fn default_keep_local() -> Vec<String> {
+    ["localRepository", "offline", "interactiveMode"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// settings.xml 中元素路径的写法：相对于 `<settings>`，用 `/` 分隔
+pub fn validate_element_path(path: &str) -> Result<(), String> {
+    let valid_name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    };
+    if path.trim().split('/').all(valid_name) {
+        Ok(())
+    } else {
+        Err(format!("元素路径无效: {}", path))
+    }
+}
+
+fn default_targets() -> Vec<SyncTarget> {
+    vec![SyncTarget::maven_settings()]
+}
+
+/// 合并进 settings.xml `<servers>` 的本机凭据
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerCredential {
+    /// 对应 `<server><id>`
+    pub server_id: String,
+    pub username: String,
+    /// 用主密码加密后的密码（`{...}`），不保存明文
+    pub password: String,
+}
+
+/// 一个同步环境，例如公司内网、客户 VPN、离线笔记本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncProfile {
+    /// 稳定标识，用于历史记录和本地缓存目录，改名不受影响
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub interval_hours: u64,
+    pub auth: SourceAuth,
+    /// 中央清单地址，设置后由清单决定需要同步的文件
+    pub manifest_url: String,
+    /// 需要同步的文件，默认只有 ~/.m2/settings.xml
+    pub targets: Vec<SyncTarget>,
+    /// 写入 settings.xml 前合并的服务器凭据
+    pub server_credentials: Vec<ServerCredential>,
+    /// 允许下载的主机（可用 `*.example.com`），为空表示不限制
+    pub allowed_hosts: Vec<String>,
+    /// 是否只允许 HTTPS，未设置时有管理员策略则开启
+    pub https_only: Option<bool>,
+    /// 写入 settings.xml 前的本地调整
+    pub transforms: Vec<Transform>,
+    /// 始终沿用本机 settings.xml 中的这些元素，不被下载的内容覆盖
+    pub keep_local: Vec<String>,
+    /// 同步给 Gradle 的 init 脚本和 gradle.properties
+    pub gradle: GradleOutput,
+}
+
+impl SyncProfile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            id: format!("profile-{}", chrono::Local::now().timestamp_millis()),
+            name: name.to_string(),
+            url: String::new(),
+            interval_hours: DEFAULT_INTERVAL_HOURS,
+            auth: SourceAuth::None,
+            manifest_url: String::new(),
+            targets: default_targets(),
+            server_credentials: Vec::new(),
+            allowed_hosts: Vec::new(),
+            https_only: None,
+            transforms: Vec::new(),
+            keep_local: default_keep_local(),
+            gradle: GradleOutput::Off,
+        }
+    }
+
+    /// 是否由中央清单管理
+    pub fn uses_manifest(&self) -> bool {
+        !self.manifest_url.trim().is_empty()
+    }
+
+    /// 是否配置了可用的下载来源（下载地址或清单地址）
+    pub fn has_source(&self) -> bool {
+        self.uses_manifest() || self.url.starts_with("http")
+    }
+
+    /// 目标实际使用的来源地址
+    pub fn source_url<'a>(&'a self, target: &'a SyncTarget) -> &'a str {
+        if target.url.is_empty() {
+            &self.url
+        } else {
+            &target.url
+        }
+    }
+
+    /// 与界面中手动修改相同的检查：地址格式、间隔范围、目标路径
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.url.is_empty() && !is_http(&self.url) {
+            return Err(format!("下载地址无效: {}", self.url));
+        }
+        if !(1..=168).contains(&self.interval_hours) {
+            return Err(format!("更新间隔应为 1 到 168 小时，实际为 {}", self.interval_hours));
+        }
+        if self.uses_manifest() && !is_http(self.manifest_url.trim()) {
+            return Err(format!("中央清单地址无效: {}", self.manifest_url));
+        }
+        for target in &self.targets {
+            target.validate()?;
+        }
+        for credential in &self.server_credentials {
+            if credential.server_id.trim().is_empty() {
+                return Err("服务器凭据缺少 server id".to_string());
+            }
+        }
+        for host in &self.allowed_hosts {
+            crate::allowlist::validate_host_pattern(host)?;
+        }
+        for transform in &self.transforms {
+            transform.validate()?;
+        }
+        for path in &self.keep_local {
+            validate_element_path(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for SyncProfile {
+    fn default() -> Self {
+        Self {
+            id: DEFAULT_PROFILE_ID.to_string(),
+            name: "默认环境".to_string(),
+            url: DEFAULT_URL.to_string(),
+            interval_hours: DEFAULT_INTERVAL_HOURS,
+            auth: SourceAuth::None,
+            manifest_url: String::new(),
+            targets: default_targets(),
+            server_credentials: Vec::new(),
+            allowed_hosts: Vec::new(),
+            https_only: None,
+            transforms: Vec::new(),
+            keep_local: default_keep_local(),
+            gradle: GradleOutput::Off,
+        }
+    }
+}
+
+/// 桌面通知的开关，按事件分别设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Notifications {
+    /// 更新失败
+    pub on_failure: bool,
+    /// 连续多次更新失败
+    pub on_repeated_failure: bool,
+    /// 更新后文件内容有变化
+    pub on_change: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            on_failure: true,
+            on_repeated_failure: true,
+            on_change: true,
+        }
+    }
+}
+
+/// Webhook 的请求格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// 完整的 JSON 报告
+    #[default]
+    Json,
+    /// Slack incoming webhook（`{"text": ...}`）
+    Slack,
+    /// Microsoft Teams 连接器（MessageCard）
+    Teams,
+}
+
+impl WebhookFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WebhookFormat::Json => "JSON",
+            WebhookFormat::Slack => "Slack",
+            WebhookFormat::Teams => "Teams",
+        }
+    }
+}
+
+/// 每次同步后接收结果的 Webhook
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    /// 地址中通常带有令牌，按密钥对待，不写入日志，导出时默认不包含
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// 只在同步失败时发送
+    #[serde(default)]
+    pub failures_only: bool,
+}
+
+impl Webhook {
+    pub fn validate(&self) -> Result<(), String> {
+        match reqwest::Url::parse(self.url.trim()) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => Ok(()),
+            _ => Err("Webhook 地址应为 http:// 或 https:// 开头的完整地址".to_string()),
+        }
+    }
+
+    /// 界面和日志中显示的地址，只保留协议和主机
+    pub fn display_host(&self) -> String {
+        reqwest::Url::parse(self.url.trim())
+            .ok()
+            .and_then(|url| url.host_str().map(|host| format!("{}://{}", url.scheme(), host)))
+            .unwrap_or_else(|| "无效地址".to_string())
+    }
+}
+
+/// 本机状态接口的默认端口
+pub const DEFAULT_STATUS_PORT: u16 = 9731;
+
+/// 本机状态接口，只监听 127.0.0.1，默认关闭
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusEndpoint {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for StatusEndpoint {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_STATUS_PORT,
+        }
+    }
+}
+
+// 配置结构，新增字段都应有默认值，旧配置文件缺少时自动补齐
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// 配置文件格式版本
+    pub version: u32,
+    pub profiles: Vec<SyncProfile>,
+    /// 当前使用的环境 id
+    pub active_profile: String,
+    pub enable_scheduler: bool,
+    pub auto_launch_enabled: bool,
+    pub minimize_to_background: bool,
+    pub notifications: Notifications,
+    /// 每次同步后发送结果的 Webhook
+    pub webhooks: Vec<Webhook>,
+    /// 供监控采集的本机状态接口
+    pub status_endpoint: StatusEndpoint,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            profiles: vec![SyncProfile::default()],
+            active_profile: DEFAULT_PROFILE_ID.to_string(),
+            enable_scheduler: false,
+            auto_launch_enabled: false,
+            minimize_to_background: true,
+            notifications: Notifications::default(),
+            webhooks: Vec::new(),
+            status_endpoint: StatusEndpoint::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// 保证至少有一个环境、环境 id 不重复，且当前环境有效
+    fn normalize(&mut self) {
+        if self.profiles.is_empty() {
+            self.profiles.push(SyncProfile::default());
+        }
+        let mut seen = Vec::new();
+        for profile in &mut self.profiles {
+            let base = profile.id.clone();
+            let mut n = 2;
+            while seen.contains(&profile.id) {
+                profile.id = format!("{}-{}", base, n);
+                n += 1;
+            }
+            seen.push(profile.id.clone());
+        }
+        if !self.profiles.iter().any(|p| p.id == self.active_profile) {
+            self.active_profile = self.profiles[0].id.clone();
+        }
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.profiles
+            .iter()
+            .position(|p| p.id == self.active_profile)
+            .unwrap_or(0)
+    }
+}
+
+type Migration = fn(&mut serde_json::Map<String, serde_json::Value>);
+
+/// 按顺序执行的迁移：(起始版本, 迁移函数)，执行后版本加一
+const MIGRATIONS: &[(u32, Migration)] = &[(1, migrate_v1_to_v2)];
+
+/// v1 只有单一的 url / interval_hours，转换为默认环境
+fn migrate_v1_to_v2(config: &mut serde_json::Map<String, serde_json::Value>) {
+    let mut profile = serde_json::Map::new();
+    profile.insert("id".to_string(), DEFAULT_PROFILE_ID.into());
+    profile.insert("name".to_string(), "默认环境".into());
+    if let Some(url) = config.remove("url") {
+        profile.insert("url".to_string(), url);
+    }
+    if let Some(interval_hours) = config.remove("interval_hours") {
+        profile.insert("interval_hours".to_string(), interval_hours);
+    }
+    config.insert("profiles".to_string(), serde_json::Value::Array(vec![profile.into()]));
+    config.insert("active_profile".to_string(), DEFAULT_PROFILE_ID.into());
+}
+
+/// 配置文件中记录的版本，早期版本没有 version 字段
+fn detect_version(config: &serde_json::Map<String, serde_json::Value>) -> u32 {
+    match config.get("version").and_then(|v| v.as_u64()) {
+        Some(version) => version as u32,
+        None if config.contains_key("profiles") => 2,
+        None => 1,
+    }
+}
+
+/// 读取配置的结果，warning 不为空时界面需要提示用户
+pub struct LoadedConfig {
+    /// 实际生效的配置：默认值 → 用户配置 → 环境变量/命令行 → 管理员策略
+    pub config: AppConfig,
+    /// 用户配置文件中的内容，保存时用于还原被上层覆盖的项
+    pub persisted: AppConfig,
+    pub warning: Option<String>,
+}
+
+/// 把无法使用的配置文件另存一份，避免被默认配置覆盖后丢失
+fn backup_config_file(path: &Path, reason: &str) -> Option<PathBuf> {
+    let backup = path.with_file_name(format!(
+        "{}.{}-{}",
+        path.file_name()?.to_string_lossy(),
+        reason,
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    ));
+    match permissions::copy_private(path, &backup) {
+        Ok(_) => Some(backup),
+        Err(e) => {
+            log::warn!("Failed to back up config file: {}", e);
+            None
+        }
+    }
+}
+
+/// 解析配置内容并迁移到当前版本，返回配置以及是否做过迁移
+fn parse_and_migrate(content: &str) -> Result<(AppConfig, bool), String> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let serde_json::Value::Object(mut map) = value else {
+        return Err("配置文件顶层不是 JSON 对象".to_string());
+    };
+
+    let original_version = detect_version(&map);
+    if original_version > CONFIG_VERSION {
+        return Err(format!(
+            "配置文件版本 {} 高于当前程序支持的版本 {}",
+            original_version, CONFIG_VERSION
+        ));
+    }
+
+    let mut version = original_version;
+    for (from, migrate) in MIGRATIONS {
+        if version == *from {
+            migrate(&mut map);
+            version = from + 1;
+        }
+    }
+    map.insert("version".to_string(), CONFIG_VERSION.into());
+
+    let config = serde_json::from_value::<AppConfig>(serde_json::Value::Object(map)).map_err(|e| e.to_string())?;
+    Ok((config, original_version != CONFIG_VERSION))
+}
+
+/// 解析导入的配置（他人导出的文件、链接或外部修改的配置文件），迁移到当前版本并逐个检查环境
+pub fn parse_shared_config(content: &str) -> Result<AppConfig, String> {
+    let (mut config, _) = parse_and_migrate(content)?;
+    config.normalize();
+    for profile in &config.profiles {
+        profile.validate().map_err(|e| format!("环境「{}」: {}", profile.name, e))?;
+    }
+    Ok(config)
+}
+
+/// 重新读取被外部修改的配置文件，有错误时不做修复也不备份，由调用方保留当前配置
+pub fn reload_user_config() -> Result<AppConfig, String> {
+    let content = fs::read_to_string(paths::config_file()).map_err(|e| e.to_string())?;
+    let mut config = parse_shared_config(&content)?;
+    protect_credentials(&mut config);
+    Ok(config)
+}
+
+/// 配置文件中手动填写的明文凭据密码加密后立即写回
+fn protect_credentials(config: &mut AppConfig) {
+    match credentials::encrypt_plaintext_credentials(config) {
+        Ok(true) => save_config(config),
+        Ok(false) => {}
+        Err(e) => log::warn!("Failed to encrypt server credentials: {}", e),
+    }
+}
+
+/// 按 默认值 → 用户配置 → 环境变量/命令行 → 管理员策略 的顺序得到生效的配置
+pub fn load_config(overrides: &Overrides) -> LoadedConfig {
+    let (mut persisted, warning) = load_user_config(&paths::config_file());
+    persisted.normalize();
+    protect_credentials(&mut persisted);
+
+    let config = effective_config(&persisted, overrides, policy::current());
+    LoadedConfig { config, persisted, warning }
+}
+
+/// 在用户配置之上依次应用环境变量/命令行覆盖和管理员策略，策略最后应用，不会被覆盖
+fn effective_config(persisted: &AppConfig, overrides: &Overrides, policy: &Policy) -> AppConfig {
+    let mut config = persisted.clone();
+    overrides.apply(&mut config);
+    policy.apply(&mut config);
+    config
+}
+
+/// 保存界面中的生效配置，被覆盖或被策略锁定的项写回用户原来的值
+pub fn save_effective_config(effective: &AppConfig, persisted: &mut AppConfig, overrides: &Overrides) {
+    let to_save = config_to_save(effective, persisted, overrides, policy::current());
+    save_config(&to_save);
+    *persisted = to_save;
+}
+
+fn config_to_save(effective: &AppConfig, persisted: &AppConfig, overrides: &Overrides, policy: &Policy) -> AppConfig {
+    let mut to_save = effective.clone();
+    overrides.restore_user_values(&mut to_save, persisted);
+    policy.restore_user_values(&mut to_save, persisted);
+    to_save
+}
+
+fn load_user_config(path: &Path) -> (AppConfig, Option<String>) {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => {
+            // 配置文件不存在或无法读取，使用默认配置
+            return (AppConfig::default(), None);
+        }
+    };
+
+    match parse_and_migrate(&content) {
+        Ok((config, migrated)) => {
+            if migrated {
+                // 迁移前保留原文件，再写回新格式
+                log::info!("Migrating config file to version {}", CONFIG_VERSION);
+                backup_config_file(path, "pre-migration");
+                write_config(path, &config);
+            }
+            (config, None)
+        }
+        Err(e) => {
+            log::warn!("Failed to parse config file: {}, using defaults", e);
+            let warning = match backup_config_file(path, "broken") {
+                Some(backup) => format!("配置文件无法读取（{}），已使用默认配置，原文件已备份到 {}", e, backup.display()),
+                None => format!("配置文件无法读取（{}），已使用默认配置", e),
+            };
+            (AppConfig::default(), Some(warning))
+        }
+    }
+}
+
+fn save_config(config: &AppConfig) {
+    write_config(&paths::config_file(), config);
+}
+
+fn write_config(path: &Path, config: &AppConfig) {
+    if let Some(parent) = path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            log::warn!("Failed to create config directory: {}", e);
+            return;
+        }
+    }
+    match serde_json::to_string_pretty(config) {
+        Ok(json) => {
+            if let Err(e) = permissions::write_private(path, json) {
+                log::warn!("Failed to write config file: {}", e);
+            }
+        }
+        Err(e) => {
+            log::warn!("Failed to serialize config: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overrides::Override;
+
+    fn user_config() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.profiles[0].url = "https://user.example.com/settings.xml".to_string();
+        config.profiles[0].interval_hours = 2;
+        config.profiles[0].manifest_url = "https://user.example.com/manifest.json".to_string();
+        config
+    }
+
+    fn locking_policy() -> Policy {
+        Policy {
+            source_url: Some("https://policy.example.com/settings.xml".to_string()),
+            manifest_url: Some(String::new()),
+            min_interval_hours: Some(6),
+            ..Policy::default()
+        }
+    }
+
+    fn env_overrides() -> Overrides {
+        let source = || "环境变量 MSETTINGS_URL".to_string();
+        Overrides {
+            url: Some(Override { value: "https://env.example.com/settings.xml".to_string(), source: source() }),
+            interval_hours: Some(Override { value: 1, source: source() }),
+            manifest_url: Some(Override { value: "https://env.example.com/manifest.json".to_string(), source: source() }),
+            ..Overrides::default()
+        }
+    }
+
+    #[test]
+    fn policy_wins_over_user_config_and_overrides() {
+        let effective = effective_config(&user_config(), &env_overrides(), &locking_policy());
+        let profile = &effective.profiles[0];
+        assert_eq!(profile.url, "https://policy.example.com/settings.xml");
+        assert_eq!(profile.manifest_url, "");
+        assert_eq!(profile.interval_hours, 6);
+
+        // 没有策略时覆盖生效
+        let effective = effective_config(&user_config(), &env_overrides(), &Policy::default());
+        assert_eq!(effective.profiles[0].url, "https://env.example.com/settings.xml");
+        assert_eq!(effective.profiles[0].interval_hours, 1);
+    }
+
+    #[test]
+    fn policy_allows_unlocked_user_values() {
+        let mut user = user_config();
+        user.profiles[0].interval_hours = 12;
+        let effective = effective_config(&user, &Overrides::default(), &locking_policy());
+        assert_eq!(effective.profiles[0].interval_hours, 12);
+    }
+
+    #[test]
+    fn saving_keeps_user_values_behind_locks() {
+        let user = user_config();
+        let policy = locking_policy();
+        let overrides = env_overrides();
+        let mut effective = effective_config(&user, &overrides, &policy);
+        effective.profiles[0].name = "公司内网".to_string();
+
+        let saved = config_to_save(&effective, &user, &overrides, &policy);
+        let profile = &saved.profiles[0];
+        assert_eq!(profile.url, "https://user.example.com/settings.xml");
+        assert_eq!(profile.manifest_url, "https://user.example.com/manifest.json");
+        assert_eq!(profile.interval_hours, 2);
+        assert_eq!(profile.name, "公司内网");
+
+        // 重新加载后策略仍然生效
+        let reloaded = effective_config(&saved, &overrides, &policy);
+        assert_eq!(reloaded.profiles[0].url, "https://policy.example.com/settings.xml");
+        assert_eq!(reloaded.profiles[0].interval_hours, 6);
+    }
+
+    #[test]
+    fn restore_user_values_only_touches_locked_fields() {
+        let user = user_config();
+        let policy = Policy {
+            min_interval_hours: Some(4),
+            ..Policy::default()
+        };
+        let mut to_save = effective_config(&user, &Overrides::default(), &policy);
+        to_save.profiles[0].url = "https://new.example.com/settings.xml".to_string();
+        policy.restore_user_values(&mut to_save, &user);
+        // 地址未锁定，保存用户的修改；间隔只因下限被抬高，保存原值
+        assert_eq!(to_save.profiles[0].url, "https://new.example.com/settings.xml");
+        assert_eq!(to_save.profiles[0].interval_hours, 2);
+    }
+
+    const V1_CONFIG: &str = r#"{
+        "url": "https://repo.corp.example.com/settings.xml",
+        "interval_hours": 8,
+        "enable_scheduler": true,
+        "auto_launch_enabled": true,
+        "minimize_to_background": false
+    }"#;
+
+    fn temp_config(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("msettings-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// 配置文件所在目录中以 `config.json.<前缀>` 开头的备份
+    fn backups(path: &Path, prefix: &str) -> Vec<PathBuf> {
+        let prefix = format!("config.json.{}-", prefix);
+        fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.file_name().unwrap().to_string_lossy().starts_with(&prefix))
+            .collect()
+    }
+
+    #[test]
+    fn detects_version_from_fields() {
+        let map = |json: &str| serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(json).unwrap();
+        assert_eq!(detect_version(&map(V1_CONFIG)), 1);
+        // 没有 version 但已有环境列表的是 v2
+        assert_eq!(detect_version(&map(r#"{"profiles": []}"#)), 2);
+        assert_eq!(detect_version(&map(r#"{"version": 7, "url": "x"}"#)), 7);
+    }
+
+    #[test]
+    fn migrates_v1_to_default_profile() {
+        let mut map = serde_json::from_str(V1_CONFIG).unwrap();
+        migrate_v1_to_v2(&mut map);
+        assert!(!map.contains_key("url"));
+        assert!(!map.contains_key("interval_hours"));
+        assert_eq!(map["active_profile"], DEFAULT_PROFILE_ID);
+        assert_eq!(map["profiles"][0]["url"], "https://repo.corp.example.com/settings.xml");
+
+        let (config, migrated) = parse_and_migrate(V1_CONFIG).unwrap();
+        assert!(migrated);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.profiles.len(), 1);
+        assert_eq!(config.active_profile, DEFAULT_PROFILE_ID);
+        assert_eq!(config.profiles[0].url, "https://repo.corp.example.com/settings.xml");
+        assert_eq!(config.profiles[0].interval_hours, 8);
+        // 其余设置原样保留
+        assert!(config.enable_scheduler);
+        assert!(config.auto_launch_enabled);
+        assert!(!config.minimize_to_background);
+    }
+
+    #[test]
+    fn unversioned_config_with_profiles_is_not_migrated() {
+        let content = r#"{
+            "profiles": [{ "id": "corp", "name": "公司", "url": "https://corp.example.com/settings.xml", "interval_hours": 3 }],
+            "active_profile": "corp",
+            "enable_scheduler": true
+        }"#;
+        let (config, migrated) = parse_and_migrate(content).unwrap();
+        assert!(!migrated);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.active_profile, "corp");
+        assert_eq!(config.profiles[0].url, "https://corp.example.com/settings.xml");
+        assert_eq!(config.profiles[0].interval_hours, 3);
+        assert!(config.enable_scheduler);
+    }
+
+    #[test]
+    fn refuses_newer_version() {
+        let content = format!(r#"{{"version": {}, "profiles": []}}"#, CONFIG_VERSION + 1);
+        let error = parse_and_migrate(&content).err().unwrap();
+        assert!(error.contains("高于当前程序支持的版本"));
+        assert!(parse_and_migrate("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn migration_backs_up_and_rewrites_file() {
+        let path = temp_config("migrate", V1_CONFIG);
+        let (config, warning) = load_user_config(&path);
+        assert!(warning.is_none());
+        assert_eq!(config.profiles[0].url, "https://repo.corp.example.com/settings.xml");
+
+        let backup = backups(&path, "pre-migration");
+        assert_eq!(backup.len(), 1);
+        assert_eq!(fs::read_to_string(&backup[0]).unwrap(), V1_CONFIG);
+        let (rewritten, migrated) = parse_and_migrate(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(!migrated);
+        assert_eq!(rewritten, config);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn broken_file_is_backed_up_with_warning() {
+        let broken = "{ \"profiles\": [ ";
+        let path = temp_config("broken", broken);
+        let (config, warning) = load_user_config(&path);
+        assert_eq!(config, AppConfig::default());
+
+        let backup = backups(&path, "broken");
+        assert_eq!(backup.len(), 1);
+        assert_eq!(fs::read_to_string(&backup[0]).unwrap(), broken);
+        let warning = warning.unwrap();
+        assert!(warning.contains(&backup[0].display().to_string()));
+        // 原文件不被默认配置覆盖
+        assert_eq!(fs::read_to_string(&path).unwrap(), broken);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
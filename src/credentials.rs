@@ -0,0 +1,346 @@
+// credentials.rs
+// 本机服务器凭据：写入前合并进下载的 settings.xml，并保证写到磁盘的密码都已加密
+//
+// 修改通过 xmledit 按元素的字节范围替换原文完成，其余内容（注释、格式）保持不变。
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::config::{AppConfig, ServerCredential, SyncTarget};
+use crate::mavensec;
+use crate::xmledit::{apply_edits, before_end_tag, child, child_text, escape_xml, indent_of, Edit};
+
+/// 是否为 Maven 的 settings.xml，需要合并凭据、加密密码
+pub fn is_maven_settings(target: &SyncTarget) -> bool {
+    Path::new(target.path.trim())
+        .file_name()
+        .is_some_and(|name| name == "settings.xml")
+}
+
+fn server_block(credential: &ServerCredential, indent: &str, step: &str) -> String {
+    let mut block = format!("{}<server>\n{}{}<id>{}</id>\n", indent, indent, step, escape_xml(&credential.server_id));
+    if !credential.username.is_empty() {
+        block.push_str(&format!("{}{}<username>{}</username>\n", indent, step, escape_xml(&credential.username)));
+    }
+    if !credential.password.is_empty() {
+        block.push_str(&format!("{}{}<password>{}</password>\n", indent, step, escape_xml(&credential.password)));
+    }
+    block.push_str(&format!("{}</server>", indent));
+    block
+}
+
+/// 合并凭据：已有同 id 的 `<server>` 更新用户名和密码，没有的追加到 `<servers>`
+fn merge_credentials(content: &str, credentials: &[ServerCredential]) -> Result<String, String> {
+    if credentials.is_empty() {
+        return Ok(content.to_string());
+    }
+    let doc = roxmltree::Document::parse(content).map_err(|e| format!("XML 格式错误: {}", e))?;
+    let root = doc.root_element();
+    let servers = child(root, "servers");
+    let step = "  ";
+
+    let mut edits = Vec::new();
+    let mut missing = Vec::new();
+    for credential in credentials {
+        let server = servers.and_then(|servers| {
+            servers
+                .children()
+                .find(|n| n.has_tag_name("server") && child_text(*n, "id").as_deref() == Some(credential.server_id.as_str()))
+        });
+        let Some(server) = server else {
+            missing.push(credential);
+            continue;
+        };
+
+        // 已有的子元素原地替换，缺少的插在 <id> 之后
+        let Some(id_node) = child(server, "id") else {
+            continue;
+        };
+        let indent = indent_of(content, id_node.range().start);
+        let mut inserted = String::new();
+        for (name, value) in [("username", &credential.username), ("password", &credential.password)] {
+            if value.is_empty() {
+                continue;
+            }
+            let element = format!("<{}>{}</{}>", name, escape_xml(value), name);
+            match child(server, name) {
+                Some(node) => edits.push(Edit {
+                    range: node.range(),
+                    text: element,
+                }),
+                None => inserted.push_str(&format!("\n{}{}", indent, element)),
+            }
+        }
+        if !inserted.is_empty() {
+            let end = id_node.range().end;
+            edits.push(Edit {
+                range: end..end,
+                text: inserted,
+            });
+        }
+    }
+
+    if !missing.is_empty() {
+        match servers {
+            Some(servers) => {
+                let indent = format!("{}{}", indent_of(content, servers.range().start), step);
+                let blocks: Vec<String> = missing.iter().map(|c| server_block(c, &indent, step)).collect();
+                match before_end_tag(content, servers) {
+                    Some(pos) => edits.push(Edit {
+                        range: pos..pos,
+                        text: format!("\n{}", blocks.join("\n")),
+                    }),
+                    None => edits.push(Edit {
+                        range: servers.range(),
+                        text: format!(
+                            "<servers>\n{}\n{}</servers>",
+                            blocks.join("\n"),
+                            indent_of(content, servers.range().start)
+                        ),
+                    }),
+                }
+            }
+            None => {
+                let root_indent = indent_of(content, root.range().start);
+                let indent = format!("{}{}", root_indent, step);
+                let inner = format!("{}{}", indent, step);
+                let blocks: Vec<String> = missing.iter().map(|c| server_block(c, &inner, step)).collect();
+                let pos = before_end_tag(content, root).ok_or_else(|| "settings.xml 的根元素为空".to_string())?;
+                edits.push(Edit {
+                    range: pos..pos,
+                    text: format!("\n{}<servers>\n{}\n{}</servers>", indent, blocks.join("\n"), indent),
+                });
+            }
+        }
+    }
+
+    Ok(apply_edits(content, edits))
+}
+
+/// `<server>` 中的明文密码和口令
+fn plaintext_secrets<'a, 'input>(doc: &'a roxmltree::Document<'input>) -> Vec<roxmltree::Node<'a, 'input>> {
+    doc.descendants()
+        .filter(|n| n.has_tag_name("server"))
+        .flat_map(|server| server.children().filter(|n| n.has_tag_name("password") || n.has_tag_name("passphrase")))
+        .filter(|n| {
+            let text = n.text().unwrap_or_default().trim();
+            !text.is_empty() && !mavensec::is_encrypted(text) && !text.starts_with("${")
+        })
+        .collect()
+}
+
+/// settings.xml 中是否还有明文密码
+pub fn has_plaintext_secrets(content: &str) -> bool {
+    roxmltree::Document::parse(content).is_ok_and(|doc| !plaintext_secrets(&doc).is_empty())
+}
+
+/// 密码所在 `<server>` 的 id 和元素名
+fn secret_key(node: roxmltree::Node) -> Option<(String, String)> {
+    let server = node.parent()?;
+    Some((child_text(server, "id")?, node.tag_name().name().to_string()))
+}
+
+/// 现有文件中能用主密码解密的密码：(server id, 元素名) → (密文, 明文)
+fn existing_secrets(existing: &str, master: &str) -> HashMap<(String, String), (String, String)> {
+    let Ok(doc) = roxmltree::Document::parse(existing) else {
+        return HashMap::new();
+    };
+    doc.descendants()
+        .filter(|n| n.has_tag_name("password") || n.has_tag_name("passphrase"))
+        .filter(|n| n.parent().is_some_and(|p| p.has_tag_name("server")))
+        .filter_map(|node| {
+            let cipher = node.text().unwrap_or_default().trim();
+            let plain = mavensec::is_encrypted(cipher).then(|| mavensec::decrypt(cipher, master).ok())??;
+            Some((secret_key(node)?, (cipher.to_string(), plain)))
+        })
+        .collect()
+}
+
+/// 用主密码加密 settings.xml 中的明文密码，无法解析的内容原样返回
+///
+/// 每次加密的盐和填充都是随机的，明文与现有文件中同一服务器的密码相同时沿用原密文，
+/// 否则内容相同的下载每次写入的字节都不同，会被当作有变化。
+pub fn protect_plaintext(content: &str, master: &str, existing: Option<&str>) -> String {
+    let Ok(doc) = roxmltree::Document::parse(content) else {
+        return content.to_string();
+    };
+    let existing = existing.map(|existing| existing_secrets(existing, master)).unwrap_or_default();
+    let edits = plaintext_secrets(&doc)
+        .into_iter()
+        .map(|node| {
+            let name = node.tag_name().name();
+            let plain = node.text().unwrap_or_default().trim();
+            let cipher = secret_key(node)
+                .and_then(|key| existing.get(&key))
+                .filter(|(_, existing_plain)| existing_plain == plain)
+                .map(|(cipher, _)| cipher.clone())
+                .unwrap_or_else(|| mavensec::encrypt(plain, master));
+            Edit {
+                range: node.range(),
+                text: format!("<{}>{}</{}>", name, cipher, name),
+            }
+        })
+        .collect();
+    apply_edits(content, edits)
+}
+
+/// 写入前处理下载的 settings.xml：合并本机凭据，并加密所有明文密码
+///
+/// `existing` 为目标文件当前的内容，密码未变时沿用其中的密文。
+pub fn prepare_settings(
+    content: &[u8],
+    credentials: &[ServerCredential],
+    existing: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(content).map_err(|_| "内容不是有效的 UTF-8 文本".to_string())?;
+    if credentials.is_empty() && !has_plaintext_secrets(text) {
+        return Ok(content.to_vec());
+    }
+
+    let master = mavensec::ensure_master_password()?;
+    prepare_with_master(text, credentials, existing, &master)
+}
+
+fn prepare_with_master(
+    text: &str,
+    credentials: &[ServerCredential],
+    existing: Option<&[u8]>,
+    master: &str,
+) -> Result<Vec<u8>, String> {
+    for credential in credentials.iter().filter(|c| !c.password.is_empty()) {
+        mavensec::decrypt(&credential.password, master).map_err(|_| {
+            format!("服务器 {} 的凭据无法用当前主密码解密，请重新输入密码", credential.server_id)
+        })?;
+    }
+
+    let merged = merge_credentials(text, credentials)?;
+    let existing = existing.and_then(|existing| std::str::from_utf8(existing).ok());
+    Ok(protect_plaintext(&merged, master, existing).into_bytes())
+}
+
+/// 加密 settings.xml 内容中的明文密码，没有明文密码（或不是文本）时原样返回
+pub fn protect_secrets(content: &[u8]) -> Result<Vec<u8>, String> {
+    let Ok(text) = std::str::from_utf8(content) else {
+        return Ok(content.to_vec());
+    };
+    if !has_plaintext_secrets(text) {
+        return Ok(content.to_vec());
+    }
+    let master = mavensec::ensure_master_password()?;
+    Ok(protect_plaintext(text, &master, None).into_bytes())
+}
+
+/// 备份前加密现有 settings.xml 中的明文密码（例如用户手动粘贴的）
+pub fn protect_for_backup(content: &[u8]) -> Vec<u8> {
+    protect_secrets(content).unwrap_or_else(|e| {
+        log::warn!("Failed to get master password for backup: {}", e);
+        content.to_vec()
+    })
+}
+
+/// 配置中手动填写或导入的明文凭据密码改为加密保存，返回是否有修改
+pub fn encrypt_plaintext_credentials(config: &mut AppConfig) -> Result<bool, String> {
+    let needs_encryption = |c: &ServerCredential| !c.password.is_empty() && !mavensec::is_encrypted(&c.password);
+    if !config.profiles.iter().flat_map(|p| &p.server_credentials).any(needs_encryption) {
+        return Ok(false);
+    }
+    let master = mavensec::ensure_master_password()?;
+    for credential in config.profiles.iter_mut().flat_map(|p| &mut p.server_credentials) {
+        if needs_encryption(credential) {
+            credential.password = mavensec::encrypt(&credential.password, &master);
+        }
+    }
+    Ok(true)
+}
+
+/// 更换主密码后用新主密码重新加密凭据，无法解密的密码被清空，返回其 server id
+pub fn reencrypt_credentials(config: &mut AppConfig, old: Option<&str>, new: &str) -> Vec<String> {
+    let mut failed = Vec::new();
+    for credential in config.profiles.iter_mut().flat_map(|p| &mut p.server_credentials) {
+        if credential.password.is_empty() {
+            continue;
+        }
+        match old.map(|old| mavensec::decrypt(&credential.password, old)) {
+            Some(Ok(plain)) => credential.password = mavensec::encrypt(&plain, new),
+            _ => {
+                credential.password.clear();
+                failed.push(credential.server_id.clone());
+            }
+        }
+    }
+    failed
+}
+
+/// 导出时解密凭据，便于在其他电脑上用各自的主密码重新加密
+pub fn decrypt_credentials_for_export(config: &mut AppConfig) {
+    let master = mavensec::master_password().ok().flatten();
+    for credential in config.profiles.iter_mut().flat_map(|p| &mut p.server_credentials) {
+        credential.password = master
+            .as_deref()
+            .and_then(|master| mavensec::decrypt(&credential.password, master).ok())
+            .unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER: &str = "master-secret";
+
+    const DOWNLOADED: &str = r#"<settings>
+  <servers>
+    <server>
+      <id>nexus</id>
+      <username>deploy</username>
+      <password>plain-pa55</password>
+    </server>
+  </servers>
+</settings>"#;
+
+    fn server_password(content: &[u8], id: &str) -> String {
+        let text = std::str::from_utf8(content).unwrap();
+        let doc = roxmltree::Document::parse(text).unwrap();
+        let server = doc
+            .descendants()
+            .find(|n| n.has_tag_name("server") && child_text(*n, "id").as_deref() == Some(id))
+            .unwrap();
+        child_text(server, "password").unwrap()
+    }
+
+    #[test]
+    fn resyncing_same_plaintext_keeps_written_bytes() {
+        let credential = ServerCredential {
+            server_id: "releases".to_string(),
+            username: "ci".to_string(),
+            password: mavensec::encrypt("ci-pa55", MASTER),
+        };
+        let credentials = vec![credential];
+
+        // 第一次同步：目标文件不存在
+        let first = prepare_with_master(DOWNLOADED, &credentials, None, MASTER).unwrap();
+        assert!(!String::from_utf8_lossy(&first).contains("plain-pa55"));
+        // 第二次同步同样的内容，写入的字节不变，不算作有变化
+        let second = prepare_with_master(DOWNLOADED, &credentials, Some(&first), MASTER).unwrap();
+        let changed = Some(&first[..]) != Some(&second[..]);
+        assert!(!changed);
+    }
+
+    #[test]
+    fn reencrypts_when_plaintext_changes() {
+        let first = prepare_with_master(DOWNLOADED, &[], None, MASTER).unwrap();
+        let updated = DOWNLOADED.replace("plain-pa55", "new-pa55");
+        let second = prepare_with_master(&updated, &[], Some(&first), MASTER).unwrap();
+        assert_ne!(first, second);
+        let cipher = server_password(&second, "nexus");
+        assert_eq!(mavensec::decrypt(&cipher, MASTER).unwrap(), "new-pa55");
+    }
+
+    #[test]
+    fn ignores_existing_secrets_of_other_servers() {
+        let existing = protect_plaintext(&DOWNLOADED.replace("<id>nexus</id>", "<id>other</id>"), MASTER, None);
+        let written = protect_plaintext(DOWNLOADED, MASTER, Some(&existing));
+        let reused = server_password(existing.as_bytes(), "other");
+        assert_ne!(server_password(written.as_bytes(), "nexus"), reused);
+    }
+}
@@ -0,0 +1,524 @@
+// gradle.rs
+// 把 Maven settings.xml 中的镜像和凭据同步给 Gradle：
+// `~/.gradle/init.d/msettings.gradle(.kts)` 按 mirrorOf 规则把仓库地址重定向到镜像，
+// `~/.gradle/gradle.properties` 中由标记行包围的一段保存镜像的用户名和密码
+//
+// 路由表在生成时按 settings.xml 计算：中央仓库和激活 profile 中的仓库各自对应的镜像，
+// 以及任意外部仓库会落到的镜像（mirrorOf 为 `*`、`external:*` 时），脚本运行时只查表。
+// gradle.properties 中标记以外的内容保持不变。设置了 GRADLE_USER_HOME 时写到该目录下。
+
+use std::fs;
+
+use crate::config::{GradleOutput, SyncProfile, SyncTarget, TargetValidation};
+use crate::{credentials, inspect, mavensec, targets};
+use crate::xmledit::{child, child_text};
+
+const SCRIPT_NAME: &str = "msettings";
+const HEADER: &str = "// 由 AutoUpdateMavenSettings 根据 Maven settings.xml 生成，每次同步时覆盖，请勿手动修改";
+const BLOCK_START: &str = "# >>> msettings：由 AutoUpdateMavenSettings 根据 Maven settings.xml 生成，请勿手动修改";
+const BLOCK_END: &str = "# <<< msettings";
+const PROPERTIES_ID: &str = "gradle-properties";
+
+/// 判断任意外部仓库会落到哪个镜像时使用的仓库
+const ANY_REPOSITORY: (&str, &str) = ("msettings-any-repository", "https://repository.invalid/maven2");
+/// Gradle 中 mavenCentral() 可能使用的地址
+const CENTRAL_ALIASES: [&str; 1] = ["https://repo1.maven.org/maven2"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GradleMirrors {
+    /// (镜像 id, 地址)
+    pub mirrors: Vec<(String, String)>,
+    /// (仓库地址, 镜像 id)
+    pub routes: Vec<(String, String)>,
+    /// 其他外部仓库使用的镜像
+    pub fallback: Option<String>,
+    /// (镜像 id, 用户名, 明文密码)
+    pub credentials: Vec<(String, String, String)>,
+}
+
+fn normalize_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+fn gradle_home() -> &'static str {
+    if std::env::var_os("GRADLE_USER_HOME").is_some() {
+        "${GRADLE_USER_HOME}"
+    } else {
+        "~/.gradle"
+    }
+}
+
+fn output_target(id: &str, name: &str, path: String, backup_count: usize) -> SyncTarget {
+    SyncTarget {
+        id: id.to_string(),
+        name: name.to_string(),
+        url: String::new(),
+        path,
+        validation: TargetValidation::NonEmpty,
+        backup_count,
+        sha256: None,
+        enforcement: Default::default(),
+        mirror_check: Default::default(),
+        merge_jdks: false,
+    }
+}
+
+fn script_target(output: GradleOutput, backup_count: usize) -> Option<SyncTarget> {
+    let file = match output {
+        GradleOutput::Off => return None,
+        GradleOutput::Groovy => format!("{}.gradle", SCRIPT_NAME),
+        GradleOutput::Kotlin => format!("{}.gradle.kts", SCRIPT_NAME),
+    };
+    Some(output_target(
+        "gradle-init",
+        &file,
+        format!("{}/init.d/{}", gradle_home(), file),
+        backup_count,
+    ))
+}
+
+fn properties_target(backup_count: usize) -> SyncTarget {
+    output_target(
+        PROPERTIES_ID,
+        "gradle.properties",
+        format!("{}/gradle.properties", gradle_home()),
+        backup_count,
+    )
+}
+
+/// 从 settings.xml 计算镜像路由和凭据，加密的密码用主密码解密
+pub fn from_settings(content: &str) -> Result<GradleMirrors, String> {
+    let settings = inspect::parse(content)?;
+    let mut result = GradleMirrors::default();
+    let mut used = Vec::new();
+    for repo in settings.effective_repositories() {
+        if let Some(mirror) = inspect::resolve(&settings.mirrors, &repo.id, &repo.url) {
+            let url = normalize_url(&repo.url);
+            if url != normalize_url(&mirror.url) && !result.routes.iter().any(|(u, _)| *u == url) {
+                result.routes.push((url, mirror.id.clone()));
+            }
+            if repo.id == inspect::CENTRAL_ID {
+                for alias in CENTRAL_ALIASES {
+                    result.routes.push((alias.to_string(), mirror.id.clone()));
+                }
+            }
+            used.push(mirror.id.clone());
+        }
+    }
+    if let Some(mirror) = inspect::resolve(&settings.mirrors, ANY_REPOSITORY.0, ANY_REPOSITORY.1) {
+        result.fallback = Some(mirror.id.clone());
+        used.push(mirror.id.clone());
+    }
+    for mirror in &settings.mirrors {
+        if used.contains(&mirror.id) && !result.mirrors.iter().any(|(id, _)| *id == mirror.id) {
+            result.mirrors.push((mirror.id.clone(), normalize_url(&mirror.url)));
+        }
+    }
+
+    // 与镜像 id 相同的 <server> 即镜像的凭据
+    let doc = roxmltree::Document::parse(content).map_err(|e| format!("XML 格式错误: {}", e))?;
+    let servers = child(doc.root_element(), "servers");
+    let mut master = None;
+    for server in servers.into_iter().flat_map(|s| s.children()).filter(|n| n.has_tag_name("server")) {
+        let id = child_text(server, "id").unwrap_or_default();
+        let username = child_text(server, "username").unwrap_or_default();
+        if username.is_empty() || !result.mirrors.iter().any(|(mirror, _)| *mirror == id) {
+            continue;
+        }
+        let password = child_text(server, "password").unwrap_or_default();
+        let password = if password.starts_with("${") {
+            log::warn!("Password of server {} is a property reference, skipped for Gradle", id);
+            continue;
+        } else if mavensec::is_encrypted(&password) {
+            if master.is_none() {
+                master = mavensec::master_password()?;
+            }
+            let key = master.as_deref().ok_or_else(|| "settings.xml 中有加密的密码，但没有主密码".to_string())?;
+            mavensec::decrypt(&password, key).map_err(|e| format!("无法解密服务器 {} 的密码: {}", id, e))?
+        } else {
+            password
+        };
+        result.credentials.push((id, username, password));
+    }
+    Ok(result)
+}
+
+fn groovy_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn kotlin_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('$', "\\$"))
+}
+
+const GROOVY_BODY: &str = r#"
+def msettingsRedirect = { RepositoryHandler repositories, Closure property ->
+    repositories.withType(MavenArtifactRepository).configureEach { repo ->
+        def url = repo.url.toString().replaceAll('/+$', '')
+        def mirror = msettingsRoutes[url]
+        if (mirror == null && msettingsFallback != null && repo.url.scheme in ['http', 'https'] && !msettingsMirrors.containsValue(url)) {
+            mirror = msettingsFallback
+        }
+        if (mirror == null) {
+            return
+        }
+        repo.url = msettingsMirrors[mirror]
+        def username = property("msettings.${mirror}.username".toString())
+        if (username != null) {
+            repo.credentials {
+                it.username = username
+                it.password = property("msettings.${mirror}.password".toString())
+            }
+        }
+    }
+}
+
+settingsEvaluated { settings ->
+    def property = { name -> settings.providers.gradleProperty(name).getOrNull() }
+    msettingsRedirect(settings.pluginManagement.repositories, property)
+    msettingsRedirect(settings.dependencyResolutionManagement.repositories, property)
+}
+
+allprojects { project ->
+    def property = { name -> project.findProperty(name)?.toString() }
+    msettingsRedirect(project.buildscript.repositories, property)
+    msettingsRedirect(project.repositories, property)
+}
+"#;
+
+const KOTLIN_BODY: &str = r#"
+fun msettingsRedirect(repositories: RepositoryHandler, property: (String) -> String?) {
+    repositories.withType(MavenArtifactRepository::class.java).configureEach {
+        val url = this.url.toString().trimEnd('/')
+        val mirror = msettingsRoutes[url]
+            ?: msettingsFallback?.takeIf { this.url.scheme in listOf("http", "https") && url !in msettingsMirrors.values }
+            ?: return@configureEach
+        setUrl(msettingsMirrors.getValue(mirror))
+        val username = property("msettings.$mirror.username") ?: return@configureEach
+        credentials {
+            this.username = username
+            this.password = property("msettings.$mirror.password")
+        }
+    }
+}
+
+settingsEvaluated {
+    val property = { name: String -> providers.gradleProperty(name).orNull }
+    msettingsRedirect(pluginManagement.repositories, property)
+    msettingsRedirect(dependencyResolutionManagement.repositories, property)
+}
+
+allprojects {
+    val property = { name: String -> findProperty(name)?.toString() }
+    msettingsRedirect(buildscript.repositories, property)
+    msettingsRedirect(repositories, property)
+}
+"#;
+
+/// 生成 init 脚本
+pub fn init_script(mirrors: &GradleMirrors, output: GradleOutput) -> String {
+    let kotlin = output == GradleOutput::Kotlin;
+    let quote = |value: &str| if kotlin { kotlin_string(value) } else { groovy_string(value) };
+    let map = |name: &str, entries: Vec<(String, String)>| {
+        let mut text = if kotlin {
+            format!("val {} = mapOf<String, String>(\n", name)
+        } else if entries.is_empty() {
+            format!("def {} = [:]\n", name)
+        } else {
+            format!("def {} = [\n", name)
+        };
+        let separator = if kotlin { " to " } else { ": " };
+        for (key, value) in &entries {
+            text.push_str(&format!("    {}{}{},\n", quote(key), separator, quote(value)));
+        }
+        if kotlin {
+            text.push_str(")\n");
+        } else if !entries.is_empty() {
+            text.push_str("]\n");
+        }
+        text
+    };
+
+    let mut script = format!("{}\n// 需要 Gradle 6.8 或更新版本\n\n", HEADER);
+    script.push_str(&map("msettingsMirrors", mirrors.mirrors.clone()));
+    script.push_str(&map("msettingsRoutes", mirrors.routes.clone()));
+    let fallback = mirrors.fallback.as_deref().map_or("null".to_string(), quote);
+    if kotlin {
+        script.push_str(&format!("val msettingsFallback: String? = {}\n", fallback));
+        script.push_str(KOTLIN_BODY);
+    } else {
+        script.push_str(&format!("def msettingsFallback = {}\n", fallback));
+        script.push_str(GROOVY_BODY);
+    }
+    script
+}
+
+/// properties 文件中的键和值按 ISO-8859-1 读取，其余字符用 \uXXXX 表示
+fn escape_property(value: &str, key: bool) -> String {
+    let mut escaped = String::new();
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ' ' if key || i == 0 => escaped.push_str("\\ "),
+            '=' | ':' | '#' | '!' if key => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_ascii() => escaped.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    escaped.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+fn properties_block(mirrors: &GradleMirrors) -> String {
+    let mut block = format!("{}\n", BLOCK_START);
+    for (id, username, password) in &mirrors.credentials {
+        block.push_str(&format!(
+            "{}={}\n",
+            escape_property(&format!("msettings.{}.username", id), true),
+            escape_property(username, false)
+        ));
+        block.push_str(&format!(
+            "{}={}\n",
+            escape_property(&format!("msettings.{}.password", id), true),
+            escape_property(password, false)
+        ));
+    }
+    block.push_str(BLOCK_END);
+    block.push('\n');
+    block
+}
+
+/// 替换 gradle.properties 中标记包围的一段，没有时追加到末尾；block 为空时删除这一段
+pub fn merge_properties(existing: &str, block: &str) -> String {
+    let start = existing.find(BLOCK_START);
+    let end = start.and_then(|start| existing[start..].find(BLOCK_END).map(|end| start + end + BLOCK_END.len()));
+    match (start, end) {
+        (Some(start), Some(end)) => {
+            let end = if existing[end..].starts_with("\r\n") {
+                end + 2
+            } else if existing[end..].starts_with('\n') {
+                end + 1
+            } else {
+                end
+            };
+            format!("{}{}{}", &existing[..start], block, &existing[end..])
+        }
+        _ if block.is_empty() => existing.to_string(),
+        _ if existing.is_empty() || existing.ends_with('\n') => format!("{}{}", existing, block),
+        _ => format!("{}\n{}", existing, block),
+    }
+}
+
+/// 是否为本程序写入的 gradle.properties
+pub fn is_properties(target: &SyncTarget) -> bool {
+    target.id == PROPERTIES_ID
+}
+
+/// 备份 gradle.properties 前去掉标记包围的凭据，这一段每次同步都会重新生成
+///
+/// 不是 UTF-8 的文件按替换无效字符后的文本处理，备份中的个别字符可能与原文件不同。
+pub fn without_credentials(content: &[u8]) -> Vec<u8> {
+    let text = String::from_utf8_lossy(content);
+    if !text.contains(BLOCK_START) {
+        return content.to_vec();
+    }
+    merge_properties(&text, "").into_bytes()
+}
+
+/// 内容有变化时备份并写入，返回是否写入
+fn write_if_changed(target: &SyncTarget, content: &str) -> Result<bool, String> {
+    let path = targets::expand_path(&target.path)?;
+    if fs::read_to_string(&path).is_ok_and(|current| current == content) {
+        return Ok(false);
+    }
+    targets::write_target(target, content.as_bytes()).map_err(|e| format!("无法写入 {}: {}", path.display(), e))?;
+    Ok(true)
+}
+
+/// 只删除由本程序生成的 init 脚本
+fn remove_script(target: &SyncTarget) -> Result<bool, String> {
+    let path = targets::expand_path(&target.path)?;
+    match fs::read_to_string(&path) {
+        Ok(content) if content.starts_with(HEADER) => {
+            fs::remove_file(&path).map_err(|e| format!("无法删除 {}: {}", path.display(), e))?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// 按环境中的 settings.xml 更新 Gradle 的 init 脚本和 gradle.properties，返回同步说明以及是否修改了文件
+pub fn sync(profile: &SyncProfile) -> Result<(String, bool), String> {
+    let settings = profile
+        .targets
+        .iter()
+        .find(|t| credentials::is_maven_settings(t))
+        .ok_or_else(|| "当前环境没有同步 settings.xml".to_string())?;
+    let path = targets::expand_path(&settings.path)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+    let mirrors = from_settings(&content)?;
+
+    let mut written = Vec::new();
+    let script = script_target(profile.gradle, settings.backup_count).ok_or_else(|| "未开启 Gradle 同步".to_string())?;
+    if write_if_changed(&script, &init_script(&mirrors, profile.gradle))? {
+        written.push(script.name.clone());
+    }
+    // 切换了脚本语言时删除另一种脚本，避免两个脚本同时生效
+    let other = match profile.gradle {
+        GradleOutput::Kotlin => GradleOutput::Groovy,
+        _ => GradleOutput::Kotlin,
+    };
+    let removed = match script_target(other, 0) {
+        Some(other) => remove_script(&other)?,
+        None => false,
+    };
+
+    let properties = properties_target(settings.backup_count);
+    let properties_path = targets::expand_path(&properties.path)?;
+    let existing = fs::read_to_string(&properties_path).unwrap_or_default();
+    if write_if_changed(&properties, &merge_properties(&existing, &properties_block(&mirrors)))? {
+        written.push(properties.name.clone());
+    }
+
+    let routed = mirrors.mirrors.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>().join("、");
+    let summary = if routed.is_empty() { "没有镜像".to_string() } else { format!("镜像 {}", routed) };
+    let changed = removed || !written.is_empty();
+    let summary = if written.is_empty() {
+        format!("已是最新（{}）", summary)
+    } else {
+        format!("已更新 {}（{}）", written.join("、"), summary)
+    };
+    Ok((summary, changed))
+}
+
+/// 关闭 Gradle 同步时删除生成的脚本和 gradle.properties 中的凭据
+pub fn remove_outputs() -> Result<(), String> {
+    for output in [GradleOutput::Groovy, GradleOutput::Kotlin] {
+        if let Some(target) = script_target(output, 0) {
+            remove_script(&target)?;
+        }
+    }
+    let properties = properties_target(1);
+    let path = targets::expand_path(&properties.path)?;
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let stripped = merge_properties(&existing, "");
+        if stripped != existing {
+            write_if_changed(&properties, &stripped)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &str = r#"<settings>
+  <mirrors>
+    <mirror>
+      <id>corp</id>
+      <url>https://nexus.example.com/maven-public/</url>
+      <mirrorOf>external:*,!snapshots</mirrorOf>
+    </mirror>
+    <mirror>
+      <id>unused</id>
+      <url>https://unused.example.com/</url>
+      <mirrorOf>nothing</mirrorOf>
+    </mirror>
+  </mirrors>
+  <servers>
+    <server>
+      <id>corp</id>
+      <username>dev</username>
+      <password>s3cret</password>
+    </server>
+  </servers>
+  <profiles>
+    <profile>
+      <id>corp</id>
+      <repositories>
+        <repository>
+          <id>snapshots</id>
+          <url>https://nexus.example.com/snapshots</url>
+        </repository>
+      </repositories>
+    </profile>
+  </profiles>
+  <activeProfiles>
+    <activeProfile>corp</activeProfile>
+  </activeProfiles>
+</settings>"#;
+
+    #[test]
+    fn routes_repositories_through_mirrors() {
+        let mirrors = from_settings(SETTINGS).unwrap();
+        assert_eq!(mirrors.mirrors, vec![("corp".to_string(), "https://nexus.example.com/maven-public".to_string())]);
+        assert!(mirrors
+            .routes
+            .contains(&("https://repo.maven.apache.org/maven2".to_string(), "corp".to_string())));
+        // 被 !snapshots 排除的仓库不重定向
+        assert!(!mirrors.routes.iter().any(|(url, _)| url.contains("snapshots")));
+        assert_eq!(mirrors.fallback.as_deref(), Some("corp"));
+        assert_eq!(mirrors.credentials, vec![("corp".to_string(), "dev".to_string(), "s3cret".to_string())]);
+    }
+
+    #[test]
+    fn writes_both_script_languages() {
+        let mirrors = from_settings(SETTINGS).unwrap();
+        let groovy = init_script(&mirrors, GradleOutput::Groovy);
+        assert!(groovy.starts_with(HEADER));
+        assert!(groovy.contains("    'corp': 'https://nexus.example.com/maven-public',\n"));
+        assert!(groovy.contains("def msettingsFallback = 'corp'\n"));
+        let kotlin = init_script(&mirrors, GradleOutput::Kotlin);
+        assert!(kotlin.contains("    \"corp\" to \"https://nexus.example.com/maven-public\",\n"));
+        assert!(kotlin.contains("val msettingsFallback: String? = \"corp\"\n"));
+
+        let empty = init_script(&GradleMirrors::default(), GradleOutput::Groovy);
+        assert!(empty.contains("def msettingsMirrors = [:]\n"));
+        assert!(empty.contains("def msettingsFallback = null\n"));
+    }
+
+    #[test]
+    fn replaces_only_the_managed_block() {
+        let mirrors = from_settings(SETTINGS).unwrap();
+        let block = properties_block(&mirrors);
+        let existing = "org.gradle.jvmargs=-Xmx2g";
+        let merged = merge_properties(existing, &block);
+        assert!(merged.starts_with("org.gradle.jvmargs=-Xmx2g\n# >>> msettings"));
+        assert!(merged.contains("msettings.corp.password=s3cret\n"));
+
+        let updated = merge_properties(&format!("{}org.gradle.caching=true\n", merged), &block.replace("s3cret", "new"));
+        assert!(updated.contains("msettings.corp.password=new\n# <<< msettings\norg.gradle.caching=true\n"));
+        assert_eq!(merge_properties(&merged, ""), "org.gradle.jvmargs=-Xmx2g\n");
+    }
+
+    #[test]
+    fn backups_drop_generated_credentials() {
+        let mirrors = from_settings(SETTINGS).unwrap();
+        let existing = "org.gradle.jvmargs=-Xmx2g\n";
+        let written = merge_properties(existing, &properties_block(&mirrors));
+        let backup = without_credentials(written.as_bytes());
+        assert_eq!(backup, existing.as_bytes());
+        assert!(!String::from_utf8_lossy(&backup).contains("s3cret"));
+        // 没有生成的一段时原样备份，包括非 UTF-8 内容
+        let latin1 = b"name=caf\xe9\n".to_vec();
+        assert_eq!(without_credentials(&latin1), latin1);
+    }
+
+    #[test]
+    fn escapes_property_values() {
+        assert_eq!(escape_property("a b=c", true), "a\\ b\\=c");
+        assert_eq!(escape_property(" p\\w", false), "\\ p\\\\w");
+        assert_eq!(escape_property("密", false), "\\u5bc6");
+    }
+}
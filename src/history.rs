@@ -0,0 +1,58 @@
+// history.rs
+// 执行历史的持久化，保存在状态目录的 history.json 中
+//
+// 记录在写入前遮盖密码、令牌等敏感信息，旧版本留下的记录在加载时补做一次。
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::{paths, permissions};
+use crate::redact::redact;
+
+/// 每个 `环境/目标` 最多保留的记录条数
+const MAX_RECORDS: usize = 200;
+
+pub type Histories = HashMap<String, Vec<String>>;
+
+pub fn load_histories() -> Histories {
+    let mut histories: Histories = match fs::read_to_string(paths::history_file()) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Failed to parse history file: {}", e);
+            Histories::new()
+        }),
+        Err(_) => Histories::new(),
+    };
+    for record in histories.values_mut().flatten() {
+        *record = redact(record);
+    }
+    histories
+}
+
+/// 追加一条记录，敏感信息先被遮盖
+pub fn push_record(histories: &mut Histories, key: String, record: &str) {
+    histories.entry(key).or_default().push(redact(record));
+}
+
+pub fn save_histories(histories: &mut Histories) {
+    for records in histories.values_mut() {
+        if records.len() > MAX_RECORDS {
+            records.drain(..records.len() - MAX_RECORDS);
+        }
+    }
+
+    let path = paths::history_file();
+    if let Some(parent) = path.parent() {
+        if let Err(e) = fs::create_dir_all(parent) {
+            log::warn!("Failed to create state directory: {}", e);
+            return;
+        }
+    }
+    match serde_json::to_string_pretty(histories) {
+        Ok(json) => {
+            if let Err(e) = permissions::write_private(&path, json) {
+                log::warn!("Failed to write history file: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Failed to serialize history: {}", e),
+    }
+}
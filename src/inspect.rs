@@ -0,0 +1,334 @@
+// inspect.rs
+// 解析本机 settings.xml 的生效内容：激活的 profile、镜像、服务器、代理和本地仓库，
+// 并按 Maven 的 mirrorOf 规则计算某个仓库会被路由到哪个镜像
+//
+// mirrorOf 规则（与 Maven 的 DefaultMirrorSelector 一致）：
+// 先找 mirrorOf 与仓库 id 完全相同的镜像，没有再按顺序找第一个匹配的镜像。
+// 匹配时逗号分隔的各项依次判断：`*` 匹配所有仓库，`external:*` 匹配非本机、非 file: 的仓库，
+// `external:http:*` 只匹配其中的 http 仓库，`!id` 排除该仓库（排除优先），其余按仓库 id 匹配。
+
+use std::fs;
+use std::path::Path;
+
+use crate::mavensec;
+
+/// Maven 内置的中央仓库
+pub const CENTRAL_ID: &str = "central";
+pub const CENTRAL_URL: &str = "https://repo.maven.apache.org/maven2";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mirror {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub mirror_of: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repository {
+    pub id: String,
+    pub url: String,
+    /// 是否为插件仓库
+    pub plugin: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub id: String,
+    pub active: bool,
+    pub repositories: Vec<Repository>,
+}
+
+/// 服务器凭据，只记录是否有密码，不保存密码本身
+#[derive(Debug, Clone, PartialEq)]
+pub struct Server {
+    pub id: String,
+    pub username: String,
+    pub password: SecretState,
+    pub private_key: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecretState {
+    None,
+    Encrypted,
+    Plaintext,
+}
+
+impl SecretState {
+    fn of(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            SecretState::None
+        } else if mavensec::is_encrypted(value) || value.starts_with("${") {
+            SecretState::Encrypted
+        } else {
+            SecretState::Plaintext
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SecretState::None => "无密码",
+            SecretState::Encrypted => "密码 ***（已加密）",
+            SecretState::Plaintext => "密码 ***（明文）",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proxy {
+    pub id: String,
+    pub active: bool,
+    pub protocol: String,
+    pub host: String,
+    pub port: String,
+    pub username: String,
+    pub non_proxy_hosts: String,
+}
+
+/// settings.xml 的生效内容
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EffectiveSettings {
+    pub local_repository: Option<String>,
+    pub offline: bool,
+    pub profiles: Vec<Profile>,
+    pub mirrors: Vec<Mirror>,
+    pub servers: Vec<Server>,
+    pub proxies: Vec<Proxy>,
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text(node: roxmltree::Node, name: &str) -> String {
+    child(node, name)
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn children<'a, 'input>(
+    node: Option<roxmltree::Node<'a, 'input>>,
+    name: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> + 'a {
+    node.into_iter()
+        .flat_map(|node| node.children())
+        .filter(move |n| n.has_tag_name(name))
+}
+
+fn repositories(profile: roxmltree::Node, list: &str, item: &str, plugin: bool) -> Vec<Repository> {
+    children(child(profile, list), item)
+        .map(|repository| Repository {
+            id: child_text(repository, "id"),
+            url: child_text(repository, "url"),
+            plugin,
+        })
+        .collect()
+}
+
+pub fn parse(content: &str) -> Result<EffectiveSettings, String> {
+    let doc = roxmltree::Document::parse(content).map_err(|e| format!("XML 格式错误: {}", e))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("settings") {
+        return Err(format!("根元素应为 <settings>，实际为 <{}>", root.tag_name().name()));
+    }
+
+    let listed: Vec<String> = children(child(root, "activeProfiles"), "activeProfile")
+        .filter_map(|n| n.text())
+        .map(|id| id.trim().to_string())
+        .collect();
+    let mut profiles: Vec<Profile> = children(child(root, "profiles"), "profile")
+        .map(|profile| {
+            let id = child_text(profile, "id");
+            let mut repos = repositories(profile, "repositories", "repository", false);
+            repos.extend(repositories(profile, "pluginRepositories", "pluginRepository", true));
+            let by_default = child(profile, "activation").is_some_and(|a| child_text(a, "activeByDefault") == "true");
+            Profile {
+                active: listed.contains(&id) || by_default,
+                id,
+                repositories: repos,
+            }
+        })
+        .collect();
+    // activeByDefault 的 profile 只在没有其他 profile 被显式激活时生效
+    if profiles.iter().any(|p| listed.contains(&p.id)) {
+        for profile in &mut profiles {
+            profile.active = listed.contains(&profile.id);
+        }
+    }
+
+    let local_repository = Some(child_text(root, "localRepository")).filter(|s| !s.is_empty());
+    Ok(EffectiveSettings {
+        local_repository,
+        offline: child_text(root, "offline") == "true",
+        profiles,
+        mirrors: children(child(root, "mirrors"), "mirror")
+            .map(|mirror| Mirror {
+                id: child_text(mirror, "id"),
+                name: child_text(mirror, "name"),
+                url: child_text(mirror, "url"),
+                mirror_of: child_text(mirror, "mirrorOf"),
+            })
+            .collect(),
+        servers: children(child(root, "servers"), "server")
+            .map(|server| Server {
+                id: child_text(server, "id"),
+                username: child_text(server, "username"),
+                password: SecretState::of(&child_text(server, "password")),
+                private_key: !child_text(server, "privateKey").is_empty(),
+            })
+            .collect(),
+        proxies: children(child(root, "proxies"), "proxy")
+            .map(|proxy| Proxy {
+                id: child_text(proxy, "id"),
+                // 未写 <active> 时默认生效
+                active: child_text(proxy, "active") != "false",
+                protocol: child_text(proxy, "protocol"),
+                host: child_text(proxy, "host"),
+                port: child_text(proxy, "port"),
+                username: child_text(proxy, "username"),
+                non_proxy_hosts: child_text(proxy, "nonProxyHosts"),
+            })
+            .collect(),
+    })
+}
+
+/// 读取并解析 settings.xml
+pub fn load(path: &Path) -> Result<EffectiveSettings, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+    parse(&content)
+}
+
+/// 仓库是否在本机之外：不是 file: 协议，也不是 localhost/127.0.0.1
+fn is_external(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    url.scheme() != "file" && !matches!(url.host_str(), Some("localhost" | "127.0.0.1"))
+}
+
+fn is_external_http(url: &str) -> bool {
+    is_external(url) && url.starts_with("http:")
+}
+
+/// 某个 mirrorOf 是否匹配仓库
+pub fn matches_pattern(mirror_of: &str, repo_id: &str, repo_url: &str) -> bool {
+    let mirror_of = mirror_of.trim();
+    if mirror_of == "*" || mirror_of == repo_id {
+        return true;
+    }
+    let mut result = false;
+    for pattern in mirror_of.split(',').map(str::trim) {
+        if let Some(excluded) = pattern.strip_prefix('!').filter(|id| !id.is_empty()) {
+            if excluded == repo_id {
+                return false;
+            }
+        } else if pattern == repo_id {
+            return true;
+        } else if pattern == "external:http:*" {
+            // 不提前返回，后面可能还有排除项
+            result |= is_external_http(repo_url);
+        } else if pattern == "external:*" {
+            result |= is_external(repo_url);
+        } else if pattern == "*" {
+            result = true;
+        }
+    }
+    result
+}
+
+/// 仓库会被路由到的镜像，没有匹配的镜像时直接访问仓库本身
+pub fn resolve<'a>(mirrors: &'a [Mirror], repo_id: &str, repo_url: &str) -> Option<&'a Mirror> {
+    mirrors
+        .iter()
+        .find(|m| m.mirror_of.trim() == repo_id)
+        .or_else(|| mirrors.iter().find(|m| matches_pattern(&m.mirror_of, repo_id, repo_url)))
+}
+
+impl EffectiveSettings {
+    /// 生效的仓库：中央仓库加上已激活 profile 中的仓库（同 id 只保留第一个）
+    pub fn effective_repositories(&self) -> Vec<Repository> {
+        let mut repos = vec![Repository {
+            id: CENTRAL_ID.to_string(),
+            url: CENTRAL_URL.to_string(),
+            plugin: false,
+        }];
+        for repo in self.profiles.iter().filter(|p| p.active).flat_map(|p| &p.repositories) {
+            if !repos.iter().any(|r| r.id == repo.id && r.plugin == repo.plugin) {
+                repos.push(repo.clone());
+            }
+        }
+        repos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror(id: &str, mirror_of: &str) -> Mirror {
+        Mirror {
+            id: id.to_string(),
+            name: String::new(),
+            url: format!("https://{}.example.com/", id),
+            mirror_of: mirror_of.to_string(),
+        }
+    }
+
+    #[test]
+    fn exact_id_wins_over_earlier_wildcard() {
+        let mirrors = [mirror("all", "*"), mirror("corp", "corp-releases")];
+        assert_eq!(resolve(&mirrors, "corp-releases", "https://r/").unwrap().id, "corp");
+        assert_eq!(resolve(&mirrors, "central", CENTRAL_URL).unwrap().id, "all");
+    }
+
+    #[test]
+    fn exclusions_and_external_patterns() {
+        let mirrors = [mirror("ext", "external:*,!snapshots"), mirror("http", "external:http:*")];
+        assert_eq!(resolve(&mirrors, "central", CENTRAL_URL).unwrap().id, "ext");
+        assert!(resolve(&mirrors, "snapshots", "https://r.example.com/").is_none());
+        assert!(resolve(&mirrors, "local", "http://localhost:8081/repo").is_none());
+        assert!(resolve(&mirrors, "files", "file:///tmp/repo").is_none());
+        assert!(matches_pattern("external:http:*", "old", "http://old.example.com/"));
+        assert!(!matches_pattern("external:http:*", "new", "https://new.example.com/"));
+    }
+
+    #[test]
+    fn exclusion_after_wildcard_still_applies() {
+        assert!(!matches_pattern("*,!central", "central", CENTRAL_URL));
+        assert!(matches_pattern("*,!central", "other", "https://o/"));
+        assert!(matches_pattern("a, b", "b", "https://o/"));
+    }
+
+    #[test]
+    fn parses_settings_and_masks_secrets() {
+        let xml = r#"<settings>
+  <localRepository>/data/m2</localRepository>
+  <servers>
+    <server><id>nexus</id><username>dev</username><password>{abc=}</password></server>
+    <server><id>plain</id><password>hunter2</password></server>
+  </servers>
+  <proxies><proxy><id>p</id><host>proxy</host><port>8080</port></proxy></proxies>
+  <mirrors><mirror><id>corp</id><mirrorOf>*</mirrorOf><url>https://m/</url></mirror></mirrors>
+  <profiles>
+    <profile><id>default</id><activation><activeByDefault>true</activeByDefault></activation></profile>
+    <profile><id>corp</id><repositories><repository><id>snap</id><url>https://s/</url></repository></repositories></profile>
+  </profiles>
+  <activeProfiles><activeProfile>corp</activeProfile></activeProfiles>
+</settings>"#;
+        let settings = parse(xml).unwrap();
+        assert_eq!(settings.local_repository.as_deref(), Some("/data/m2"));
+        assert_eq!(settings.servers[0].password, SecretState::Encrypted);
+        assert_eq!(settings.servers[1].password, SecretState::Plaintext);
+        assert!(settings.proxies[0].active);
+        // 显式激活 corp 后 activeByDefault 的 profile 不再生效
+        let active: Vec<&str> = settings.profiles.iter().filter(|p| p.active).map(|p| p.id.as_str()).collect();
+        assert_eq!(active, vec!["corp"]);
+        let repos = settings.effective_repositories();
+        let ids: Vec<&str> = repos.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["central", "snap"]);
+    }
+}
@@ -0,0 +1,318 @@
+// ipc.rs
+// 第二次启动时将命令行参数转发给已运行的实例，并把执行结果带回调用方。
+//
+// 沿用显示窗口信号文件的做法：调用方在状态目录的 commands 下写入 `<id>.json`，
+// 运行中的实例由后台线程发现新命令后唤醒界面，在界面刷新时取走并执行，完成后写入 `<id>.response`，
+// 调用方轮询该文件并打印结果。
+
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{overrides, paths, redact, share};
+
+/// 等待运行中实例返回结果的最长时间（下载本身有 30 秒超时）
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(90);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+pub const USAGE: &str = "用法: AutoUpdateMavenSettings [选项]
+
+选项:
+  --update-now             立即从下载地址更新 settings.xml
+  --url <地址>             使用指定的 settings.xml 下载地址
+  --interval <小时>        使用指定的自动更新间隔（1-168）
+  --target <路径>          使用指定的目标路径（第一个同步文件）
+  --manifest-url <地址>    使用指定的中央清单地址
+  --import <文件或链接>    导入他人分享的配置（文件或 msettings:// 链接），确认后应用
+  -h, --help               显示此帮助信息
+
+--url、--interval、--target、--manifest-url 只在本次运行中生效，不会写入配置文件，
+也可以用环境变量 MSETTINGS_URL、MSETTINGS_INTERVAL、MSETTINGS_TARGET、
+MSETTINGS_MANIFEST_URL 设置，命令行参数优先。
+
+如果已有实例在运行，命令会转发给该实例执行，并在此处输出结果。";
+
+/// 命令行可以请求的操作
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CliCommand {
+    pub update_now: bool,
+    pub url: Option<String>,
+    pub interval_hours: Option<u64>,
+    pub target: Option<String>,
+    pub manifest_url: Option<String>,
+    /// 要导入的配置文件（绝对路径）或 msettings:// 链接
+    pub import: Option<String>,
+}
+
+impl CliCommand {
+    pub fn is_empty(&self) -> bool {
+        !self.update_now && !self.has_overrides() && self.import.is_none()
+    }
+
+    /// 是否带有覆盖配置的参数
+    pub fn has_overrides(&self) -> bool {
+        self.url.is_some() || self.interval_hours.is_some() || self.target.is_some() || self.manifest_url.is_some()
+    }
+}
+
+/// 命令行解析结果
+pub enum CliAction {
+    Run(CliCommand),
+    Help,
+}
+
+/// 解析命令行参数（不含程序名）
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<CliAction, String> {
+    let mut command = CliCommand::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // 同时支持 `--url <地址>` 和 `--url=<地址>`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} 需要一个参数", flag))
+        };
+
+        match flag.as_str() {
+            "--update-now" => command.update_now = true,
+            "--url" => command.url = Some(overrides::parse_url(&value()?)?),
+            "--interval" => command.interval_hours = Some(overrides::parse_interval(&value()?)?),
+            "--target" => command.target = Some(overrides::parse_target(&value()?)?),
+            "--manifest-url" => command.manifest_url = Some(overrides::parse_manifest_url(&value()?)?),
+            "--import" => command.import = Some(import_source(&value()?)?),
+            "-h" | "--help" => return Ok(CliAction::Help),
+            // 旧版 macOS 从 Finder 启动时会附带进程序列号参数
+            other if other.starts_with("-psn_") => {}
+            // 系统打开 msettings:// 链接时把链接作为唯一参数传入
+            other if share::is_link(other) => command.import = Some(other.to_string()),
+            other => return Err(format!("未知参数: {}", other)),
+        }
+    }
+
+    if command.import.is_some() && (command.update_now || command.has_overrides()) {
+        return Err("--import 不能与其他参数同时使用".to_string());
+    }
+
+    Ok(CliAction::Run(command))
+}
+
+// 文件路径转为绝对路径，命令可能由工作目录不同的运行中实例执行
+fn import_source(value: &str) -> Result<String, String> {
+    if share::is_link(value) {
+        return Ok(value.to_string());
+    }
+    std::path::absolute(value)
+        .map(|path| path.display().to_string())
+        .map_err(|e| format!("无效的文件路径 {}: {}", value, e))
+}
+
+/// 实例对转发命令的回复
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CommandRequest {
+    id: String,
+    command: CliCommand,
+}
+
+/// 运行中实例收到的一条命令，回复后调用方即可退出
+pub struct ForwardedCommand {
+    pub command: CliCommand,
+    id: Option<String>,
+}
+
+impl ForwardedCommand {
+    /// 本进程启动参数产生的命令，无需回复
+    pub fn local(command: CliCommand) -> Self {
+        Self { command, id: None }
+    }
+
+    pub fn reply(&self, success: bool, message: &str) {
+        let Some(id) = &self.id else {
+            return;
+        };
+        let response = CommandResponse {
+            success,
+            message: redact::redact(message),
+        };
+        let json = match serde_json::to_string(&response) {
+            Ok(json) => json,
+            Err(e) => {
+                log::warn!("Failed to serialize command response: {}", e);
+                return;
+            }
+        };
+        // 先写临时文件再改名，避免调用方读到半截内容
+        let final_path = paths::commands_dir().join(format!("{}.response", id));
+        let tmp_path = final_path.with_extension("response.tmp");
+        if let Err(e) = fs::write(&tmp_path, json).and_then(|_| fs::rename(&tmp_path, &final_path)) {
+            log::warn!("Failed to write command response: {}", e);
+        }
+    }
+}
+
+/// 将命令交给运行中的实例执行并等待结果
+pub fn forward_to_running_instance(command: &CliCommand) -> Result<CommandResponse, String> {
+    let dir = paths::commands_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("无法创建命令目录: {}", e))?;
+
+    let id = format!(
+        "{}-{}",
+        std::process::id(),
+        chrono::Local::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+    let request = CommandRequest {
+        id: id.clone(),
+        command: command.clone(),
+    };
+    let json = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+
+    // 与回复相同，先写临时文件再改名，运行中的实例只会看到完整的请求
+    let request_path = dir.join(format!("{}.json", id));
+    let tmp_path = dir.join(format!("{}.json.tmp", id));
+    fs::write(&tmp_path, json)
+        .and_then(|_| fs::rename(&tmp_path, &request_path))
+        .map_err(|e| format!("无法写入命令文件: {}", e))?;
+
+    let response_path = dir.join(format!("{}.response", id));
+    let started = Instant::now();
+    while started.elapsed() < RESPONSE_TIMEOUT {
+        if let Ok(content) = fs::read_to_string(&response_path) {
+            let _ = fs::remove_file(&response_path);
+            return serde_json::from_str(&content).map_err(|e| format!("无法解析实例回复: {}", e));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    // 超时后撤回尚未被处理的请求
+    let _ = fs::remove_file(&request_path);
+    Err("等待运行中的实例响应超时".to_string())
+}
+
+/// 取走所有待处理的转发命令
+pub fn take_forwarded_commands() -> Vec<ForwardedCommand> {
+    let dir = paths::commands_dir();
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut commands = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let content = fs::read_to_string(&path);
+        let _ = fs::remove_file(&path);
+        match content.map_err(|e| e.to_string()).and_then(|c| {
+            serde_json::from_str::<CommandRequest>(&c).map_err(|e| e.to_string())
+        }) {
+            Ok(request) => commands.push(ForwardedCommand {
+                command: request.command,
+                id: Some(request.id),
+            }),
+            Err(e) => log::warn!("Ignoring invalid command file {}: {}", path.display(), e),
+        }
+    }
+    commands
+}
+
+/// 后台轮询命令目录，有待处理的命令时唤醒界面
+///
+/// 窗口可见且空闲时界面不会自行刷新，只在刷新时取命令会让调用方一直等到超时。
+pub fn wake_on_commands(ctx: egui::Context) {
+    thread::spawn(move || loop {
+        if has_pending_commands() {
+            ctx.request_repaint();
+        }
+        thread::sleep(POLL_INTERVAL);
+    });
+}
+
+fn has_pending_commands() -> bool {
+    fs::read_dir(paths::commands_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.path().extension().and_then(|ext| ext.to_str()) == Some("json"))
+        })
+        .unwrap_or(false)
+}
+
+/// Windows 发布版没有控制台，从命令行调用时附加到父进程的控制台以便输出结果
+pub fn attach_parent_console() {
+    #[cfg(target_os = "windows")]
+    unsafe {
+        winapi::um::wincon::AttachConsole(winapi::um::wincon::ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliCommand, String> {
+        match parse_args(args.iter().map(|arg| arg.to_string()))? {
+            CliAction::Run(command) => Ok(command),
+            CliAction::Help => Err("help".to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_flags_in_both_forms() {
+        let command = parse(&[
+            "--update-now",
+            "--url",
+            "https://repo.example.com/settings.xml",
+            "--interval=6",
+            "--manifest-url=",
+        ])
+        .unwrap();
+        assert!(command.update_now);
+        assert_eq!(command.url.as_deref(), Some("https://repo.example.com/settings.xml"));
+        assert_eq!(command.interval_hours, Some(6));
+        assert_eq!(command.manifest_url.as_deref(), Some(""));
+        assert!(command.has_overrides());
+
+        assert!(parse(&[]).unwrap().is_empty());
+        assert_eq!(parse(&["--help"]).unwrap_err(), "help");
+        assert!(parse(&["--interval", "0"]).is_err());
+        assert!(parse(&["--url"]).unwrap_err().contains("需要一个参数"));
+        assert!(parse(&["--url", "ftp://repo.example.com"]).is_err());
+        assert!(parse(&["--verbose"]).unwrap_err().contains("未知参数"));
+    }
+
+    #[test]
+    fn import_cannot_be_combined() {
+        let link = "msettings://import?config=abc";
+        assert_eq!(parse(&["--import", link]).unwrap().import.as_deref(), Some(link));
+        assert!(parse(&["--import", link, "--update-now"]).is_err());
+        assert!(parse(&["--interval", "4", "--import", link]).is_err());
+        // 文件路径转为绝对路径
+        let import = parse(&["--import=shared.json"]).unwrap().import.unwrap();
+        assert!(std::path::Path::new(&import).is_absolute());
+    }
+
+    #[test]
+    fn accepts_bare_link_and_ignores_psn() {
+        let link = "msettings://import?config=abc";
+        let command = parse(&["-psn_0_12345", link]).unwrap();
+        assert_eq!(command.import.as_deref(), Some(link));
+        assert!(!command.update_now && !command.has_overrides());
+        assert!(parse(&["-psn_0_12345"]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+// logging.rs
+// 运行日志：`log` 宏的实现，发布版本没有控制台，诊断信息写入状态目录下的日志文件
+//
+// 每条日志为一行 JSON（`ts`、`level`、`target`、`msg`），写入前脱敏。
+// 日志文件超过 1 MiB 时轮换，保留 msettings.log.1 ~ .3；最近的日志同时保存在内存中供界面查看。
+// 级别由 RUST_LOG 控制，写法与 env_logger 相同（如 `debug`、`warn,AutoUpdateMavenSettings=debug`），
+// 未设置时本程序记录 info 及以上，依赖库只记录 warn 及以上。
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+
+use crate::{paths, permissions, redact};
+
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const ROTATED_FILES: usize = 3;
+/// 内存中保留的日志条数
+const RECENT_ENTRIES: usize = 2000;
+
+const CRATE_NAME: &str = env!("CARGO_CRATE_NAME");
+
+/// 一条日志
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// 本地时间，精确到毫秒
+    pub ts: String,
+    #[serde(with = "level_name")]
+    pub level: Level,
+    pub target: String,
+    pub msg: String,
+}
+
+mod level_name {
+    use log::Level;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(level: &Level, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(level.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Level, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Entry {
+    /// 复制诊断信息和控制台输出时使用的单行文本
+    pub fn line(&self) -> String {
+        format!("{} {:<5} {}: {}", self.ts, self.level, self.target, self.msg)
+    }
+}
+
+/// RUST_LOG 解析出的级别规则：(模块前缀, 级别)，前缀为 None 时对所有模块生效
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    directives: Vec<(Option<String>, LevelFilter)>,
+}
+
+impl Filter {
+    fn default_filter() -> Self {
+        Self {
+            directives: vec![(None, LevelFilter::Warn), (Some(CRATE_NAME.to_string()), LevelFilter::Info)],
+        }
+    }
+
+    /// 解析 RUST_LOG，无法识别的规则被忽略，全部无效时使用默认规则
+    fn parse(spec: &str) -> Self {
+        let mut directives = Vec::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parsed = match directive.split_once('=') {
+                Some((target, level)) => level.trim().parse().ok().map(|level| (Some(target.trim().to_string()), level)),
+                // 只写级别时对所有模块生效，只写模块名时该模块记录全部级别
+                None => match directive.parse() {
+                    Ok(level) => Some((None, level)),
+                    Err(_) => Some((Some(directive.to_string()), LevelFilter::Trace)),
+                },
+            };
+            directives.extend(parsed);
+        }
+        if directives.is_empty() {
+            Self::default_filter()
+        } else {
+            Self { directives }
+        }
+    }
+
+    /// 模块前缀最长的规则生效
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let matches = |prefix: &str| {
+            target == prefix || target.strip_prefix(prefix).is_some_and(|rest| rest.starts_with("::"))
+        };
+        self.directives
+            .iter()
+            .filter(|(prefix, _)| match prefix {
+                Some(prefix) => matches(prefix),
+                None => true,
+            })
+            .max_by_key(|(prefix, _)| prefix.as_ref().map_or(0, |p| p.len() + 1))
+            .map(|(_, level)| *level)
+            .unwrap_or(LevelFilter::Off)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.directives.iter().map(|(_, level)| *level).max().unwrap_or(LevelFilter::Off)
+    }
+}
+
+/// 按大小轮换的日志文件
+struct RotatingFile {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf) -> Self {
+        Self { path, file: None, size: 0 }
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut options = fs::OpenOptions::new();
+            options.create(true).append(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let file = options.open(&self.path)?;
+            self.size = file.metadata()?.len();
+            if self.size == 0 {
+                permissions::restrict(&self.path)?;
+            }
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("log file opened"))
+    }
+
+    /// msettings.log → .1 → .2 → .3，最旧的被覆盖
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        for i in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, i);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, i + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        self.open()?;
+        if self.size > 0 && self.size + len > MAX_LOG_BYTES {
+            self.rotate()?;
+            self.open()?;
+        }
+        let file = self.open()?;
+        writeln!(file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+struct Logger {
+    filter: Filter,
+    file: Mutex<RotatingFile>,
+    recent: Mutex<VecDeque<Entry>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let entry = Entry {
+            ts: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            level: record.level(),
+            target: record.target().to_string(),
+            msg: redact::redact(&record.args().to_string()),
+        };
+        eprintln!("{}", entry.line());
+        if let Ok(json) = serde_json::to_string(&entry) {
+            if let Ok(mut file) = self.file.lock() {
+                // 写日志失败时无处可报，只能输出到控制台
+                if let Err(e) = file.write_line(&json) {
+                    eprintln!("Warning: Failed to write log file {}: {}", file.path.display(), e);
+                }
+            }
+        }
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == RECENT_ENTRIES {
+                recent.pop_front();
+            }
+            recent.push_back(entry);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.file.as_mut() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+pub fn log_file() -> PathBuf {
+    paths::log_dir().join("msettings.log")
+}
+
+/// 读取日志文件末尾的记录，让界面也能看到本次启动之前的日志
+fn load_recent(path: &Path) -> VecDeque<Entry> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let mut recent: VecDeque<Entry> = content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    while recent.len() > RECENT_ENTRIES {
+        recent.pop_front();
+    }
+    recent
+}
+
+/// 安装日志实现，只在启动时调用一次
+pub fn init() {
+    let filter = std::env::var("RUST_LOG")
+        .map(|spec| Filter::parse(&spec))
+        .unwrap_or_else(|_| Filter::default_filter());
+    let max_level = filter.max_level();
+    let path = log_file();
+    let logger = LOGGER.get_or_init(|| Logger {
+        filter,
+        recent: Mutex::new(load_recent(&path)),
+        file: Mutex::new(RotatingFile::new(path)),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// 内存中最近的日志，按时间顺序
+pub fn recent() -> Vec<Entry> {
+    LOGGER
+        .get()
+        .and_then(|logger| logger.recent.lock().ok().map(|recent| recent.iter().cloned().collect()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rust_log_like_env_logger() {
+        let filter = Filter::parse("warn, AutoUpdateMavenSettings=debug ,reqwest::connect=trace");
+        assert_eq!(filter.level_for("hyper::client"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("AutoUpdateMavenSettings::sync"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("reqwest::connect"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("reqwest"), LevelFilter::Warn);
+        // 前缀只按模块边界匹配
+        assert_eq!(Filter::parse("reqwest=off").level_for("reqwest_extra"), LevelFilter::Off);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+
+        let default = Filter::parse("");
+        assert_eq!(default, Filter::default_filter());
+        assert_eq!(default.level_for(CRATE_NAME), LevelFilter::Info);
+        assert_eq!(default.level_for("eframe::native"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn rotates_by_size_and_keeps_limited_files() {
+        let dir = std::env::temp_dir().join(format!("msettings-log-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("msettings.log");
+        let mut file = RotatingFile::new(path.clone());
+        let line = "x".repeat(MAX_LOG_BYTES as usize / 2);
+        for _ in 0..10 {
+            file.write_line(&line).unwrap();
+        }
+        assert!(fs::metadata(&path).unwrap().len() <= MAX_LOG_BYTES);
+        assert!(rotated_path(&path, ROTATED_FILES).exists());
+        assert!(!rotated_path(&path, ROTATED_FILES + 1).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn entries_round_trip_as_json_lines() {
+        let entry = Entry {
+            ts: "2026-10-18 09:30:00.125".to_string(),
+            level: Level::Warn,
+            target: "AutoUpdateMavenSettings::sync".to_string(),
+            msg: "Failed to cache download settings.xml".to_string(),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains("\"level\":\"WARN\""));
+        let dir = std::env::temp_dir().join(format!("msettings-log-load-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("msettings.log");
+        fs::write(&path, format!("{}\nnot json\n{}\n", json, json)).unwrap();
+        assert_eq!(load_recent(&path), VecDeque::from(vec![entry.clone(), entry]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -5,118 +5,59 @@
 #[link(name = "AppKit", kind = "framework")]
 extern "C" {}
 
+mod allowlist;
+mod config;
+mod credentials;
+mod gradle;
+mod history;
+mod inspect;
+mod ipc;
+mod logging;
+mod manifest;
+mod mavensec;
+mod metrics;
+mod notifications;
+mod overrides;
+mod paths;
+mod permissions;
+mod policy;
+mod probe;
+mod redact;
+mod share;
+mod statusserver;
+mod sync;
+mod targets;
+mod toolchains;
+mod transform;
+mod watch;
+mod webhooks;
+mod xmledit;
+
 use auto_launch::AutoLaunch;
 use eframe::egui::{self, Color32, Stroke, Rounding};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use serde::{Deserialize, Serialize};
+use config::{AppConfig, Enforcement, GradleOutput, MirrorCheck, SourceAuth, SyncProfile, SyncTarget};
 use single_instance::SingleInstance;
 
-// 配置结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct AppConfig {
-    url: String,
-    interval_hours: u64,
-    enable_scheduler: bool,
-    auto_launch_enabled: bool,
-    minimize_to_background: bool,
-}
-
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            url: "https://devcloud.greenvoltis.com/maven/settings.xml".to_string(),
-            interval_hours: 10,
-            enable_scheduler: false,
-            auto_launch_enabled: false,
-            minimize_to_background: true,
-        }
-    }
-}
-
-// 顶部添加辅助函数
-fn get_config_file_path() -> PathBuf {
-    #[cfg(target_os = "windows")]
-    let base = std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string());
-    #[cfg(not(target_os = "windows"))]
-    let base = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(base).join(".msettings_config.json")
-}
-
-fn load_config() -> AppConfig {
-    let path = get_config_file_path();
-    match fs::read_to_string(&path) {
-        Ok(content) => {
-            match serde_json::from_str::<AppConfig>(&content) {
-                Ok(config) => config,
-                Err(e) => {
-                    eprintln!("Warning: Failed to parse config file: {}, using defaults", e);
-                    AppConfig::default()
-                }
-            }
-        }
-        Err(_) => {
-            // 配置文件不存在或无法读取，使用默认配置
-            AppConfig::default()
-        }
-    }
-}
-
-fn save_config(config: &AppConfig) {
-    let path = get_config_file_path();
-    if let Some(parent) = path.parent() {
-        if let Err(e) = fs::create_dir_all(parent) {
-            eprintln!("Warning: Failed to create config directory: {}", e);
-            return;
-        }
-    }
-    match serde_json::to_string_pretty(config) {
-        Ok(json) => {
-            match fs::File::create(&path) {
-                Ok(mut file) => {
-                    if let Err(e) = file.write_all(json.as_bytes()) {
-                        eprintln!("Warning: Failed to write config file: {}", e);
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Warning: Failed to create config file: {}", e);
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Warning: Failed to serialize config: {}", e);
-        }
-    }
-}
-
 // 创建AutoLaunch实例的辅助函数
 fn create_auto_launch(app_name: &str, exe_path: &str) -> Result<AutoLaunch, Box<dyn std::error::Error>> {
-    #[cfg(target_os = "windows")]
+    // 只有 macOS 版本多一个 use_launch_agent 参数
+    #[cfg(not(target_os = "macos"))]
     let auto_launch = AutoLaunch::new(app_name, exe_path, &[] as &[&str]);
     
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(target_os = "macos")]
     let auto_launch = AutoLaunch::new(app_name, exe_path, false, &[] as &[&str]);
     
     Ok(auto_launch)
 }
 
-// 保持向后兼容的函数
-#[allow(dead_code)]
-fn load_saved_url() -> Option<String> {
-    Some(load_config().url)
-}
-
-fn save_url_to_config(url: &str) {
-    let mut config = load_config();
-    config.url = url.to_string();
-    save_config(&config);
-}
-
 fn setup_custom_fonts(ctx: &egui::Context) {
     use eframe::egui::{FontData, FontDefinitions, FontFamily};
     use std::path::Path;
@@ -142,7 +83,7 @@ fn setup_custom_fonts(ctx: &egui::Context) {
                     FontData::from_owned(font_data),
                 );
                 font_loaded = true;
-                println!("✅ 成功加载字体: {}", font_path);
+                log::info!("Loaded font {}", font_path);
                 break;
             }
         }
@@ -160,7 +101,7 @@ fn setup_custom_fonts(ctx: &egui::Context) {
             .unwrap()
             .insert(0, "chinese_font".to_string());
     } else {
-        println!("⚠️ 无法加载嵌入字体，尝试系统字体");
+        log::warn!("Failed to load bundled fonts, trying system fonts");
 
         #[cfg(target_os = "macos")]
         let fallback_font = "PingFang SC";
@@ -186,27 +127,45 @@ fn setup_custom_fonts(ctx: &egui::Context) {
     ctx.set_fonts(fonts);
 }
 
-#[cfg(target_os = "windows")]
-fn get_m2_settings_path() -> PathBuf {
-    let home = std::env::var("USERPROFILE").unwrap();
-    PathBuf::from(home).join(".m2").join("settings.xml")
-}
-
-#[cfg(not(target_os = "windows"))]
-fn get_m2_settings_path() -> PathBuf {
-    let home = std::env::var("HOME").unwrap();
-    PathBuf::from(home).join(".m2").join("settings.xml")
-}
-
 struct AppState {
-    url: String,
-    previous_url: String,  // 新增
-    interval_hours: u64,
-    previous_interval_hours: u64,  // 新增
+    // 同步环境，界面直接编辑当前环境
+    profiles: Vec<SyncProfile>,
+    active_profile: usize,
+    saved_profiles: Vec<SyncProfile>,  // 上次保存时的内容，用于检测修改
+    edited_profiles: Vec<SyncProfile>,  // 最近一次修改后的内容，用于判断是否仍在编辑
+    profiles_edited_at: Instant,  // 最近一次修改的时间
+    history_target: usize,  // 历史记录中查看的目标文件
+    config_warning: Option<String>,  // 配置文件被修复时的提示
+    persisted_config: AppConfig,  // 用户配置文件中的内容，不含策略锁定的值
+    overrides: overrides::Overrides,  // 环境变量和命令行参数的临时覆盖，不会保存
+    show_diagnostics: bool,
+    // 配置导入/导出
+    import_source: String,
+    export_include_secrets: bool,
+    pending_import: Option<share::PendingImport>,
+    // 服务器凭据输入（密码仅在内存中，添加时立即加密）
+    credential_input: config::ServerCredential,
+    host_input: String,  // 正在输入的允许主机
+    // settings.xml 本地调整
+    keep_input: String,  // 正在输入的保留本机元素
+    transform_input: config::Transform,
+    transform_preview: Option<Result<(String, Vec<String>), String>>,  // (diff, 执行说明)
+    detected_jdks: Option<Vec<toolchains::Jdk>>,  // 最近一次检测到的本机 JDK
+    has_master_password: bool,
+    // 配置文件和目标文件的外部修改
+    watcher: Option<watch::FileWatcher>,
+    locally_modified: Vec<String>,  // 当前环境中被本地修改的目标 id
+    drift_diff: Option<(String, String)>,  // 正在查看的差异：(目标名称, diff)
+    // settings.xml 生效内容查看器
+    inspected: Option<(PathBuf, Result<inspect::EffectiveSettings, String>)>,
+    route_repo_id: String,
+    route_repo_url: String,
     status: String,
     running: bool,
     enable_scheduler: bool,
     history: Vec<String>,
+    right_tab: RightTab,
+    log_level: log::LevelFilter,  // 日志查看器显示的最低级别
     shared_state: Arc<Mutex<SharedState>>,
     auto_launch_enabled: bool,
     auto_launch: AutoLaunch,
@@ -218,15 +177,120 @@ struct AppState {
     // 窗口显示控制
     show_window: bool,
     minimize_to_background: bool,
+    notifications: config::Notifications,  // 各事件的桌面通知开关
+    webhooks: Vec<config::Webhook>,
+    webhook_input: config::Webhook,  // 正在添加的 Webhook
+    status_endpoint: config::StatusEndpoint,
+    status_server: Option<statusserver::StatusServer>,
     // 重新显示窗口的机制
     should_show_window: Arc<Mutex<bool>>,
+    // 本次启动命令行参数对应的命令，首帧执行
+    startup_commands: Vec<ipc::ForwardedCommand>,
 }
 
 struct SharedState {
     enable_scheduler: bool,
-    url: String,
-    interval_hours: u64,
-    history: Vec<String>,
+    profile: SyncProfile,
+    // 按 `环境/目标` 分开的执行历史，见 sync::history_key
+    histories: history::Histories,
+    // 清单给出的目标列表和推荐间隔，按环境 id 暂存，由界面线程写回配置
+    manifest_updates: HashMap<String, (Vec<SyncTarget>, Option<u64>)>,
+    // 最近一次同步 settings.xml 时的镜像检查结果，键与历史记录相同
+    probe_reports: HashMap<String, Vec<probe::ProbeResult>>,
+    notifications: config::Notifications,
+    webhooks: Vec<config::Webhook>,
+    // 各环境连续失败的次数，成功后清零
+    failure_streaks: HashMap<String, u32>,
+    // 本机状态接口的数据：同步统计和界面状态卡片的内容
+    metrics: metrics::Metrics,
+    ui_status: metrics::UiStatus,
+    // 定时任务线程计算的下次更新时间
+    next_run: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl SharedState {
+    /// 把一次同步的结果写入各目标的历史记录，返回是否全部成功以及汇总文本
+    fn record_run(&mut self, profile_id: &str, run: sync::SyncRun, action: &str) -> (bool, String) {
+        self.metrics.record(profile_id, action, &run);
+        if let Some(targets) = run.manifest_targets {
+            self.manifest_updates
+                .insert(profile_id.to_string(), (targets, run.recommended_interval_hours));
+        }
+
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let mut success = true;
+        let mut summary = Vec::new();
+        let mut errors = Vec::new();
+        let mut changed = Vec::new();
+        for outcome in run.outcomes {
+            let key = sync::history_key(profile_id, &outcome.target_id);
+            if !outcome.probes.is_empty() {
+                self.probe_reports.insert(key.clone(), outcome.probes);
+            }
+            if outcome.changed && outcome.result.is_ok() {
+                changed.push(outcome.target_name.clone());
+            }
+            let record = match outcome.result {
+                Ok(detail) if detail.is_empty() => format!("{}: {}成功", now, action),
+                Ok(detail) => format!("{}: {}成功 - {}", now, action, detail),
+                Err(e) => {
+                    success = false;
+                    errors.push(format!("[{}] {}", outcome.target_name, e));
+                    format!("{}: {}失败 - {}", now, action, e)
+                }
+            };
+            summary.push(format!("[{}] {}", outcome.target_name, record));
+            history::push_record(&mut self.histories, key, &record);
+        }
+        history::save_histories(&mut self.histories);
+
+        // 窗口多数时间隐藏在后台，失败和内容变化用桌面通知提示
+        let failures = if success {
+            self.failure_streaks.remove(profile_id);
+            0
+        } else {
+            let streak = self.failure_streaks.entry(profile_id.to_string()).or_insert(0);
+            *streak += 1;
+            *streak
+        };
+        let profile_name = if self.profile.id == profile_id { self.profile.name.clone() } else { profile_id.to_string() };
+        notifications::send(
+            &self.notifications,
+            &profile_name,
+            notifications::events_for_run(failures, &errors, changed),
+        );
+        (success, summary.join("\n"))
+    }
+}
+
+/// 右侧下方显示的内容
+#[derive(Clone, Copy, PartialEq)]
+enum RightTab {
+    History,
+    Logs,
+}
+
+/// 日志查看器可选的最低级别
+const LOG_LEVELS: [(log::LevelFilter, &str); 5] = [
+    (log::LevelFilter::Error, "错误"),
+    (log::LevelFilter::Warn, "警告"),
+    (log::LevelFilter::Info, "信息"),
+    (log::LevelFilter::Debug, "调试"),
+    (log::LevelFilter::Trace, "全部"),
+];
+
+/// 复制诊断信息时附带的最近日志条数
+const DIAGNOSTIC_LOG_LINES: usize = 200;
+
+/// 停止编辑环境多久之后保存配置文件
+const PROFILE_SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// 对被本地修改的目标文件的处理方式
+#[derive(Clone, Copy)]
+enum DriftAction {
+    Keep,
+    Restore,
+    Diff,
 }
 
 // 颜色常量
@@ -249,7 +313,7 @@ impl Default for AppState {
         let exe_path = match std::env::current_exe() {
             Ok(path) => path,
             Err(e) => {
-                eprintln!("Warning: Failed to get current exe path: {}, using fallback", e);
+                log::warn!("Failed to get current exe path: {}, using fallback", e);
                 std::path::PathBuf::from("AutoUpdateMavenSettings")
             }
         };
@@ -257,7 +321,7 @@ impl Default for AppState {
         let exe_path_str = match exe_path.to_str() {
             Some(path) => path,
             None => {
-                eprintln!("Warning: Exe path contains invalid UTF-8, using fallback");
+                log::warn!("Exe path contains invalid UTF-8, using fallback");
                 "AutoUpdateMavenSettings"
             }
         };
@@ -266,37 +330,79 @@ impl Default for AppState {
         let auto_launch = match create_auto_launch(app_name, exe_path_str) {
             Ok(launcher) => launcher,
             Err(e) => {
-                eprintln!("Warning: Failed to create AutoLaunch: {}", e);
+                log::warn!("Failed to create AutoLaunch: {}", e);
                 // 创建一个空的AutoLaunch作为fallback
-                #[cfg(target_os = "windows")]
+                #[cfg(not(target_os = "macos"))]
                 let fallback = AutoLaunch::new("fallback", "fallback", &[] as &[&str]);
-                #[cfg(not(target_os = "windows"))]
+                #[cfg(target_os = "macos")]
                 let fallback = AutoLaunch::new("fallback", "fallback", false, &[] as &[&str]);
                 fallback
             }
         };
 
         // 读取配置文件
-        eprintln!("Loading configuration...");
-        let config = load_config();
-        eprintln!("Configuration loaded successfully");
+        log::info!("Loading configuration...");
+        let (overrides, override_warnings) = overrides::Overrides::from_env();
+        let loaded = config::load_config(&overrides);
+        let config = loaded.config;
+        log::info!("Configuration loaded successfully");
+        let permission_warning = permissions::fix_existing(&config);
+        let active_profile = config.active_index();
         
         let shared_state = Arc::new(Mutex::new(SharedState {
             enable_scheduler: config.enable_scheduler,
-            url: config.url.clone(),
-            interval_hours: config.interval_hours,
-            history: Vec::new(),
+            profile: config.profiles[active_profile].clone(),
+            histories: history::load_histories(),
+            manifest_updates: HashMap::new(),
+            probe_reports: HashMap::new(),
+            notifications: config.notifications.clone(),
+            webhooks: config.webhooks.clone(),
+            failure_streaks: HashMap::new(),
+            metrics: metrics::Metrics::default(),
+            ui_status: metrics::UiStatus::default(),
+            next_run: None,
         }));
 
-        Self {
-            url: config.url.clone(),
-            previous_url: config.url,
-            interval_hours: config.interval_hours,
-            previous_interval_hours: config.interval_hours,
+        let mut app = Self {
+            profiles: config.profiles.clone(),
+            active_profile,
+            edited_profiles: config.profiles.clone(),
+            saved_profiles: config.profiles,
+            profiles_edited_at: Instant::now(),
+            history_target: 0,
+            config_warning: loaded
+                .warning
+                .or_else(|| policy::current().error.clone())
+                .or_else(|| (!override_warnings.is_empty()).then(|| override_warnings.join("\n")))
+                .or(permission_warning),
+            persisted_config: loaded.persisted,
+            overrides,
+            show_diagnostics: false,
+            import_source: String::new(),
+            export_include_secrets: false,
+            pending_import: None,
+            credential_input: config::ServerCredential::default(),
+            host_input: String::new(),
+            keep_input: String::new(),
+            transform_input: config::Transform::SetElement {
+                path: "localRepository".to_string(),
+                value: String::new(),
+            },
+            transform_preview: None,
+            detected_jdks: None,
+            has_master_password: matches!(mavensec::master_password(), Ok(Some(_))),
+            watcher: None,
+            locally_modified: Vec::new(),
+            drift_diff: None,
+            inspected: None,
+            route_repo_id: String::new(),
+            route_repo_url: String::new(),
             status: "未开始".to_string(),
             running: false,
             enable_scheduler: config.enable_scheduler,
             history: Vec::new(),
+            right_tab: RightTab::History,
+            log_level: log::LevelFilter::Info,
             shared_state,
             auto_launch_enabled: config.auto_launch_enabled,
             auto_launch,
@@ -305,22 +411,128 @@ impl Default for AppState {
             next_update_time: None,
             show_window: true,
             minimize_to_background: config.minimize_to_background,
+            notifications: config.notifications.clone(),
+            webhooks: config.webhooks.clone(),
+            webhook_input: config::Webhook::default(),
+            status_endpoint: config.status_endpoint.clone(),
+            status_server: None,
             should_show_window: Arc::new(Mutex::new(false)),
-        }
+            startup_commands: Vec::new(),
+        };
+        app.restart_status_server();
+        app
     }
 }
 
 impl AppState {
     /// 保存当前所有配置到文件
-    fn save_current_config(&self) {
+    fn save_current_config(&mut self) {
         let config = AppConfig {
-            url: self.url.clone(),
-            interval_hours: self.interval_hours,
+            version: config::CONFIG_VERSION,
+            profiles: self.profiles.clone(),
+            active_profile: self.profile().id.clone(),
             enable_scheduler: self.enable_scheduler,
             auto_launch_enabled: self.auto_launch_enabled,
             minimize_to_background: self.minimize_to_background,
+            notifications: self.notifications.clone(),
+            webhooks: self.webhooks.clone(),
+            status_endpoint: self.status_endpoint.clone(),
+        };
+        config::save_effective_config(&config, &mut self.persisted_config, &self.overrides);
+        self.saved_profiles = self.profiles.clone();
+    }
+
+    /// 当前使用的同步环境
+    fn profile(&self) -> &SyncProfile {
+        &self.profiles[self.active_profile]
+    }
+
+    fn profile_mut(&mut self) -> &mut SyncProfile {
+        &mut self.profiles[self.active_profile]
+    }
+
+    /// 把当前环境同步给后台线程，有修改时保存配置
+    fn sync_profiles(&mut self) {
+        if let Ok(mut shared) = self.shared_state.lock() {
+            // 先写回清单给出的目标列表和推荐间隔
+            for (profile_id, (targets, interval)) in shared.manifest_updates.drain() {
+                if let Some(profile) = self.profiles.iter_mut().find(|p| p.id == profile_id && p.uses_manifest()) {
+                    profile.targets = targets;
+                    if let Some(interval) = interval {
+                        profile.interval_hours = interval.clamp(policy::current().min_interval(), 168);
+                    }
+                    self.overrides.apply_to_profile(profile);
+                    policy::current().apply_to_profile(profile);
+                }
+            }
+            shared.profile = self.profiles[self.active_profile].clone();
+        }
+        if !self.has_unsaved_profiles() {
+            return;
+        }
+        // 输入时每一帧都可能有修改，停止编辑一段时间后再保存，避免每次按键都写配置文件
+        if self.profiles != self.edited_profiles {
+            self.edited_profiles = self.profiles.clone();
+            self.profiles_edited_at = Instant::now();
+        }
+        if self.profiles_edited_at.elapsed() >= PROFILE_SAVE_DELAY {
+            self.save_current_config();
+            // 目标或处理方式可能有变化
+            self.check_local_modifications();
+        }
+    }
+
+    fn has_unsaved_profiles(&self) -> bool {
+        self.profiles != self.saved_profiles
+    }
+
+    /// 历史记录可查看的来源：清单（如有）和各目标文件，(id, 名称)
+    fn history_sources(&self) -> Vec<(String, String)> {
+        let profile = self.profile();
+        let mut sources = Vec::new();
+        if profile.uses_manifest() {
+            sources.push((sync::MANIFEST_TARGET_ID.to_string(), "清单".to_string()));
+        }
+        sources.extend(profile.targets.iter().map(|t| (t.id.clone(), t.name.clone())));
+        if profile.gradle != GradleOutput::Off {
+            sources.push((sync::GRADLE_TARGET_ID.to_string(), "Gradle".to_string()));
+        }
+        sources
+    }
+
+    /// 切换到指定环境，并应用该环境最近一次成功下载的文件
+    fn activate_profile(&mut self, index: usize) {
+        if index >= self.profiles.len() {
+            return;
+        }
+        self.active_profile = index;
+        self.save_current_config();
+        self.sync_profiles();
+
+        let profile = self.profile().clone();
+        self.history_target = 0;
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let mut any_applied = false;
+        if let Ok(mut shared) = self.shared_state.lock() {
+            for (outcome, applied) in sync::apply_last_known_good(&profile) {
+                any_applied |= applied;
+                let record = match outcome.result {
+                    Ok(detail) if applied && !detail.is_empty() => format!("{}: 切换环境成功 - {}", now, detail),
+                    Ok(_) if applied => format!("{}: 切换环境成功 - 已应用最近一次成功下载的配置", now),
+                    Ok(_) => format!("{}: 切换环境 - 该环境尚无成功下载的配置", now),
+                    Err(e) => format!("{}: 切换环境失败 - {}", now, e),
+                };
+                history::push_record(&mut shared.histories, sync::history_key(&profile.id, &outcome.target_id), &record);
+            }
+            history::save_histories(&mut shared.histories);
+        }
+        self.status = if any_applied {
+            format!("已切换到「{}」", profile.name)
+        } else {
+            format!("已切换到「{}」，尚无可用配置", profile.name)
         };
-        save_config(&config);
+        self.locally_modified.clear();
+        self.check_local_modifications();
     }
 
     /// 处理窗口显示状态
@@ -362,11 +574,57 @@ impl AppState {
             ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
         }
     }
+    /// 执行启动参数以及其他实例转发过来的命令
+    fn handle_forwarded_commands(&mut self, ctx: &egui::Context) {
+        let mut commands = std::mem::take(&mut self.startup_commands);
+        commands.extend(ipc::take_forwarded_commands());
+
+        for forwarded in commands {
+            // 导入在窗口中预览，由用户确认后应用
+            if let Some(source) = &forwarded.command.import {
+                match share::load_import(source, &self.persisted_config) {
+                    Ok(pending) => {
+                        forwarded.reply(true, &format!("已在窗口中打开导入预览（{} 项变更），请确认后应用", pending.changes.len()));
+                        self.pending_import = Some(pending);
+                        self.show_window = true;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                    }
+                    Err(e) => forwarded.reply(false, &format!("无法导入配置: {}", e)),
+                }
+                continue;
+            }
+
+            // 命令行参数只覆盖本次运行的配置，策略仍然优先
+            if forwarded.command.has_overrides() {
+                self.overrides.merge_cli(&forwarded.command);
+                for profile in &mut self.profiles {
+                    self.overrides.apply_to_profile(profile);
+                    policy::current().apply_to_profile(profile);
+                }
+                self.sync_profiles();
+            }
+
+            if forwarded.command.update_now {
+                self.perform_immediate_update_async(ctx, Some(forwarded));
+            } else {
+                forwarded.reply(true, &format!(
+                    "已临时使用以下配置（不会保存到配置文件）:\n下载地址: {}\n更新间隔: {} 小时",
+                    self.profile().url,
+                    self.profile().interval_hours
+                ));
+            }
+        }
+    }
+
     /// 异步执行立即更新，避免阻塞 UI 线程
-    fn perform_immediate_update_async(&mut self, ctx: &egui::Context) {
+    fn perform_immediate_update_async(&mut self, ctx: &egui::Context, reply: Option<ipc::ForwardedCommand>) {
         // 校验 URL
-        if !self.url.starts_with("http") {
+        if !self.profile().has_source() {
             self.status = "请输入有效的下载地址".to_string();
+            if let Some(reply) = reply {
+                reply.reply(false, &self.status);
+            }
             return;
         }
 
@@ -379,22 +637,32 @@ impl AppState {
         self.running = true;
 
         // 克隆需要在子线程中使用的值
-        let url = self.url.clone();
+        let profile = self.profile().clone();
+        let webhooks = webhooks::effective(&self.webhooks);
         let shared_state = Arc::clone(&self.shared_state);
         let ctx_clone = ctx.clone();
 
         // 后台线程执行下载和替换
         thread::spawn(move || {
-            let now = chrono::Local::now();
-            let result = download_and_replace(&url);
-            let record = match result {
-                Ok(_) => format!("{}: 立即更新成功", now.format("%Y-%m-%d %H:%M:%S")),
-                Err(e) => format!("{}: 立即更新失败 - {}", now.format("%Y-%m-%d %H:%M:%S"), e),
-            };
+            let run = sync::sync_profile(&profile);
+            let report = (!webhooks.is_empty()).then(|| webhooks::Report::from_run(&profile, &run, "立即更新"));
 
             // 只更新共享历史记录，不直接修改 AppState
-            if let Ok(mut shared) = shared_state.lock() {
-                shared.history.push(record);
+            let result = shared_state
+                .lock()
+                .map(|mut shared| shared.record_run(&profile.id, run, "立即更新"));
+
+            // 回复转发命令的调用方
+            if let Some(reply) = reply {
+                match result {
+                    Ok((success, summary)) => reply.reply(success, &summary),
+                    Err(_) => reply.reply(false, "内部状态不可用"),
+                }
+            }
+
+            // 不持有锁，发送可能因重试耗时较长
+            if let Some(report) = report {
+                webhooks::deliver_all(&webhooks, &report);
             }
 
             // 通知主线程重绘界面
@@ -486,7 +754,7 @@ impl AppState {
                                     }
                                 );
                                 ui.add_space(12.0);
-                                println!("⚠️ Logo 未找到，使用文字替代");
+                                log::trace!("Logo not found, using text instead");
                             }
 
                             ui.vertical(|ui| {
@@ -680,7 +948,7 @@ impl AppState {
 
                 ui.add_space(8.0);
                 ui.label(
-                    egui::RichText::new(format!("状态：{}", self.status))
+                    egui::RichText::new(format!("状态：{}", redact::redact(&self.status)))
                         .size(14.0)
                         .color(TEXT_COLOR)
                         .strong()
@@ -747,17 +1015,33 @@ impl AppState {
         );
     }
 
-    fn draw_history_section(&self, ui: &mut egui::Ui) {
+    fn draw_history_section(&mut self, ui: &mut egui::Ui) {
         // 历史记录标题
         ui.horizontal(|ui| {
             ui.label("📋");
             ui.add_space(6.0);
             ui.label(
-                egui::RichText::new("执行历史记录")
+                egui::RichText::new(format!("执行历史记录 · {}", self.profile().name))
                     .size(14.0)
                     .color(TEXT_COLOR)
                     .strong()
             );
+
+            // 多个来源时选择查看哪个文件的历史
+            let sources = self.history_sources();
+            if sources.len() > 1 {
+                let mut selected = self.history_target.min(sources.len() - 1);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    egui::ComboBox::from_id_source("history_target")
+                        .selected_text(sources[selected].1.clone())
+                        .show_ui(ui, |ui| {
+                            for (i, (_, name)) in sources.iter().enumerate() {
+                                ui.selectable_value(&mut selected, i, name);
+                            }
+                        });
+                });
+                self.history_target = selected;
+            }
         });
         ui.add_space(12.0);
 
@@ -839,8 +1123,79 @@ impl AppState {
     }
 
     fn draw_left_panel(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        // 同步环境
+        self.draw_section_title(ui, "同步环境");
+
+        let mut selected = self.active_profile;
+        let mut add_profile = false;
+        let mut remove_profile = false;
+        ui.allocate_ui_with_layout(
+            egui::vec2(ui.available_width(), 30.0),
+            egui::Layout::left_to_right(egui::Align::Center),
+            |ui| {
+                egui::ComboBox::from_id_source("profile_switcher")
+                    .width(ui.available_width() - 100.0)
+                    .selected_text(self.profiles[self.active_profile].name.clone())
+                    .show_ui(ui, |ui| {
+                        for (i, profile) in self.profiles.iter().enumerate() {
+                            ui.selectable_value(&mut selected, i, &profile.name);
+                        }
+                    });
+                if ui.button("新建").clicked() {
+                    add_profile = true;
+                }
+                if ui.add_enabled(self.profiles.len() > 1, egui::Button::new("删除")).clicked() {
+                    remove_profile = true;
+                }
+            }
+        );
+
+        if add_profile {
+            let mut profile = SyncProfile::new(&format!("环境 {}", self.profiles.len() + 1));
+            profile.interval_hours = self.profile().interval_hours;
+            self.overrides.apply_to_profile(&mut profile);
+            policy::current().apply_to_profile(&mut profile);
+            self.profiles.push(profile);
+            self.activate_profile(self.profiles.len() - 1);
+        } else if remove_profile {
+            let removed = self.profiles.remove(self.active_profile);
+            if let Ok(mut shared) = self.shared_state.lock() {
+                let prefix = sync::history_key(&removed.id, "");
+                shared.histories.retain(|key, _| !key.starts_with(&prefix));
+                shared.probe_reports.retain(|key, _| !key.starts_with(&prefix));
+                history::save_histories(&mut shared.histories);
+            }
+            let _ = fs::remove_dir_all(paths::profile_data_dir(&removed.id));
+            self.activate_profile(0);
+        } else if selected != self.active_profile {
+            self.activate_profile(selected);
+        }
+
+        ui.add_space(6.0);
+        ui.allocate_ui_with_layout(
+            egui::vec2(ui.available_width(), 30.0),
+            egui::Layout::left_to_right(egui::Align::Center),
+            |ui| {
+                ui.label(
+                    egui::RichText::new("名称")
+                        .size(13.0)
+                        .color(SECONDARY_TEXT_COLOR)
+                );
+                ui.add_sized(
+                    [ui.available_width(), 30.0],
+                    egui::TextEdit::singleline(&mut self.profiles[self.active_profile].name)
+                        .vertical_align(egui::Align::Center)
+                );
+            }
+        );
+
+        ui.add_space(20.0);
+
         // 基础配置
         self.draw_section_title(ui, "基础配置");
+        let url_lock = self.url_lock();
+        let manifest_lock = self.manifest_lock();
+        let interval_lock = self.overrides.interval_hours.as_ref().map(|o| o.hint());
 
         ui.label(
             egui::RichText::new("Settings.xml 下载地址")
@@ -856,23 +1211,16 @@ impl AppState {
             |ui| {
                 ui.add_sized(
                     [ui.available_width(), 36.0],
-                    egui::TextEdit::singleline(&mut self.url)
+                    egui::TextEdit::singleline(&mut self.profiles[self.active_profile].url)
                         .hint_text("请输入 HTTP 下载链接...")
                         .desired_width(ui.available_width())
                         .vertical_align(egui::Align::Center)
+                        .interactive(url_lock.is_none())
                 );
             }
         );
-
-        // 同步 URL 到共享状态并保存配置
-        if let Ok(mut shared) = self.shared_state.lock() {
-            shared.url = self.url.clone();
-        }
-        
-        // 如果URL发生变化，保存到配置文件
-        if self.url != self.previous_url {
-            save_url_to_config(&self.url);
-            self.previous_url = self.url.clone();
+        if let Some(reason) = &url_lock {
+            self.draw_lock_hint(ui, reason);
         }
 
         ui.add_space(16.0);
@@ -892,11 +1240,13 @@ impl AppState {
             egui::Layout::left_to_right(egui::Align::Center),
             |ui| {
                 // 数值输入框
-                ui.add_sized(
-                    [75.0, 36.0],
-                    egui::DragValue::new(&mut self.interval_hours)
-                        .clamp_range(1..=168)
-                );
+                ui.add_enabled_ui(interval_lock.is_none(), |ui| {
+                    ui.add_sized(
+                        [75.0, 36.0],
+                        egui::DragValue::new(&mut self.profiles[self.active_profile].interval_hours)
+                            .clamp_range(policy::current().min_interval()..=168)
+                    );
+                });
                 ui.add_space(8.0);
                 // 垂直居中的"小时"标签
                 ui.label(
@@ -905,24 +1255,56 @@ impl AppState {
                         .color(SECONDARY_TEXT_COLOR)
                 );
             });
+        if let Some(reason) = &interval_lock {
+            self.draw_lock_hint(ui, reason);
+        }
 
-            // 同步配置到共享状态并保存
-            if let Ok(mut shared) = self.shared_state.lock() {
-                shared.url = self.url.clone();
-                shared.interval_hours = self.interval_hours;
-            }
-            
-            // 如果间隔小时数发生变化，保存配置
-            if self.interval_hours != self.previous_interval_hours {
-                self.save_current_config();
-                self.previous_interval_hours = self.interval_hours;
-            }
+        ui.add_space(16.0);
+        ui.label(
+            egui::RichText::new("中央清单地址（可选）")
+                .size(13.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        ui.add_space(6.0);
+        ui.add_sized(
+            [ui.available_width(), 36.0],
+            egui::TextEdit::singleline(&mut self.profiles[self.active_profile].manifest_url)
+                .hint_text("JSON/TOML 清单链接...")
+                .vertical_align(egui::Align::Center)
+                .interactive(manifest_lock.is_none())
+        );
+        if let Some(reason) = &manifest_lock {
+            self.draw_lock_hint(ui, reason);
+        }
 
-            // 新增：如果地址发生变化则保存到配置文件
-            if self.url != self.previous_url {
-                save_url_to_config(&self.url);
-                self.previous_url = self.url.clone();
-            }
+        ui.add_space(16.0);
+        self.draw_auth_settings(ui);
+
+        ui.add_space(16.0);
+        self.draw_source_security(ui);
+
+        ui.add_space(20.0);
+        self.draw_section_title(ui, "同步文件");
+        self.draw_target_settings(ui);
+
+        ui.add_space(20.0);
+        self.draw_section_title(ui, "服务器凭据");
+        self.draw_credential_settings(ui);
+
+        ui.add_space(20.0);
+        self.draw_section_title(ui, "本地调整");
+        self.draw_transform_settings(ui);
+
+        ui.add_space(20.0);
+        self.draw_section_title(ui, "JDK 工具链");
+        self.draw_toolchain_settings(ui);
+
+        ui.add_space(20.0);
+        self.draw_section_title(ui, "Gradle");
+        self.draw_gradle_settings(ui);
+
+        // 同步配置到共享状态，有修改时保存
+        self.sync_profiles();
 
         ui.add_space(20.0);
 
@@ -943,12 +1325,12 @@ impl AppState {
             self.auto_launch_enabled = !self.auto_launch_enabled;
             if self.auto_launch_enabled {
                 if let Err(e) = self.auto_launch.enable() {
-                    eprintln!("Failed to enable auto launch: {}", e);
+                    log::warn!("Failed to enable auto launch: {}", e);
                     self.auto_launch_enabled = false;
                 }
             } else {
                 if let Err(e) = self.auto_launch.disable() {
-                    eprintln!("Failed to disable auto launch: {}", e);
+                    log::warn!("Failed to disable auto launch: {}", e);
                 }
             }
             self.save_current_config();
@@ -970,12 +1352,21 @@ impl AppState {
                 "Ctrl+Shift+M"
             };
             ui.label(
-                egui::RichText::new(&format!("提示: 关闭窗口时程序将在后台继续运行\n按ESC键最小化，按{}重新显示，或双击应用图标", shortcut))
+                egui::RichText::new(format!("提示: 关闭窗口时程序将在后台继续运行\n按ESC键最小化，按{}重新显示，或双击应用图标", shortcut))
                     .size(11.0)
                     .color(SECONDARY_TEXT_COLOR)
             );
         }
 
+        ui.add_space(10.0);
+        self.draw_notification_settings(ui);
+
+        ui.add_space(10.0);
+        self.draw_webhook_settings(ui);
+
+        ui.add_space(10.0);
+        self.draw_status_endpoint_settings(ui);
+
         ui.add_space(20.0);
 
         // 操作按钮
@@ -983,7 +1374,7 @@ impl AppState {
 
         if !self.running {
             if self.draw_custom_button(ui, "启动自动更新", true, true).clicked() {
-                if self.url.starts_with("http") && self.interval_hours > 0 {
+                if self.profile().has_source() && self.profile().interval_hours > 0 {
                     self.start_update_task(ctx);
                 } else {
                     self.status = "请输入有效的 URL 和间隔".to_string();
@@ -994,49 +1385,1750 @@ impl AppState {
                 self.stop_scheduler();
             }
         }
-    }
 
-    fn draw_right_panel(&self, ui: &mut egui::Ui) {
-        // 状态显示
-        self.draw_section_title(ui, "运行状态");
-        self.draw_status_card(ui);
+        ui.add_space(20.0);
+        self.draw_section_title(ui, "配置分享");
+        self.draw_share_settings(ui);
+    }
 
-        ui.add_space(16.0);
+    /// 下载来源的安全限制：仅 HTTPS 和允许的主机
+    fn draw_source_security(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("下载安全")
+                .size(13.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        ui.add_space(6.0);
 
-        // 下次执行时间显示
-        if self.scheduler_running && self.next_update_time.is_some() {
-            self.draw_next_update_card(ui);
-            ui.add_space(16.0);
+        let https_lock = allowlist::https_only_lock();
+        let mut https_only = allowlist::SourceRules::for_profile(self.profile()).https_only;
+        ui.add_enabled_ui(https_lock.is_none(), |ui| {
+            if ui.checkbox(&mut https_only, "仅允许 HTTPS 下载").changed() {
+                self.profile_mut().https_only = Some(https_only);
+            }
+        });
+        if let Some(reason) = &https_lock {
+            self.draw_lock_hint(ui, reason);
         }
 
-        // 执行历史
-        self.draw_history_section(ui);
+        ui.label(
+            egui::RichText::new("允许的主机（为空表示不限制，重定向同样检查）")
+                .size(12.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        let mut remove = None;
+        for (i, host) in self.profile().allowed_hosts.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(host).size(12.0).color(TEXT_COLOR));
+                if ui.small_button("删除").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.profile_mut().allowed_hosts.remove(i);
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.host_input)
+                    .hint_text("maven.example.com 或 *.example.com")
+                    .desired_width(ui.available_width() - 60.0)
+            );
+            if ui.button("添加").clicked() {
+                let host = self.host_input.trim().to_lowercase();
+                match allowlist::validate_host_pattern(&host) {
+                    Ok(_) => {
+                        if !self.profile().allowed_hosts.contains(&host) {
+                            self.profile_mut().allowed_hosts.push(host);
+                        }
+                        self.host_input.clear();
+                    }
+                    Err(e) => self.status = e,
+                }
+            }
+        });
+        if !policy::current().allowed_hosts.is_empty() {
+            self.draw_lock_hint(
+                ui,
+                &format!("管理员策略只允许: {}", policy::current().allowed_hosts.join("、")),
+            );
+        }
     }
 
-    fn start_update_task(&mut self, ctx: &egui::Context) {
-        // 立即执行一次更新：改用异步版本
-        self.perform_immediate_update_async(ctx);
-
-        if self.enable_scheduler && !self.scheduler_running {
-            // 设置下次更新时间
-            let now = chrono::Local::now();
-            self.next_update_time = Some(now + chrono::Duration::hours(self.interval_hours as i64));
+    /// 合并进 settings.xml 的服务器凭据，以及 settings-security.xml 中的主密码
+    fn draw_credential_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("写入 settings.xml 的 <servers>，密码用主密码加密保存，不会以明文写入磁盘")
+                .size(12.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
 
-            // 启动定时任务
-            self.scheduler_running = true;
-            self.running = true;
-            self.status = "定时任务已启动".to_string();
+        let mut remove = None;
+        for (i, credential) in self.profile().server_credentials.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let password = if credential.password.is_empty() { "无密码" } else { "密码已加密" };
+                ui.label(
+                    egui::RichText::new(format!("{} · {} · {}", credential.server_id, credential.username, password))
+                        .size(12.0)
+                        .color(TEXT_COLOR)
+                );
+                if ui.small_button("删除").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.profile_mut().server_credentials.remove(i);
+        }
 
-            // 重置停止信号
-            if let Ok(mut stop) = self.stop_signal.lock() {
-                *stop = false;
+        ui.horizontal(|ui| {
+            let width = (ui.available_width() - 60.0) / 3.0;
+            ui.add(egui::TextEdit::singleline(&mut self.credential_input.server_id).hint_text("server id").desired_width(width));
+            ui.add(egui::TextEdit::singleline(&mut self.credential_input.username).hint_text("用户名").desired_width(width));
+            ui.add(
+                egui::TextEdit::singleline(&mut self.credential_input.password)
+                    .hint_text("密码")
+                    .password(true)
+                    .desired_width(width)
+            );
+            if ui.button("保存").clicked() {
+                self.save_credential_input();
             }
+        });
 
-            let shared_state = Arc::clone(&self.shared_state);
-            let stop_signal = Arc::clone(&self.stop_signal);
-            let ctx_clone = ctx.clone();
-
-            thread::spawn(move || {
+        ui.add_space(6.0);
+        let master_status = if self.has_master_password {
+            format!("主密码: 已配置（{}）", mavensec::security_file().display())
+        } else {
+            "主密码: 未配置，保存凭据时自动生成".to_string()
+        };
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(master_status).size(12.0).color(SECONDARY_TEXT_COLOR));
+            let label = if self.has_master_password { "更换主密码" } else { "生成主密码" };
+            if ui.small_button(label).clicked() {
+                self.rotate_master_password();
+            }
+        });
+    }
+
+    /// 写入 settings.xml 前的本地调整：始终保留的本机元素和按顺序执行的规则
+    fn draw_transform_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("始终保留本机的元素（每次同步后写回本机的值，本机没有时删除下载的值）")
+                .size(12.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        let mut remove_keep = None;
+        for (i, path) in self.profile().keep_local.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(path).size(12.0).color(TEXT_COLOR));
+                if ui.small_button("删除").clicked() {
+                    remove_keep = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove_keep {
+            self.profile_mut().keep_local.remove(i);
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.keep_input)
+                    .hint_text("元素路径，如 localRepository、pluginGroups")
+                    .desired_width(ui.available_width() - 60.0)
+            );
+            if ui.button("添加").clicked() {
+                let path = self.keep_input.trim().to_string();
+                match config::validate_element_path(&path) {
+                    Ok(_) => {
+                        if !self.profile().keep_local.contains(&path) {
+                            self.profile_mut().keep_local.push(path);
+                        }
+                        self.keep_input.clear();
+                    }
+                    Err(e) => self.status = e,
+                }
+            }
+        });
+        ui.add_space(6.0);
+
+        ui.label(
+            egui::RichText::new("在下载的 settings.xml 上做少量本机调整，每次同步时按顺序执行并记入历史")
+                .size(12.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+
+        let mut remove = None;
+        let mut move_up = None;
+        for (i, transform) in self.profile().transforms.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(format!("{}. {}", i + 1, redact::redact(&transform.describe())))
+                        .size(12.0)
+                        .color(TEXT_COLOR)
+                );
+                if i > 0 && ui.small_button("上移").clicked() {
+                    move_up = Some(i);
+                }
+                if ui.small_button("删除").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = move_up {
+            self.profile_mut().transforms.swap(i - 1, i);
+        }
+        if let Some(i) = remove {
+            self.profile_mut().transforms.remove(i);
+        }
+
+        let kinds = [
+            config::Transform::SetElement {
+                path: "localRepository".to_string(),
+                value: String::new(),
+            },
+            config::Transform::RemoveById {
+                section: "mirrors".to_string(),
+                id: String::new(),
+            },
+            config::Transform::AddActiveProfile { id: String::new() },
+            config::Transform::SetProxy {
+                id: String::new(),
+                protocol: "http".to_string(),
+                host: String::new(),
+                port: 8080,
+                non_proxy_hosts: String::new(),
+                active: true,
+            },
+        ];
+        egui::ComboBox::from_id_source("transform_kind")
+            .selected_text(self.transform_input.kind_label())
+            .show_ui(ui, |ui| {
+                for kind in kinds {
+                    let label = kind.kind_label();
+                    let selected = label == self.transform_input.kind_label();
+                    if ui.selectable_label(selected, label).clicked() && !selected {
+                        self.transform_input = kind;
+                    }
+                }
+            });
+
+        let field = |ui: &mut egui::Ui, value: &mut String, hint: &str| {
+            ui.add(egui::TextEdit::singleline(value).hint_text(hint).desired_width(ui.available_width()));
+        };
+        match &mut self.transform_input {
+            config::Transform::SetElement { path, value } => {
+                field(ui, path, "元素路径，如 localRepository、offline");
+                field(ui, value, "值");
+            }
+            config::Transform::RemoveById { section, id } => {
+                egui::ComboBox::from_id_source("transform_section")
+                    .selected_text(section.clone())
+                    .show_ui(ui, |ui| {
+                        for (name, _) in config::TRANSFORM_SECTIONS {
+                            ui.selectable_value(section, name.to_string(), name);
+                        }
+                    });
+                field(ui, id, "id");
+            }
+            config::Transform::AddActiveProfile { id } => field(ui, id, "profile id"),
+            config::Transform::SetProxy {
+                id,
+                protocol,
+                host,
+                port,
+                non_proxy_hosts,
+                active,
+            } => {
+                field(ui, id, "代理 id");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("transform_proxy_protocol")
+                        .selected_text(protocol.clone())
+                        .show_ui(ui, |ui| {
+                            for name in ["http", "https"] {
+                                ui.selectable_value(protocol, name.to_string(), name);
+                            }
+                        });
+                    ui.add(egui::TextEdit::singleline(host).hint_text("主机").desired_width(160.0));
+                    ui.add(egui::DragValue::new(port).clamp_range(1..=65535));
+                    ui.checkbox(active, "启用");
+                });
+                field(ui, non_proxy_hosts, "不走代理的主机，如 localhost|*.corp.example.com");
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("添加规则").clicked() {
+                match self.transform_input.validate() {
+                    Ok(_) => {
+                        let transform = self.transform_input.clone();
+                        self.profile_mut().transforms.push(transform);
+                    }
+                    Err(e) => self.status = e,
+                }
+            }
+            if ui
+                .add_enabled(
+                    !self.profile().transforms.is_empty() || !self.profile().keep_local.is_empty(),
+                    egui::Button::new("预览")
+                )
+                .clicked()
+            {
+                self.preview_transforms();
+            }
+        });
+    }
+
+    /// 本机 JDK 列表，以及用它们生成 toolchains.xml
+    fn draw_toolchain_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("检测 JAVA_HOME、/usr/lib/jvm、SDKMAN、asdf、~/.jdks 等位置的 JDK，写入 ~/.m2/toolchains.xml")
+                .size(12.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        match &self.detected_jdks {
+            Some(jdks) if jdks.is_empty() => {
+                ui.label(egui::RichText::new("没有检测到 JDK").size(12.0).color(SECONDARY_TEXT_COLOR));
+            }
+            Some(jdks) => {
+                for jdk in jdks {
+                    ui.label(
+                        egui::RichText::new(format!("{}（{}）  {}", jdk.version, jdk.vendor, jdk.home.display()))
+                            .size(12.0)
+                            .color(TEXT_COLOR)
+                    );
+                }
+            }
+            None => {}
+        }
+
+        // 环境同步 toolchains.xml 并合并 JDK 时，由同步负责写入
+        let synced = self
+            .profile()
+            .targets
+            .iter()
+            .any(|t| toolchains::is_toolchains(t) && t.merge_jdks);
+        ui.horizontal(|ui| {
+            if ui.button("检测本机 JDK").clicked() {
+                self.detected_jdks = Some(toolchains::detect());
+            }
+            if !synced && ui.button("写入 toolchains.xml").clicked() {
+                let target = SyncTarget::presets()
+                    .into_iter()
+                    .find(|t| t.id == "toolchains")
+                    .expect("toolchains preset");
+                self.status = match toolchains::generate(&target) {
+                    Ok(log) if log.is_empty() => "toolchains.xml 已是最新".to_string(),
+                    Ok(log) => format!("已更新 toolchains.xml: {}", log.join("；")),
+                    Err(e) => format!("生成 toolchains.xml 失败: {}", e),
+                };
+                self.detected_jdks = Some(toolchains::detect());
+            }
+        });
+        if synced {
+            ui.label(
+                egui::RichText::new("当前环境同步 toolchains.xml，每次同步时会自动合并本机 JDK")
+                    .size(12.0)
+                    .color(SECONDARY_TEXT_COLOR)
+            );
+        }
+    }
+
+    /// 各事件的桌面通知开关
+    fn draw_notification_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("桌面通知")
+                .size(13.0)
+                .color(TEXT_COLOR)
+        );
+        let mut settings = self.notifications.clone();
+        ui.checkbox(&mut settings.on_failure, "更新失败时");
+        ui.checkbox(
+            &mut settings.on_repeated_failure,
+            format!("连续 {} 次更新失败时", notifications::REPEATED_FAILURES),
+        );
+        ui.checkbox(&mut settings.on_change, "配置内容有变化时");
+        if settings != self.notifications {
+            self.notifications = settings;
+            if let Ok(mut shared) = self.shared_state.lock() {
+                shared.notifications = self.notifications.clone();
+            }
+            self.save_current_config();
+        }
+    }
+
+    /// 每次同步后接收结果的 Webhook，策略中的只读显示
+    fn draw_webhook_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("Webhook")
+                .size(13.0)
+                .color(TEXT_COLOR)
+        );
+        ui.label(
+            egui::RichText::new("每次同步后 POST 主机名、用户、结果、文件校验和与错误（已脱敏）")
+                .size(11.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        let describe = |webhook: &config::Webhook| {
+            let mut text = format!("{}（{}）", webhook.display_host(), webhook.format.label());
+            if webhook.failures_only {
+                text.push_str(" 仅失败时");
+            }
+            text
+        };
+        let policy_webhooks = &policy::current().webhooks;
+        for webhook in policy_webhooks {
+            ui.label(egui::RichText::new(describe(webhook)).size(12.0).color(TEXT_COLOR));
+        }
+        if !policy_webhooks.is_empty() {
+            self.draw_lock_hint(ui, &policy::current().reason());
+        }
+
+        let mut remove = None;
+        for (i, webhook) in self.webhooks.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(describe(webhook)).size(12.0).color(TEXT_COLOR));
+                if ui.small_button("删除").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        let mut changed = false;
+        if let Some(i) = remove {
+            self.webhooks.remove(i);
+            changed = true;
+        }
+
+        ui.add(
+            egui::TextEdit::singleline(&mut self.webhook_input.url)
+                .hint_text("https://hooks.example.com/...")
+                .desired_width(ui.available_width())
+        );
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("webhook_format")
+                .selected_text(self.webhook_input.format.label())
+                .show_ui(ui, |ui| {
+                    for format in [config::WebhookFormat::Json, config::WebhookFormat::Slack, config::WebhookFormat::Teams] {
+                        ui.selectable_value(&mut self.webhook_input.format, format, format.label());
+                    }
+                });
+            ui.checkbox(&mut self.webhook_input.failures_only, "仅失败时");
+            if ui.button("添加").clicked() {
+                match self.webhook_input.validate() {
+                    Ok(_) => {
+                        let mut webhook = std::mem::take(&mut self.webhook_input);
+                        webhook.url = webhook.url.trim().to_string();
+                        self.webhooks.push(webhook);
+                        changed = true;
+                    }
+                    Err(e) => self.status = e,
+                }
+            }
+        });
+
+        if changed {
+            if let Ok(mut shared) = self.shared_state.lock() {
+                shared.webhooks = self.webhooks.clone();
+            }
+            self.save_current_config();
+        }
+    }
+
+    /// 本机状态接口的开关和端口
+    fn draw_status_endpoint_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("本机状态接口")
+                .size(13.0)
+                .color(TEXT_COLOR)
+        );
+        let mut endpoint = self.status_endpoint.clone();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut endpoint.enabled, "开启");
+            ui.label("端口:");
+            ui.add(egui::DragValue::new(&mut endpoint.port).clamp_range(1024..=65535));
+        });
+        if let Some(server) = &self.status_server {
+            ui.label(
+                egui::RichText::new(format!(
+                    "http://127.0.0.1:{0}/status（JSON），http://127.0.0.1:{0}/metrics（Prometheus）",
+                    server.port()
+                ))
+                .size(11.0)
+                .color(SECONDARY_TEXT_COLOR)
+            );
+        } else {
+            ui.label(
+                egui::RichText::new("只监听 127.0.0.1，供本机的监控代理采集同步状态")
+                    .size(11.0)
+                    .color(SECONDARY_TEXT_COLOR)
+            );
+        }
+        if endpoint != self.status_endpoint {
+            self.status_endpoint = endpoint;
+            self.restart_status_server();
+            self.save_current_config();
+        }
+    }
+
+    /// 按当前设置停止或（重新）启动本机状态接口
+    fn restart_status_server(&mut self) {
+        // 先停止旧的监听，端口才能重新绑定
+        self.status_server = None;
+        if !self.status_endpoint.enabled {
+            return;
+        }
+        let shared_state = Arc::clone(&self.shared_state);
+        let handler: statusserver::Handler = Arc::new(move |path| {
+            let shared = shared_state.lock().ok()?;
+            match path {
+                "/status" => Some((
+                    "application/json",
+                    shared.metrics.status_json(&shared.ui_status, shared.enable_scheduler, &shared.profile),
+                )),
+                "/metrics" => Some((
+                    metrics::PROMETHEUS_CONTENT_TYPE,
+                    shared.metrics.prometheus(&shared.ui_status, shared.enable_scheduler),
+                )),
+                _ => None,
+            }
+        });
+        match statusserver::StatusServer::start(self.status_endpoint.port, handler) {
+            Ok(server) => self.status_server = Some(server),
+            Err(e) => {
+                log::warn!("Failed to start status endpoint: {}", e);
+                self.status = format!("本机状态接口启动失败: {}", e);
+            }
+        }
+    }
+
+    /// 把状态卡片的内容交给状态接口，并取回定时任务计算的下次更新时间
+    fn publish_status(&mut self) {
+        // 同步线程持有锁时跳过本帧，不阻塞界面
+        let Ok(mut shared) = self.shared_state.try_lock() else {
+            return;
+        };
+        if self.scheduler_running {
+            if let Some(next_run) = shared.next_run {
+                self.next_update_time = Some(next_run);
+            }
+        }
+        shared.ui_status = metrics::UiStatus {
+            status: self.status.clone(),
+            running: self.running,
+            next_run: self.next_update_time,
+        };
+    }
+
+    /// 把 settings.xml 中的镜像和凭据同步给 Gradle
+    fn draw_gradle_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("每次同步后按 settings.xml 生成 ~/.gradle/init.d 下的仓库重定向脚本，镜像凭据写入 ~/.gradle/gradle.properties")
+                .size(12.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        let mut output = self.profile().gradle;
+        egui::ComboBox::from_id_source("gradle_output")
+            .width(ui.available_width())
+            .selected_text(output.label())
+            .show_ui(ui, |ui| {
+                for mode in [GradleOutput::Off, GradleOutput::Groovy, GradleOutput::Kotlin] {
+                    ui.selectable_value(&mut output, mode, mode.label());
+                }
+            });
+        if output != self.profile().gradle {
+            self.profile_mut().gradle = output;
+            // 关闭后不再保留旧的重定向和明文凭据
+            if output == GradleOutput::Off {
+                self.status = match gradle::remove_outputs() {
+                    Ok(_) => "已删除生成的 Gradle 脚本和凭据".to_string(),
+                    Err(e) => format!("删除 Gradle 文件失败: {}", e),
+                };
+            } else {
+                self.status = "下次同步时生成 Gradle 文件".to_string();
+            }
+        }
+    }
+
+    /// 对最近一次下载的 settings.xml 试运行本地调整
+    fn preview_transforms(&mut self) {
+        let profile = self.profile().clone();
+        let preview = match profile.targets.iter().find(|t| credentials::is_maven_settings(t)) {
+            Some(target) => transform::preview(&profile, target),
+            None => Err("当前环境没有同步 settings.xml".to_string()),
+        };
+        self.transform_preview = Some(preview);
+    }
+
+    /// 加密输入的密码并保存凭据，同一 server id 的凭据被替换
+    fn save_credential_input(&mut self) {
+        let mut credential = std::mem::take(&mut self.credential_input);
+        credential.server_id = credential.server_id.trim().to_string();
+        if credential.server_id.is_empty() {
+            self.status = "请填写 server id".to_string();
+            self.credential_input = credential;
+            return;
+        }
+        if !credential.password.is_empty() {
+            match mavensec::ensure_master_password() {
+                Ok(master) => credential.password = mavensec::encrypt(&credential.password, &master),
+                Err(e) => {
+                    self.status = format!("无法加密密码: {}", e);
+                    credential.password.clear();
+                    self.credential_input = credential;
+                    return;
+                }
+            }
+            self.has_master_password = true;
+        }
+
+        let credentials = &mut self.profile_mut().server_credentials;
+        match credentials.iter_mut().find(|c| c.server_id == credential.server_id) {
+            Some(existing) => *existing = credential,
+            None => credentials.push(credential),
+        }
+        self.status = "凭据已保存，下次同步时写入 settings.xml".to_string();
+    }
+
+    /// 生成新的主密码并重新加密已保存的凭据
+    fn rotate_master_password(&mut self) {
+        match mavensec::rotate_master_password() {
+            Ok((old, new)) => {
+                let mut user = self.persisted_config.clone();
+                let failed = credentials::reencrypt_credentials(&mut user, old.as_deref(), &new);
+                self.has_master_password = true;
+                self.apply_user_config(user);
+                self.save_current_config();
+                self.status = if failed.is_empty() {
+                    "主密码已更新，下次同步时重新写入 settings.xml 中的密码".to_string()
+                } else {
+                    format!("主密码已更新，以下服务器的密码无法迁移，请重新输入: {}", failed.join("、"))
+                };
+            }
+            Err(e) => self.status = format!("无法更新主密码: {}", e),
+        }
+    }
+
+    /// 导出当前配置，或从文件/链接导入他人分享的配置
+    fn draw_share_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.export_include_secrets, "导出时包含密码和令牌");
+        ui.horizontal(|ui| {
+            if ui.button("导出到文件").clicked() {
+                let path = share::default_export_path();
+                self.status = match share::export_config(&self.persisted_config, self.export_include_secrets)
+                    .and_then(|content| share::write_export(&path, &content))
+                {
+                    Ok(_) => format!("配置已导出到 {}", path.display()),
+                    Err(e) => format!("导出失败: {}", e),
+                };
+            }
+            if ui.button("复制分享链接").clicked() {
+                match share::export_link(&self.persisted_config, self.export_include_secrets) {
+                    Ok(link) => {
+                        ui.output_mut(|o| o.copied_text = link);
+                        self.status = "分享链接已复制到剪贴板".to_string();
+                    }
+                    Err(e) => self.status = format!("导出失败: {}", e),
+                }
+            }
+        });
+
+        ui.add_space(8.0);
+        ui.add_sized(
+            [ui.available_width(), 28.0],
+            egui::TextEdit::singleline(&mut self.import_source)
+                .hint_text("配置文件路径或 msettings:// 链接")
+                .vertical_align(egui::Align::Center)
+        );
+        if ui.button("预览导入").clicked() {
+            match share::load_import(&self.import_source, &self.persisted_config) {
+                Ok(pending) => self.pending_import = Some(pending),
+                Err(e) => self.status = format!("无法导入配置: {}", e),
+            }
+        }
+    }
+
+    /// 导入前的变更预览，确认后才写入配置
+    fn draw_import_preview(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.pending_import else {
+            return;
+        };
+
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new("导入配置")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(format!("来源: {}", pending.source))
+                        .size(12.0)
+                        .color(SECONDARY_TEXT_COLOR)
+                );
+                ui.add_space(8.0);
+                if pending.changes.is_empty() {
+                    ui.label("导入的配置与当前配置相同");
+                } else {
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for change in &pending.changes {
+                            ui.label(egui::RichText::new(format!("• {}", redact::redact(change))).size(12.0).color(TEXT_COLOR));
+                        }
+                    });
+                }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!pending.changes.is_empty(), egui::Button::new("应用")).clicked() {
+                        apply = true;
+                    }
+                    if ui.button("取消").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if apply {
+            if let Some(pending) = self.pending_import.take() {
+                self.apply_import(pending.config);
+            }
+        } else if cancel {
+            self.pending_import = None;
+        }
+    }
+
+    /// 应用导入的用户配置并保存
+    fn apply_import(&mut self, mut imported: AppConfig) {
+        // 导入的明文凭据密码用本机主密码加密
+        if let Err(e) = credentials::encrypt_plaintext_credentials(&mut imported) {
+            self.status = format!("无法导入配置: {}", e);
+            return;
+        }
+        self.has_master_password = matches!(mavensec::master_password(), Ok(Some(_)));
+        self.apply_user_config(imported);
+        self.save_current_config();
+        self.status = "配置已导入".to_string();
+    }
+
+    /// 替换用户配置，环境变量/命令行覆盖和管理员策略仍然生效
+    fn apply_user_config(&mut self, user: AppConfig) {
+        self.persisted_config = user.clone();
+
+        let mut effective = user;
+        self.overrides.apply(&mut effective);
+        policy::current().apply(&mut effective);
+        self.active_profile = effective.active_index();
+        self.profiles = effective.profiles;
+        self.enable_scheduler = effective.enable_scheduler;
+        self.minimize_to_background = effective.minimize_to_background;
+        self.notifications = effective.notifications;
+        self.webhooks = effective.webhooks;
+        if self.status_endpoint != effective.status_endpoint {
+            self.status_endpoint = effective.status_endpoint;
+            self.restart_status_server();
+        }
+        self.history_target = 0;
+        if let Ok(mut shared) = self.shared_state.lock() {
+            shared.enable_scheduler = self.enable_scheduler;
+            shared.notifications = self.notifications.clone();
+            shared.webhooks = self.webhooks.clone();
+        }
+        self.saved_profiles = self.profiles.clone();
+        self.sync_profiles();
+        self.check_local_modifications();
+    }
+
+    /// 需要监听外部修改的文件：配置文件和当前环境的目标文件
+    fn watched_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![paths::config_file()];
+        files.extend(self.profile().targets.iter().filter_map(|t| targets::expand_path(&t.path).ok()));
+        files
+    }
+
+    /// 处理文件监听事件：配置文件被修改时重新加载，目标文件被修改时检查是否偏离同步的版本
+    fn handle_file_changes(&mut self, ctx: &egui::Context) {
+        if self.watcher.is_none() {
+            self.watcher = Some(watch::FileWatcher::new(ctx.clone()));
+            self.check_local_modifications();
+        }
+        let files = self.watched_files();
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        watcher.watch(files);
+        let changed = watcher.changed_files();
+        if changed.is_empty() {
+            return;
+        }
+
+        if changed.contains(&paths::config_file()) {
+            self.reload_config();
+        }
+        if changed.iter().any(|path| *path != paths::config_file()) {
+            self.check_local_modifications();
+        }
+    }
+
+    /// 配置文件被外部修改后重新加载，内容与当前一致（如本程序自己保存）时忽略
+    fn reload_config(&mut self) {
+        match config::reload_user_config() {
+            Ok(user) if user != self.persisted_config => {
+                log::info!("Config file changed on disk, reloading");
+                self.apply_user_config(user);
+                self.status = "配置文件已被修改，已重新加载".to_string();
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::warn!("Ignoring invalid config file change: {}", e);
+                self.config_warning = Some(format!("配置文件被外部修改，但内容有误，暂未重新加载: {}", e));
+            }
+        }
+    }
+
+    /// 检查当前环境的目标文件是否被本地修改，按各目标的处理方式提示或自动恢复，并写入历史记录
+    fn check_local_modifications(&mut self) {
+        let profile = self.profile().clone();
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let mut modified = Vec::new();
+        let mut records = Vec::new();
+
+        for target in &profile.targets {
+            if target.enforcement == Enforcement::Off || !targets::is_locally_modified(&profile.id, target) {
+                continue;
+            }
+            let summary = targets::diff_summary(&profile.id, target);
+            if target.enforcement == Enforcement::Restore {
+                match targets::apply_last_good(&profile.id, target) {
+                    Ok(_) => records.push((target.id.clone(), format!("{}: 自动恢复本地修改成功 - {}", now, summary))),
+                    Err(e) => {
+                        // 恢复失败时退回提示，由用户处理
+                        records.push((target.id.clone(), format!("{}: 自动恢复本地修改失败 - {}（{}）", now, e, summary)));
+                        modified.push(target.id.clone());
+                    }
+                }
+            } else {
+                if !self.locally_modified.contains(&target.id) {
+                    records.push((target.id.clone(), format!("{}: 检测到本地修改 - {}", now, summary)));
+                }
+                modified.push(target.id.clone());
+            }
+        }
+
+        if !records.is_empty() {
+            if let Ok(mut shared) = self.shared_state.lock() {
+                for (target_id, record) in records {
+                    history::push_record(&mut shared.histories, sync::history_key(&profile.id, &target_id), &record);
+                }
+                history::save_histories(&mut shared.histories);
+            }
+        }
+        self.locally_modified = modified;
+    }
+
+    /// 处理被本地修改的目标：保留、恢复同步的版本或查看差异
+    fn resolve_local_modification(&mut self, target_id: &str, action: DriftAction) {
+        let profile = self.profile().clone();
+        let Some(target) = profile.targets.iter().find(|t| t.id == target_id) else {
+            return;
+        };
+
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        let record = match action {
+            DriftAction::Keep => match targets::keep_local(&profile.id, target) {
+                Ok(_) => format!("{}: 保留本地修改", now),
+                Err(e) => format!("{}: 保留本地修改失败 - {}", now, e),
+            },
+            DriftAction::Restore => {
+                let summary = targets::diff_summary(&profile.id, target);
+                match targets::apply_last_good(&profile.id, target) {
+                    Ok(_) => format!("{}: 恢复同步的版本成功 - {}", now, summary),
+                    Err(e) => format!("{}: 恢复同步的版本失败 - {}", now, e),
+                }
+            }
+            DriftAction::Diff => {
+                match targets::diff_local(&profile.id, target) {
+                    Ok(diff) => self.drift_diff = Some((target.name.clone(), diff)),
+                    Err(e) => self.status = format!("无法比较 {}: {}", target.name, e),
+                }
+                return;
+            }
+        };
+
+        if let Ok(mut shared) = self.shared_state.lock() {
+            history::push_record(&mut shared.histories, sync::history_key(&profile.id, target_id), &record);
+            history::save_histories(&mut shared.histories);
+        }
+        self.check_local_modifications();
+    }
+
+    /// 下载地址被管理员策略或环境变量/命令行锁定时的说明
+    fn url_lock(&self) -> Option<String> {
+        if policy::current().locks_url() {
+            Some(policy::current().reason())
+        } else {
+            self.overrides.url.as_ref().map(|o| o.hint())
+        }
+    }
+
+    fn manifest_lock(&self) -> Option<String> {
+        if policy::current().locks_manifest_url() {
+            Some(policy::current().reason())
+        } else {
+            self.overrides.manifest_url.as_ref().map(|o| o.hint())
+        }
+    }
+
+    /// 被锁定的配置项下方的说明
+    fn draw_lock_hint(&self, ui: &mut egui::Ui, reason: &str) {
+        ui.label(
+            egui::RichText::new(format!("🔒 {}", reason))
+                .size(11.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+    }
+
+    fn draw_auth_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new("下载认证")
+                .size(13.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        ui.add_space(6.0);
+
+        let auth = &mut self.profiles[self.active_profile].auth;
+        let current = match auth {
+            SourceAuth::None => 0,
+            SourceAuth::Basic { .. } => 1,
+            SourceAuth::Bearer { .. } => 2,
+        };
+        let labels = ["无", "用户名密码", "Bearer Token"];
+        let mut selected = current;
+        egui::ComboBox::from_id_source("auth_kind")
+            .width(ui.available_width())
+            .selected_text(labels[current])
+            .show_ui(ui, |ui| {
+                for (i, label) in labels.iter().enumerate() {
+                    ui.selectable_value(&mut selected, i, *label);
+                }
+            });
+        if selected != current {
+            *auth = match selected {
+                1 => SourceAuth::Basic { username: String::new(), password: String::new() },
+                2 => SourceAuth::Bearer { token: String::new() },
+                _ => SourceAuth::None,
+            };
+        }
+
+        match auth {
+            SourceAuth::None => {}
+            SourceAuth::Basic { username, password } => {
+                ui.add_space(6.0);
+                ui.add_sized(
+                    [ui.available_width(), 30.0],
+                    egui::TextEdit::singleline(username)
+                        .hint_text("用户名")
+                        .vertical_align(egui::Align::Center)
+                );
+                ui.add_sized(
+                    [ui.available_width(), 30.0],
+                    egui::TextEdit::singleline(password)
+                        .hint_text("密码")
+                        .password(true)
+                        .vertical_align(egui::Align::Center)
+                );
+            }
+            SourceAuth::Bearer { token } => {
+                ui.add_space(6.0);
+                ui.add_sized(
+                    [ui.available_width(), 30.0],
+                    egui::TextEdit::singleline(token)
+                        .hint_text("Token")
+                        .password(true)
+                        .vertical_align(egui::Align::Center)
+                );
+            }
+        }
+    }
+
+    fn draw_target_settings(&mut self, ui: &mut egui::Ui) {
+        let target_lock = self.overrides.target_path.as_ref().map(|o| o.hint());
+        let target_url_lock = policy::current().locks_target_urls().then(|| policy::current().reason());
+        let profile = &mut self.profiles[self.active_profile];
+
+        // 清单模式下文件列表由清单决定，只读显示
+        if profile.uses_manifest() {
+            ui.label(
+                egui::RichText::new("文件列表由中央清单管理，每次更新时与清单对账")
+                    .size(12.0)
+                    .color(SECONDARY_TEXT_COLOR)
+            );
+            for target in &profile.targets {
+                ui.label(
+                    egui::RichText::new(if credentials::is_maven_settings(target) {
+                        format!(
+                            "{} → {}（本地修改: {}，镜像检查: {}）",
+                            target.name,
+                            target.path,
+                            target.enforcement.label(),
+                            target.mirror_check.label()
+                        )
+                    } else {
+                        format!("{} → {}（本地修改: {}）", target.name, target.path, target.enforcement.label())
+                    })
+                        .size(12.0)
+                        .color(TEXT_COLOR)
+                );
+            }
+            return;
+        }
+
+        let mut remove_target = None;
+
+        for (i, target) in profile.targets.iter_mut().enumerate() {
+            egui::CollapsingHeader::new(target.name.clone())
+                .id_source(("sync_target", i))
+                .show(ui, |ui| {
+                    ui.label(
+                        egui::RichText::new("来源地址（留空则使用上方下载地址）")
+                            .size(12.0)
+                            .color(SECONDARY_TEXT_COLOR)
+                    );
+                    ui.add_sized(
+                        [ui.available_width(), 28.0],
+                        egui::TextEdit::singleline(&mut target.url)
+                            .hint_text("https://...")
+                            .vertical_align(egui::Align::Center)
+                            .interactive(target_url_lock.is_none())
+                    );
+                    if let Some(reason) = &target_url_lock {
+                        ui.label(
+                            egui::RichText::new(format!("🔒 {}", reason))
+                                .size(11.0)
+                                .color(SECONDARY_TEXT_COLOR)
+                        );
+                    }
+                    ui.label(
+                        egui::RichText::new("目标路径（支持 ~ 和 ${MAVEN_HOME}）")
+                            .size(12.0)
+                            .color(SECONDARY_TEXT_COLOR)
+                    );
+                    // 第一个文件的路径可以被 MSETTINGS_TARGET / --target 覆盖
+                    let path_lock = target_lock.as_ref().filter(|_| i == 0);
+                    ui.add_sized(
+                        [ui.available_width(), 28.0],
+                        egui::TextEdit::singleline(&mut target.path)
+                            .vertical_align(egui::Align::Center)
+                            .interactive(path_lock.is_none())
+                    );
+                    if let Some(reason) = path_lock {
+                        ui.label(
+                            egui::RichText::new(format!("🔒 {}", reason))
+                                .size(11.0)
+                                .color(SECONDARY_TEXT_COLOR)
+                        );
+                    }
+                    if let Err(e) = targets::expand_path(&target.path) {
+                        ui.label(egui::RichText::new(e).size(11.0).color(ERROR_COLOR));
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new("校验")
+                                .size(12.0)
+                                .color(SECONDARY_TEXT_COLOR)
+                        );
+                        let current = match &target.validation {
+                            config::TargetValidation::None => "不校验".to_string(),
+                            config::TargetValidation::NonEmpty => "非空".to_string(),
+                            config::TargetValidation::Xml { root: Some(root) } => format!("XML <{}>", root),
+                            config::TargetValidation::Xml { root: None } => "XML".to_string(),
+                        };
+                        egui::ComboBox::from_id_source(("target_validation", i))
+                            .selected_text(current)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut target.validation, config::TargetValidation::None, "不校验");
+                                ui.selectable_value(&mut target.validation, config::TargetValidation::NonEmpty, "非空");
+                                ui.selectable_value(&mut target.validation, config::TargetValidation::Xml { root: None }, "XML");
+                            });
+                    });
+                    if let config::TargetValidation::Xml { root } = &mut target.validation {
+                        let mut root_text = root.clone().unwrap_or_default();
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new("根元素")
+                                    .size(12.0)
+                                    .color(SECONDARY_TEXT_COLOR)
+                            );
+                            ui.text_edit_singleline(&mut root_text);
+                        });
+                        *root = if root_text.trim().is_empty() { None } else { Some(root_text.trim().to_string()) };
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new("本地修改")
+                                .size(12.0)
+                                .color(SECONDARY_TEXT_COLOR)
+                        );
+                        egui::ComboBox::from_id_source(("target_enforcement", i))
+                            .selected_text(target.enforcement.label())
+                            .show_ui(ui, |ui| {
+                                for mode in [Enforcement::Off, Enforcement::Warn, Enforcement::Restore] {
+                                    ui.selectable_value(&mut target.enforcement, mode, mode.label());
+                                }
+                            });
+                    });
+
+                    if credentials::is_maven_settings(target) {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new("镜像检查")
+                                    .size(12.0)
+                                    .color(SECONDARY_TEXT_COLOR)
+                            );
+                            egui::ComboBox::from_id_source(("target_mirror_check", i))
+                                .selected_text(target.mirror_check.label())
+                                .show_ui(ui, |ui| {
+                                    for mode in [MirrorCheck::Off, MirrorCheck::Report, MirrorCheck::Require] {
+                                        ui.selectable_value(&mut target.mirror_check, mode, mode.label());
+                                    }
+                                });
+                        });
+                    }
+
+                    if toolchains::is_toolchains(target) {
+                        ui.checkbox(&mut target.merge_jdks, "合并本机检测到的 JDK");
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new("保留备份")
+                                .size(12.0)
+                                .color(SECONDARY_TEXT_COLOR)
+                        );
+                        ui.add(egui::DragValue::new(&mut target.backup_count).clamp_range(0..=config::MAX_BACKUP_COUNT));
+                        ui.label(
+                            egui::RichText::new("份")
+                                .size(12.0)
+                                .color(SECONDARY_TEXT_COLOR)
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new("名称")
+                                .size(12.0)
+                                .color(SECONDARY_TEXT_COLOR)
+                        );
+                        ui.text_edit_singleline(&mut target.name);
+                    });
+
+                    if ui.button("移除此文件").clicked() {
+                        remove_target = Some(i);
+                    }
+                });
+        }
+
+        if let Some(i) = remove_target {
+            profile.targets.remove(i);
+            self.history_target = 0;
+        }
+
+        // 从常用目标中添加，或添加自定义目标
+        let mut added = None;
+        egui::ComboBox::from_id_source("add_target")
+            .width(ui.available_width())
+            .selected_text("添加文件...")
+            .show_ui(ui, |ui| {
+                for preset in SyncTarget::presets() {
+                    if ui.selectable_label(false, &preset.name).clicked() {
+                        added = Some(preset);
+                    }
+                }
+                if ui.selectable_label(false, "自定义文件").clicked() {
+                    added = Some(SyncTarget {
+                        id: String::new(),
+                        name: "自定义文件".to_string(),
+                        url: String::new(),
+                        path: String::new(),
+                        validation: config::TargetValidation::NonEmpty,
+                        backup_count: 1,
+                        sha256: None,
+                        enforcement: config::Enforcement::Warn,
+                        mirror_check: config::MirrorCheck::Report,
+                        merge_jdks: false,
+                    });
+                }
+            });
+        if let Some(mut target) = added {
+            // 目标 id 在环境内唯一，用于历史记录和缓存文件名
+            let base = if target.id.is_empty() { "custom".to_string() } else { target.id.clone() };
+            target.id = base.clone();
+            let mut n = 2;
+            while profile.targets.iter().any(|t| t.id == target.id) {
+                target.id = format!("{}-{}", base, n);
+                n += 1;
+            }
+            profile.targets.push(target);
+        }
+    }
+
+    /// 配置文件被修复时的提示卡片，用户确认后隐藏
+    fn draw_config_warning(&mut self, ui: &mut egui::Ui) {
+        let Some(warning) = self.config_warning.clone() else {
+            return;
+        };
+
+        egui::Frame::none()
+            .fill(Color32::from_rgb(255, 243, 224))
+            .rounding(Rounding::same(10.0))
+            .stroke(Stroke::new(1.0, WARNING_COLOR))
+            .inner_margin(egui::Margin::same(12.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.label(
+                    egui::RichText::new(format!("⚠️ {}", redact::redact(&warning)))
+                        .size(12.0)
+                        .color(TEXT_COLOR)
+                );
+                ui.add_space(6.0);
+                if ui.button("知道了").clicked() {
+                    self.config_warning = None;
+                }
+            });
+        ui.add_space(16.0);
+    }
+
+    /// 配置诊断：当前环境每一项的生效值及来源
+    fn draw_diagnostics(&self, ui: &mut egui::Ui) {
+        let entries = self.overrides.diagnostics(self.profile(), &self.persisted_config);
+
+        egui::Frame::none()
+            .fill(CARD_COLOR)
+            .rounding(Rounding::same(10.0))
+            .stroke(Stroke::new(1.0, BORDER_COLOR))
+            .inner_margin(egui::Margin::same(12.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                egui::Grid::new("config_diagnostics")
+                    .num_columns(3)
+                    .spacing([12.0, 6.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["配置项", "生效值", "来源"] {
+                            ui.label(egui::RichText::new(header).size(12.0).strong().color(TEXT_COLOR));
+                        }
+                        ui.end_row();
+
+                        for entry in entries {
+                            ui.label(egui::RichText::new(entry.field).size(12.0).color(SECONDARY_TEXT_COLOR));
+                            ui.label(egui::RichText::new(redact::redact(&entry.value)).size(12.0).color(TEXT_COLOR));
+                            ui.label(egui::RichText::new(entry.source).size(12.0).color(PRIMARY_COLOR));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// 被本地修改的目标文件，提供保留、恢复和查看差异
+    fn draw_local_modifications(&mut self, ui: &mut egui::Ui) {
+        if self.locally_modified.is_empty() {
+            return;
+        }
+
+        let modified: Vec<(String, String, String)> = self
+            .profile()
+            .targets
+            .iter()
+            .filter(|t| self.locally_modified.contains(&t.id))
+            .map(|t| (t.id.clone(), t.name.clone(), t.path.clone()))
+            .collect();
+        let mut action = None;
+
+        egui::Frame::none()
+            .fill(Color32::from_rgb(255, 243, 224))
+            .rounding(Rounding::same(10.0))
+            .stroke(Stroke::new(1.0, WARNING_COLOR))
+            .inner_margin(egui::Margin::same(12.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                for (id, name, path) in &modified {
+                    ui.label(
+                        egui::RichText::new(format!("⚠️ {} 已被本地修改（{}）", name, path))
+                            .size(12.0)
+                            .color(TEXT_COLOR)
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("保留").clicked() {
+                            action = Some((id.clone(), DriftAction::Keep));
+                        }
+                        if ui.button("恢复").clicked() {
+                            action = Some((id.clone(), DriftAction::Restore));
+                        }
+                        if ui.button("查看差异").clicked() {
+                            action = Some((id.clone(), DriftAction::Diff));
+                        }
+                    });
+                }
+            });
+        ui.add_space(16.0);
+
+        if let Some((id, action)) = action {
+            self.resolve_local_modification(&id, action);
+        }
+    }
+
+    /// 读取当前环境的 settings.xml（没有时为 ~/.m2/settings.xml），打开查看器
+    fn inspect_settings(&mut self) {
+        let path = self
+            .profile()
+            .targets
+            .iter()
+            .filter(|t| credentials::is_maven_settings(t))
+            .find_map(|t| targets::expand_path(&t.path).ok())
+            .unwrap_or_else(|| paths::home_dir().join(".m2").join("settings.xml"));
+        let settings = inspect::load(&path);
+        self.inspected = Some((path, settings));
+    }
+
+    /// settings.xml 生效内容：本地仓库、profile、镜像及路由、服务器、代理
+    fn draw_inspector(&mut self, ctx: &egui::Context) {
+        let Some((path, settings)) = &self.inspected else {
+            return;
+        };
+        let path = path.clone();
+        let settings = settings.clone();
+
+        let mut open = true;
+        let mut refresh = false;
+        egui::Window::new("生效的 settings.xml")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([600.0, 480.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(path.display().to_string()).size(12.0).color(SECONDARY_TEXT_COLOR));
+                    if ui.small_button("刷新").clicked() {
+                        refresh = true;
+                    }
+                });
+                let settings = match settings {
+                    Ok(settings) => settings,
+                    Err(e) => {
+                        ui.label(egui::RichText::new(e).color(ERROR_COLOR));
+                        return;
+                    }
+                };
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let line = |ui: &mut egui::Ui, text: String| {
+                        ui.label(egui::RichText::new(redact::redact(&text)).size(12.0).color(TEXT_COLOR));
+                    };
+
+                    ui.add_space(6.0);
+                    ui.strong("基本");
+                    line(
+                        ui,
+                        format!(
+                            "本地仓库: {}",
+                            settings.local_repository.as_deref().unwrap_or("~/.m2/repository（默认）")
+                        ),
+                    );
+                    line(ui, format!("离线模式: {}", if settings.offline { "开启" } else { "关闭" }));
+
+                    ui.add_space(8.0);
+                    ui.strong("Profile");
+                    if settings.profiles.is_empty() {
+                        line(ui, "（无）".to_string());
+                    }
+                    for profile in &settings.profiles {
+                        let state = if profile.active { "✅ 已激活" } else { "未激活" };
+                        line(ui, format!("{} · {} · {} 个仓库", profile.id, state, profile.repositories.len()));
+                    }
+                    ui.label(
+                        egui::RichText::new("只计算 activeProfiles 和 activeByDefault，JDK、属性等激活条件未计算")
+                            .size(11.0)
+                            .color(SECONDARY_TEXT_COLOR)
+                    );
+
+                    ui.add_space(8.0);
+                    ui.strong("镜像");
+                    if settings.mirrors.is_empty() {
+                        line(ui, "（无，直接访问各仓库）".to_string());
+                    }
+                    for mirror in &settings.mirrors {
+                        line(ui, format!("{} · mirrorOf: {} · {}", mirror.id, mirror.mirror_of, mirror.url));
+                    }
+
+                    ui.add_space(8.0);
+                    ui.strong("仓库路由");
+                    for repo in settings.effective_repositories() {
+                        let kind = if repo.plugin { "插件仓库" } else { "仓库" };
+                        let route = match inspect::resolve(&settings.mirrors, &repo.id, &repo.url) {
+                            Some(mirror) => format!("镜像 {}（{}）", mirror.id, mirror.url),
+                            None => "直接访问".to_string(),
+                        };
+                        line(ui, format!("{} {}（{}）→ {}", kind, repo.id, repo.url, route));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.route_repo_id).hint_text("仓库 id").desired_width(120.0));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.route_repo_url)
+                                .hint_text("仓库地址（可选）")
+                                .desired_width(ui.available_width())
+                        );
+                    });
+                    let repo_id = self.route_repo_id.trim();
+                    if !repo_id.is_empty() {
+                        let route = match inspect::resolve(&settings.mirrors, repo_id, self.route_repo_url.trim()) {
+                            Some(mirror) => format!("→ 镜像 {}（{}）", mirror.id, mirror.url),
+                            None => "→ 没有匹配的镜像，直接访问仓库".to_string(),
+                        };
+                        line(ui, route);
+                    }
+
+                    ui.add_space(8.0);
+                    ui.strong("服务器");
+                    if settings.servers.is_empty() {
+                        line(ui, "（无）".to_string());
+                    }
+                    for server in &settings.servers {
+                        let key = if server.private_key { " · 私钥 ***" } else { "" };
+                        let user = if server.username.is_empty() { "（无用户名）" } else { &server.username };
+                        line(ui, format!("{} · {} · {}{}", server.id, user, server.password.label(), key));
+                    }
+
+                    ui.add_space(8.0);
+                    ui.strong("代理");
+                    if settings.proxies.is_empty() {
+                        line(ui, "（无）".to_string());
+                    }
+                    for proxy in &settings.proxies {
+                        let state = if proxy.active { "生效" } else { "未启用" };
+                        let protocol = if proxy.protocol.is_empty() { "http" } else { &proxy.protocol };
+                        let mut text = format!("{} · {} · {}://{}:{}", proxy.id, state, protocol, proxy.host, proxy.port);
+                        if !proxy.username.is_empty() {
+                            text.push_str(&format!(" · 用户 {}", proxy.username));
+                        }
+                        if !proxy.non_proxy_hosts.is_empty() {
+                            text.push_str(&format!(" · 不走代理: {}", proxy.non_proxy_hosts));
+                        }
+                        line(ui, text);
+                    }
+                });
+            });
+        if !open {
+            self.inspected = None;
+        } else if refresh {
+            self.inspect_settings();
+        }
+    }
+
+    /// 统一 diff 的每一行，新增为绿色、删除为红色
+    fn draw_diff_lines(ui: &mut egui::Ui, diff: &str) {
+        for line in diff.lines() {
+            let color = if line.starts_with('+') && !line.starts_with("+++") {
+                SUCCESS_COLOR
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                ERROR_COLOR
+            } else {
+                TEXT_COLOR
+            };
+            ui.label(egui::RichText::new(line).monospace().size(12.0).color(color));
+        }
+    }
+
+    /// 本地调整的预览：每条规则的执行说明和调整前后的差异
+    fn draw_transform_preview(&mut self, ctx: &egui::Context) {
+        let Some(preview) = &self.transform_preview else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new("本地调整预览")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([560.0, 400.0])
+            .show(ctx, |ui| match preview {
+                Ok((diff, log)) => {
+                    for line in log {
+                        ui.label(egui::RichText::new(format!("• {}", redact::redact(line))).size(12.0).color(TEXT_COLOR));
+                    }
+                    ui.separator();
+                    egui::ScrollArea::both().show(ui, |ui| {
+                        if diff.is_empty() {
+                            ui.label("调整后内容没有变化");
+                        } else {
+                            Self::draw_diff_lines(ui, diff);
+                        }
+                    });
+                }
+                Err(e) => {
+                    ui.label(egui::RichText::new(redact::redact(e)).color(ERROR_COLOR));
+                }
+            });
+        if !open {
+            self.transform_preview = None;
+        }
+    }
+
+    /// 同步的版本与本地文件的差异
+    fn draw_drift_diff(&mut self, ctx: &egui::Context) {
+        let Some((name, diff)) = &self.drift_diff else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new(format!("{} 的本地修改", name))
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([560.0, 400.0])
+            .show(ctx, |ui| {
+                egui::ScrollArea::both().show(ui, |ui| {
+                    Self::draw_diff_lines(ui, diff);
+                });
+            });
+        if !open {
+            self.drift_diff = None;
+        }
+    }
+
+    fn draw_right_panel(&mut self, ui: &mut egui::Ui) {
+        self.draw_config_warning(ui);
+        self.draw_local_modifications(ui);
+
+        // 状态显示
+        self.draw_section_title(ui, "运行状态");
+        self.draw_status_card(ui);
+
+        if policy::current().exists() {
+            ui.add_space(8.0);
+            ui.label(
+                egui::RichText::new(format!("🔒 本机已启用管理员策略：{}", policy::current().reason()))
+                    .size(12.0)
+                    .color(SECONDARY_TEXT_COLOR)
+            );
+        }
+        if !self.overrides.is_empty() {
+            ui.add_space(8.0);
+            ui.label(
+                egui::RichText::new("⚙ 部分配置由环境变量或命令行临时指定，不会保存到配置文件")
+                    .size(12.0)
+                    .color(SECONDARY_TEXT_COLOR)
+            );
+        }
+
+        ui.add_space(8.0);
+        let diagnostics_label = if self.show_diagnostics { "收起配置诊断" } else { "查看配置诊断" };
+        if ui.link(diagnostics_label).clicked() {
+            self.show_diagnostics = !self.show_diagnostics;
+        }
+        if self.show_diagnostics {
+            ui.add_space(8.0);
+            self.draw_diagnostics(ui);
+        }
+        if ui.link("查看生效的 settings.xml").clicked() {
+            self.inspect_settings();
+        }
+
+        ui.add_space(16.0);
+
+        // 下次执行时间显示
+        if self.scheduler_running && self.next_update_time.is_some() {
+            self.draw_next_update_card(ui);
+            ui.add_space(16.0);
+        }
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.right_tab, RightTab::History, "执行历史");
+            ui.selectable_value(&mut self.right_tab, RightTab::Logs, "运行日志");
+        });
+        ui.add_space(8.0);
+
+        match self.right_tab {
+            RightTab::History => {
+                self.draw_probe_report(ui);
+                // 执行历史
+                self.draw_history_section(ui);
+            }
+            RightTab::Logs => self.draw_log_viewer(ui),
+        }
+    }
+
+    /// 运行日志，按级别筛选，可复制诊断信息用于反馈问题
+    fn draw_log_viewer(&mut self, ui: &mut egui::Ui) {
+        let entries: Vec<logging::Entry> = logging::recent()
+            .into_iter()
+            .filter(|entry| entry.level <= self.log_level)
+            .collect();
+
+        ui.horizontal(|ui| {
+            ui.label("📜");
+            ui.add_space(6.0);
+            ui.label(
+                egui::RichText::new("运行日志")
+                    .size(14.0)
+                    .color(TEXT_COLOR)
+                    .strong()
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("复制诊断信息").clicked() {
+                    let report = self.diagnostics_report(&entries);
+                    ui.output_mut(|o| o.copied_text = report);
+                    self.status = "诊断信息已复制".to_string();
+                }
+                let selected = LOG_LEVELS
+                    .iter()
+                    .find(|(level, _)| *level == self.log_level)
+                    .map_or("信息", |(_, label)| *label);
+                egui::ComboBox::from_id_source("log_level")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (level, label) in LOG_LEVELS {
+                            ui.selectable_value(&mut self.log_level, level, label);
+                        }
+                    });
+            });
+        });
+        ui.label(
+            egui::RichText::new(format!("日志文件: {}（可用 RUST_LOG 调整记录的级别）", logging::log_file().display()))
+                .size(11.0)
+                .color(SECONDARY_TEXT_COLOR)
+        );
+        ui.add_space(8.0);
+
+        egui::Frame::none()
+            .fill(BACKGROUND_COLOR)
+            .rounding(Rounding::same(10.0))
+            .stroke(Stroke::new(1.0, BORDER_COLOR))
+            .inner_margin(egui::Margin::same(10.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        if entries.is_empty() {
+                            ui.label(egui::RichText::new("暂无日志").size(13.0).color(SECONDARY_TEXT_COLOR));
+                        }
+                        for entry in &entries {
+                            let color = match entry.level {
+                                log::Level::Error => ERROR_COLOR,
+                                log::Level::Warn => WARNING_COLOR,
+                                log::Level::Info => TEXT_COLOR,
+                                log::Level::Debug | log::Level::Trace => SECONDARY_TEXT_COLOR,
+                            };
+                            ui.label(egui::RichText::new(entry.line()).size(11.0).monospace().color(color));
+                        }
+                    });
+            });
+    }
+
+    /// 反馈问题时附带的诊断信息：版本、路径、生效配置和最近的日志，均已脱敏
+    fn diagnostics_report(&self, entries: &[logging::Entry]) -> String {
+        let mut lines = vec![
+            format!(
+                "MSettings {} ({}/{})",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ),
+            format!("配置文件: {}", paths::config_file().display()),
+            format!("日志文件: {}", logging::log_file().display()),
+            format!(
+                "管理员策略: {}",
+                if policy::current().exists() { policy::current().reason() } else { "未启用".to_string() }
+            ),
+            format!("当前环境: {}", self.profile().name),
+            format!("状态: {}", redact::redact(&self.status)),
+            String::new(),
+            "[生效配置]".to_string(),
+        ];
+        for entry in self.overrides.diagnostics(self.profile(), &self.persisted_config) {
+            lines.push(format!("{} = {}（{}）", entry.field, redact::redact(&entry.value), entry.source));
+        }
+        lines.push(String::new());
+        lines.push("[最近日志]".to_string());
+        let skip = entries.len().saturating_sub(DIAGNOSTIC_LOG_LINES);
+        lines.extend(entries[skip..].iter().map(logging::Entry::line));
+        lines.join("\n")
+    }
+
+    /// 历史中所选文件最近一次同步时的镜像检查结果
+    fn draw_probe_report(&self, ui: &mut egui::Ui) {
+        let Some((target_id, name)) = self.history_sources().get(self.history_target).cloned() else {
+            return;
+        };
+        let key = sync::history_key(&self.profile().id, &target_id);
+        let Some(results) = self
+            .shared_state
+            .lock()
+            .ok()
+            .and_then(|shared| shared.probe_reports.get(&key).cloned())
+        else {
+            return;
+        };
+
+        egui::Frame::none()
+            .fill(CARD_COLOR)
+            .rounding(Rounding::same(10.0))
+            .stroke(Stroke::new(1.0, Color32::from_rgba_unmultiplied(0, 0, 0, 30)))
+            .inner_margin(egui::Margin::same(12.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.label(
+                    egui::RichText::new(format!("🌐 {} 的镜像检查", name))
+                        .size(13.0)
+                        .color(TEXT_COLOR)
+                        .strong()
+                );
+                ui.add_space(6.0);
+                for result in &results {
+                    let endpoint = &result.endpoint;
+                    let primary = if endpoint.primary { "（主镜像）" } else { "" };
+                    let (icon, detail, color) = match &result.result {
+                        Ok(status) => ("✅", format!("HTTP {} · {} ms", status, result.elapsed.as_millis()), SUCCESS_COLOR),
+                        Err(e) => ("❌", redact::redact(e), ERROR_COLOR),
+                    };
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} {} {}{} · {} · {}",
+                            icon,
+                            endpoint.kind.label(),
+                            endpoint.id,
+                            primary,
+                            redact::redact(&endpoint.url),
+                            detail
+                        ))
+                        .size(12.0)
+                        .color(color)
+                    );
+                }
+            });
+        ui.add_space(16.0);
+    }
+
+    fn start_update_task(&mut self, ctx: &egui::Context) {
+        // 立即执行一次更新：改用异步版本
+        self.perform_immediate_update_async(ctx, None);
+
+        if self.enable_scheduler && !self.scheduler_running {
+            // 设置下次更新时间
+            let now = chrono::Local::now();
+            self.next_update_time = Some(now + chrono::Duration::hours(self.profile().interval_hours as i64));
+
+            // 启动定时任务
+            self.scheduler_running = true;
+            self.running = true;
+            self.status = "定时任务已启动".to_string();
+
+            // 重置停止信号
+            if let Ok(mut stop) = self.stop_signal.lock() {
+                *stop = false;
+            }
+
+            let shared_state = Arc::clone(&self.shared_state);
+            let stop_signal = Arc::clone(&self.stop_signal);
+            let ctx_clone = ctx.clone();
+
+            thread::spawn(move || {
                 loop {
                     // 检查停止信号
                     if let Ok(should_stop) = stop_signal.lock() {
@@ -1051,7 +3143,7 @@ impl AppState {
                             if !shared.enable_scheduler {
                                 break; // 如果定时任务被关闭，退出循环
                             }
-                            shared.interval_hours * 3600
+                            shared.profile.interval_hours * 3600
                         } else {
                             3600 // 默认1小时
                         }
@@ -1059,6 +3151,9 @@ impl AppState {
                     
                     // 防止间隔时间过小
                     let interval_secs = interval_secs.max(3600); // 最小1小时
+                    if let Ok(mut shared) = shared_state.lock() {
+                        shared.next_run = Some(chrono::Local::now() + chrono::Duration::seconds(interval_secs as i64));
+                    }
 
                     // 分段休眠，每秒检查一次停止信号，避免长时间阻塞
                     for _ in 0..interval_secs {
@@ -1070,18 +3165,22 @@ impl AppState {
                         }
                     }
 
-                    // 执行定时更新
-                    {
+                    // 执行定时更新，下载期间不持有锁，避免阻塞界面
+                    let profile = match shared_state.lock() {
+                        Ok(shared) if shared.enable_scheduler => {
+                            Some((shared.profile.clone(), webhooks::effective(&shared.webhooks)))
+                        }
+                        _ => None,
+                    };
+                    if let Some((profile, webhooks)) = profile {
+                        let run = sync::sync_profile(&profile);
+                        let report =
+                            (!webhooks.is_empty()).then(|| webhooks::Report::from_run(&profile, &run, "定时更新"));
                         if let Ok(mut shared) = shared_state.lock() {
-                            if shared.enable_scheduler {
-                                let now = chrono::Local::now();
-                                let result = download_and_replace(&shared.url);
-                                let record = match result {
-                                    Ok(_) => format!("{}: 定时更新成功", now.format("%Y-%m-%d %H:%M:%S")),
-                                    Err(e) => format!("{}: 定时更新失败 - {}", now.format("%Y-%m-%d %H:%M:%S"), e),
-                                };
-                                shared.history.push(record);
-                            }
+                            shared.record_run(&profile.id, run, "定时更新");
+                        }
+                        if let Some(report) = report {
+                            webhooks::deliver_all(&webhooks, &report);
                         }
                     }
 
@@ -1118,6 +3217,18 @@ impl eframe::App for AppState {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // 处理窗口显示状态
         self.handle_window_visibility(ctx);
+
+        // 处理命令行转发的命令（窗口隐藏时同样需要响应）
+        self.handle_forwarded_commands(ctx);
+        self.sync_profiles();
+        if self.has_unsaved_profiles() {
+            // 编辑停止后没有新的输入也要刷新一次，才能把修改保存下来
+            ctx.request_repaint_after(PROFILE_SAVE_DELAY);
+        }
+        self.publish_status();
+
+        // 配置文件和目标文件的外部修改
+        self.handle_file_changes(ctx);
         
         // 处理窗口关闭事件 - 如果启用了后台运行，最小化到后台而不是退出
         if ctx.input(|i| i.viewport().close_requested()) {
@@ -1126,7 +3237,10 @@ impl eframe::App for AppState {
                 ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
                 return;
             } else {
-                // 如果没有启用后台运行，正常退出
+                // 如果没有启用后台运行，保存尚未保存的修改后正常退出
+                if self.has_unsaved_profiles() {
+                    self.save_current_config();
+                }
                 std::process::exit(0);
             }
         }
@@ -1187,7 +3301,11 @@ impl eframe::App for AppState {
                                             egui::vec2(left_width, ui.available_height()),
                                             egui::Layout::top_down(egui::Align::LEFT),
                                             |ui| {
-                                                self.draw_left_panel(ui, ctx);
+                                                egui::ScrollArea::vertical()
+                                                    .id_source("left_panel")
+                                                    .show(ui, |ui| {
+                                                        self.draw_left_panel(ui, ctx);
+                                                    });
                                             }
                                         );
 
@@ -1212,77 +3330,40 @@ impl eframe::App for AppState {
                     }
                 );
 
-                // 同步 shared_state 到本地 history，更新 UI
+                // 同步 shared_state 中当前环境的历史记录，更新 UI
                 if let Ok(shared) = self.shared_state.lock() {
-                    self.history = shared.history.clone();
-                }
-                // 新增代码：
-                if self.url != self.previous_url {
-                    save_url_to_config(&self.url);
-                    self.previous_url = self.url.clone();
+                    let profile_id = &self.profiles[self.active_profile].id;
+                    self.history = self
+                        .history_sources()
+                        .get(self.history_target)
+                        .and_then(|(id, _)| shared.histories.get(&sync::history_key(profile_id, id)))
+                        .cloned()
+                        .unwrap_or_default();
                 }
             });
-    }
-}
-
-fn download_and_replace(url: &str) -> Result<(), String> {
-    // URL 验证
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err("Invalid URL: must start with http:// or https://".to_string());
-    }
-    
-    // 添加超时和用户代理
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .user_agent("AutoUpdateMavenSettings/1.0")
-        .build()
-        .map_err(|e| e.to_string())?;
-    
-    let resp = client.get(url).send().map_err(|e| e.to_string())?;
-    if !resp.status().is_success() {
-        return Err(format!("HTTP 错误: {}", resp.status()));
-    }
-    let content = resp.bytes().map_err(|e| e.to_string())?;
 
-    let path = get_m2_settings_path();
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-    
-    // 备份现有文件
-    if path.exists() {
-        let backup_path = path.with_extension("xml.backup");
-        if let Err(e) = fs::copy(&path, &backup_path) {
-            eprintln!("Warning: Failed to create backup: {}", e);
-        }
+        self.draw_import_preview(ctx);
+        self.draw_drift_diff(ctx);
+        self.draw_transform_preview(ctx);
+        self.draw_inspector(ctx);
     }
-    
-    let mut file = fs::File::create(&path).map_err(|e| e.to_string())?;
-    file.write_all(&content).map_err(|e| e.to_string())?;
-    Ok(())
 }
 
 // 通知已存在的实例显示窗口
 fn show_existing_window() {
     // 创建一个信号文件来通知已存在的实例
-    let signal_path = get_signal_file_path();
+    let signal_path = paths::signal_file();
+    if let Some(parent) = signal_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
     if let Ok(mut file) = std::fs::File::create(&signal_path) {
         let _ = file.write_all(b"show_window");
     }
 }
 
-// 获取信号文件路径
-fn get_signal_file_path() -> PathBuf {
-    #[cfg(target_os = "windows")]
-    let base = std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string());
-    #[cfg(not(target_os = "windows"))]
-    let base = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(base).join(".msettings_show_signal")
-}
-
 // 检查是否有显示窗口的信号
 fn check_show_signal() -> bool {
-    let signal_path = get_signal_file_path();
+    let signal_path = paths::signal_file();
     if signal_path.exists() {
         let _ = std::fs::remove_file(&signal_path); // 移除信号文件
         return true;
@@ -1307,8 +3388,8 @@ fn load_window_icon() -> Option<egui::IconData> {
                     
                     return Some(egui::IconData {
                         rgba: pixels,
-                        width: width as u32,
-                        height: height as u32,
+                        width,
+                        height,
                     });
                 }
             }
@@ -1318,28 +3399,46 @@ fn load_window_icon() -> Option<egui::IconData> {
 }
 
 fn main() {
+    // 发布版本没有控制台，诊断信息写入日志文件
+    logging::init();
+
     // 设置panic handler用于更好的调试信息
     std::panic::set_hook(Box::new(|panic_info| {
-        eprintln!("PANIC: {}", panic_info);
+        log::error!("PANIC: {}", panic_info);
         if let Some(location) = panic_info.location() {
-            eprintln!("Panic occurred in file '{}' at line {}", location.file(), location.line());
+            log::error!("Panic occurred in file '{}' at line {}", location.file(), location.line());
         }
         if let Some(s) = panic_info.payload().downcast_ref::<&str>() {
-            eprintln!("Panic payload: {}", s);
+            log::error!("Panic payload: {}", s);
         }
+        log::logger().flush();
     }));
     
-    // 打印启动信息用于调试
-    eprintln!("Starting MSettings application...");
+    // 解析命令行参数
+    let command = match ipc::parse_args(std::env::args().skip(1)) {
+        Ok(ipc::CliAction::Run(command)) => command,
+        Ok(ipc::CliAction::Help) => {
+            ipc::attach_parent_console();
+            println!("{}", ipc::USAGE);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            ipc::attach_parent_console();
+            eprintln!("{}\n\n{}", e, ipc::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    log::info!("Starting MSettings application {}...", env!("CARGO_PKG_VERSION"));
     
     // 单实例检测 - 添加错误处理
     let instance = match SingleInstance::new("msettings-maven-updater") {
         Ok(instance) => {
-            eprintln!("Single instance created successfully");
+            log::info!("Single instance created successfully");
             Some(instance)
         }
         Err(e) => {
-            eprintln!("Failed to create single instance: {}", e);
+            log::warn!("Failed to create single instance: {}", e);
             // 如果单实例创建失败，继续运行但不做单实例检查
             None
         }
@@ -1347,15 +3446,40 @@ fn main() {
     
     if let Some(ref instance) = instance {
         if !instance.is_single() {
-            // 如果已有实例在运行，尝试通知它显示窗口
-            show_existing_window();
-            std::process::exit(0);
+            if command.is_empty() {
+                // 如果已有实例在运行，尝试通知它显示窗口
+                show_existing_window();
+                std::process::exit(0);
+            }
+
+            // 带参数启动时，交给已运行的实例执行并输出结果
+            ipc::attach_parent_console();
+            match ipc::forward_to_running_instance(&command) {
+                Ok(response) if response.success => {
+                    println!("{}", response.message);
+                    std::process::exit(0);
+                }
+                Ok(response) => {
+                    eprintln!("{}", response.message);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to forward command: {}", e);
+                    std::process::exit(1);
+                }
+            }
         }
     }
     
-    eprintln!("Creating application state...");
-    let app = AppState::default();
-    eprintln!("Application state created successfully");
+    // 旧版本放在主目录下的文件迁移到配置/状态目录
+    paths::migrate_legacy_files();
+
+    log::info!("Creating application state...");
+    let mut app = AppState::default();
+    if !command.is_empty() {
+        app.startup_commands.push(ipc::ForwardedCommand::local(command));
+    }
+    log::info!("Application state created successfully");
     
     let mut viewport_builder = egui::ViewportBuilder::default()
         .with_inner_size([760.0, 750.0])  // 增加高度以适应后台运行开关
@@ -1369,7 +3493,7 @@ fn main() {
             viewport_builder = viewport_builder.with_icon(icon);
         }
         None => {
-            eprintln!("Warning: Could not load window icon, using default");
+            log::warn!("Could not load window icon, using default");
         }
     }
     
@@ -1383,10 +3507,11 @@ fn main() {
         native_options,
         Box::new(|cc| {
             setup_custom_fonts(&cc.egui_ctx);
+            ipc::wake_on_commands(cc.egui_ctx.clone());
             Box::new(app)
         }),
     ) {
-        eprintln!("Failed to run application: {}", e);
+        log::error!("Failed to run application: {}", e);
         std::process::exit(1);
     }
 }
\ No newline at end of file
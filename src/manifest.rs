@@ -0,0 +1,238 @@
+// manifest.rs
+// 中央清单：由一个 JSON/TOML 文件描述需要同步的所有文件
+//
+// 示例（JSON）：
+// {
+//   "min_app_version": "0.1.0",
+//   "recommended_interval_hours": 8,
+//   "files": [
+//     { "url": "settings.xml", "path": "~/.m2/settings.xml", "sha256": "..." }
+//   ]
+// }
+// 文件地址可以是相对于清单地址的路径。
+
+use serde::Deserialize;
+
+use crate::config::{Enforcement, MirrorCheck, SyncTarget, TargetValidation};
+
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub min_app_version: Option<String>,
+    #[serde(default)]
+    pub recommended_interval_hours: Option<u64>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ManifestFile {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub url: String,
+    pub path: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub validation: Option<TargetValidation>,
+    #[serde(default)]
+    pub backup_count: Option<usize>,
+    #[serde(default)]
+    pub enforcement: Enforcement,
+    #[serde(default)]
+    pub mirror_check: MirrorCheck,
+    #[serde(default)]
+    pub merge_jdks: bool,
+}
+
+/// 解析清单，先按 JSON 再按 TOML 尝试
+pub fn parse(content: &[u8]) -> Result<Manifest, String> {
+    let text = std::str::from_utf8(content).map_err(|_| "清单不是有效的 UTF-8 文本".to_string())?;
+    match serde_json::from_str::<Manifest>(text) {
+        Ok(manifest) => Ok(manifest),
+        Err(json_err) => toml::from_str::<Manifest>(text)
+            .map_err(|toml_err| format!("无法解析清单（JSON: {}; TOML: {}）", json_err, toml_err)),
+    }
+}
+
+/// 比较点分版本号，缺失的部分按 0 处理
+fn version_at_least(current: &str, required: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim()
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| {
+                part.chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect::<String>()
+                    .parse()
+                    .unwrap_or(0)
+            })
+            .collect()
+    };
+    let (mut current, mut required) = (parse(current), parse(required));
+    let len = current.len().max(required.len());
+    current.resize(len, 0);
+    required.resize(len, 0);
+    current >= required
+}
+
+impl Manifest {
+    /// 检查本程序版本是否满足清单要求
+    pub fn check_app_version(&self) -> Result<(), String> {
+        let current = env!("CARGO_PKG_VERSION");
+        match &self.min_app_version {
+            Some(required) if !version_at_least(current, required) => Err(format!(
+                "清单要求程序版本至少为 {}，当前为 {}，请先升级",
+                required, current
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 把清单中的文件转换为同步目标，相对地址以清单地址为基准
+    pub fn targets(&self, manifest_url: &str) -> Result<Vec<SyncTarget>, String> {
+        let base = reqwest::Url::parse(manifest_url).map_err(|e| format!("清单地址无效: {}", e))?;
+        let mut targets: Vec<SyncTarget> = Vec::new();
+
+        for (i, file) in self.files.iter().enumerate() {
+            let url = base
+                .join(&file.url)
+                .map_err(|e| format!("清单中的文件地址无效 {}: {}", file.url, e))?;
+            let file_name = url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("file")
+                .to_string();
+            let id = file.id.clone().unwrap_or_else(|| format!("manifest-{}", i + 1));
+            if targets.iter().any(|t| t.id == id) {
+                return Err(format!("清单中的文件 id 重复: {}", id));
+            }
+
+            // 未指定校验规则时，.xml 文件至少要求格式正确
+            let validation = file.validation.clone().unwrap_or_else(|| {
+                if file.path.ends_with(".xml") {
+                    TargetValidation::Xml { root: None }
+                } else {
+                    TargetValidation::NonEmpty
+                }
+            });
+
+            let target = SyncTarget {
+                id,
+                name: file.name.clone().unwrap_or(file_name),
+                url: url.to_string(),
+                path: file.path.clone(),
+                validation,
+                backup_count: file.backup_count.unwrap_or(1),
+                sha256: file.sha256.as_ref().map(|sum| sum.trim().to_lowercase()),
+                enforcement: file.enforcement,
+                mirror_check: file.mirror_check,
+                merge_jdks: file.merge_jdks,
+            };
+            // 清单来自远程，与界面中手动添加的文件做同样的检查
+            target.validate().map_err(|e| format!("清单中的{}", e))?;
+            targets.push(target);
+        }
+        Ok(targets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST_URL: &str = "https://repo.example.com/maven/manifest.json";
+
+    fn targets(json: &str) -> Result<Vec<SyncTarget>, String> {
+        parse(json.as_bytes())?.targets(MANIFEST_URL)
+    }
+
+    #[test]
+    fn parses_json_and_toml() {
+        let json = parse(
+            br#"{"min_app_version": "0.1", "recommended_interval_hours": 8,
+                 "files": [{ "url": "settings.xml", "path": "~/.m2/settings.xml" }]}"#,
+        )
+        .unwrap();
+        assert_eq!(json.recommended_interval_hours, Some(8));
+        assert_eq!(json.files.len(), 1);
+
+        let toml = parse(
+            b"recommended_interval_hours = 4\n[[files]]\nid = \"toolchains\"\nurl = \"toolchains.xml\"\npath = \"~/.m2/toolchains.xml\"\nmerge_jdks = true\n",
+        )
+        .unwrap();
+        assert_eq!(toml.recommended_interval_hours, Some(4));
+        assert!(toml.files[0].merge_jdks);
+
+        assert!(parse(b"{\"files\": ").is_err());
+        assert!(parse(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn checks_required_app_version() {
+        let manifest = |version: &str| Manifest {
+            min_app_version: Some(version.to_string()),
+            recommended_interval_hours: None,
+            files: Vec::new(),
+        };
+        assert!(manifest("0.0.1").check_app_version().is_ok());
+        assert!(manifest(env!("CARGO_PKG_VERSION")).check_app_version().is_ok());
+        assert!(manifest("999.0").check_app_version().unwrap_err().contains("请先升级"));
+        assert!(version_at_least("1.2", "1.2.0"));
+        assert!(version_at_least("v1.10.0", "1.9.3"));
+        assert!(!version_at_least("1.2.9", "1.3"));
+    }
+
+    #[test]
+    fn resolves_relative_urls_against_manifest() {
+        let targets = targets(
+            r#"{"files": [
+                { "url": "settings.xml", "path": "~/.m2/settings.xml" },
+                { "url": "../shared/toolchains.xml", "path": "~/.m2/toolchains.xml" },
+                { "id": "config", "url": "https://other.example.com/maven.config", "path": "~/.mvn/maven.config" }
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(targets[0].url, "https://repo.example.com/maven/settings.xml");
+        assert_eq!(targets[0].id, "manifest-1");
+        assert_eq!(targets[0].name, "settings.xml");
+        assert_eq!(targets[0].validation, TargetValidation::Xml { root: None });
+        assert_eq!(targets[1].url, "https://repo.example.com/shared/toolchains.xml");
+        assert_eq!(targets[2].url, "https://other.example.com/maven.config");
+        assert_eq!(targets[2].validation, TargetValidation::NonEmpty);
+    }
+
+    #[test]
+    fn rejects_duplicate_ids() {
+        let error = targets(
+            r#"{"files": [
+                { "id": "settings", "url": "a.xml", "path": "~/.m2/a.xml" },
+                { "id": "settings", "url": "b.xml", "path": "~/.m2/b.xml" }
+            ]}"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("id 重复"));
+    }
+
+    #[test]
+    fn rejects_ids_that_escape_data_dir() {
+        for id in ["../../x", "a/b", "a\\b", "..", "C:x"] {
+            let json = format!(
+                r#"{{"files": [{{ "id": "{}", "url": "settings.xml", "path": "~/.m2/settings.xml" }}]}}"#,
+                id.replace('\\', "\\\\")
+            );
+            assert!(targets(&json).unwrap_err().contains("id 无效"), "{}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let too_many_backups = r#"{"files": [{ "url": "settings.xml", "path": "~/.m2/settings.xml", "backup_count": 1000 }]}"#;
+        assert!(targets(too_many_backups).unwrap_err().contains("备份份数"));
+        let bad_scheme = r#"{"files": [{ "url": "file:///etc/passwd", "path": "~/.m2/settings.xml" }]}"#;
+        assert!(targets(bad_scheme).unwrap_err().contains("来源地址无效"));
+    }
+}
@@ -0,0 +1,199 @@
+// mavensec.rs
+// Maven 密码加密（与 `mvn --encrypt-password` 兼容）以及 settings-security.xml 中的主密码
+//
+// 加密格式（plexus-cipher）：AES-128-CBC，密钥和 IV 为 SHA-256(密码 + 8 字节盐) 的前后 16 字节，
+// 结果为 base64(盐 | 填充长度 | 密文 | 随机填充)，写入配置时用花括号包裹：`{...}`。
+// 服务器密码用主密码加密，主密码本身用固定口令 "settings.security" 加密后保存在 settings-security.xml。
+
+use std::fs;
+use std::path::PathBuf;
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use base64::Engine;
+use rand::{Rng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::{paths, permissions};
+
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+const SALT_SIZE: usize = 8;
+const CHUNK_SIZE: usize = 16;
+/// Maven 加密主密码时使用的固定口令
+const MASTER_PASSPHRASE: &str = "settings.security";
+
+fn key_and_iv(password: &str, salt: &[u8]) -> ([u8; 16], [u8; 16]) {
+    let digest = Sha256::new().chain_update(password.as_bytes()).chain_update(salt).finalize();
+    let mut key = [0u8; 16];
+    let mut iv = [0u8; 16];
+    key.copy_from_slice(&digest[..16]);
+    iv.copy_from_slice(&digest[16..32]);
+    (key, iv)
+}
+
+/// 加密并返回 Maven 格式的 `{...}`
+pub fn encrypt(plain: &str, password: &str) -> String {
+    let mut rng = rand::thread_rng();
+    let mut salt = [0u8; SALT_SIZE];
+    rng.fill_bytes(&mut salt);
+
+    let (key, iv) = key_and_iv(password, &salt);
+    let encrypted = Aes128CbcEnc::new(&key.into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(plain.as_bytes());
+
+    let pad_len = CHUNK_SIZE - (SALT_SIZE + encrypted.len() + 1) % CHUNK_SIZE;
+    let mut all = Vec::with_capacity(SALT_SIZE + 1 + encrypted.len() + pad_len);
+    all.extend_from_slice(&salt);
+    all.push(pad_len as u8);
+    all.extend_from_slice(&encrypted);
+    all.extend((0..pad_len).map(|_| rng.gen::<u8>()));
+
+    format!("{{{}}}", base64::engine::general_purpose::STANDARD.encode(all))
+}
+
+/// 解密 `{...}` 格式的值
+pub fn decrypt(value: &str, password: &str) -> Result<String, String> {
+    let inner = unwrap_braces(value).ok_or_else(|| "不是 Maven 加密格式".to_string())?;
+    let all = base64::engine::general_purpose::STANDARD
+        .decode(inner)
+        .map_err(|e| format!("加密内容不是有效的 base64: {}", e))?;
+    if all.len() < SALT_SIZE + 1 + CHUNK_SIZE {
+        return Err("加密内容长度不正确".to_string());
+    }
+
+    let salt = &all[..SALT_SIZE];
+    let pad_len = all[SALT_SIZE] as usize;
+    let end = all
+        .len()
+        .checked_sub(pad_len)
+        .filter(|end| *end > SALT_SIZE + 1)
+        .ok_or_else(|| "加密内容长度不正确".to_string())?;
+    let (key, iv) = key_and_iv(password, salt);
+    let plain = Aes128CbcDec::new(&key.into(), &iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(&all[SALT_SIZE + 1..end])
+        .map_err(|_| "解密失败，主密码不正确".to_string())?;
+    String::from_utf8(plain).map_err(|_| "解密结果不是有效的文本".to_string())
+}
+
+fn unwrap_braces(value: &str) -> Option<&str> {
+    value.trim().strip_prefix('{')?.strip_suffix('}')
+}
+
+/// 是否为加密后的值（`{...}`），`${...}` 属性引用不算
+pub fn is_encrypted(value: &str) -> bool {
+    let value = value.trim();
+    !value.starts_with("${") && unwrap_braces(value).is_some_and(|inner| !inner.is_empty())
+}
+
+/// Maven 默认的 settings-security.xml 位置
+pub fn security_file() -> PathBuf {
+    paths::home_dir().join(".m2").join("settings-security.xml")
+}
+
+/// 读取并解密主密码，没有 settings-security.xml 时返回 None
+///
+/// 支持 `<relocation>` 指向另一个文件。
+pub fn master_password() -> Result<Option<String>, String> {
+    let mut path = security_file();
+    for _ in 0..4 {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => return Ok(None),
+        };
+        let doc = roxmltree::Document::parse(&content)
+            .map_err(|e| format!("{} 格式错误: {}", path.display(), e))?;
+        let child_text = |name: &str| {
+            doc.root_element()
+                .children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .map(|t| t.trim().to_string())
+        };
+
+        if let Some(relocation) = child_text("relocation") {
+            path = crate::targets::expand_path(&relocation)?;
+            continue;
+        }
+        return match child_text("master") {
+            Some(master) if is_encrypted(&master) => decrypt(&master, MASTER_PASSPHRASE).map(Some),
+            Some(master) if !master.is_empty() => Ok(Some(master)),
+            _ => Ok(None),
+        };
+    }
+    Err("settings-security.xml 的 relocation 层级过多".to_string())
+}
+
+/// 随机生成的主密码
+fn generate_password() -> String {
+    const CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789";
+    let mut rng = rand::thread_rng();
+    (0..24).map(|_| CHARS[rng.gen_range(0..CHARS.len())] as char).collect()
+}
+
+/// 写入新的主密码，已有文件先备份
+pub fn write_master_password(master: &str) -> Result<(), String> {
+    let path = security_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if path.exists() {
+        let backup = path.with_file_name(format!(
+            "settings-security.xml.{}",
+            chrono::Local::now().format("%Y%m%d%H%M%S")
+        ));
+        permissions::copy_private(&path, &backup).map_err(|e| format!("无法备份 {}: {}", path.display(), e))?;
+    }
+    let content = format!(
+        "<settingsSecurity>\n  <master>{}</master>\n</settingsSecurity>\n",
+        encrypt(master, MASTER_PASSPHRASE)
+    );
+    permissions::write_private(&path, content).map_err(|e| format!("无法写入 {}: {}", path.display(), e))
+}
+
+/// 返回主密码，没有时生成一个并写入 settings-security.xml
+pub fn ensure_master_password() -> Result<String, String> {
+    if let Some(master) = master_password()? {
+        return Ok(master);
+    }
+    let master = generate_password();
+    write_master_password(&master)?;
+    Ok(master)
+}
+
+/// 生成新的主密码，返回 (旧主密码, 新主密码)，调用方负责用新主密码重新加密已保存的凭据
+pub fn rotate_master_password() -> Result<(Option<String>, String), String> {
+    let old = master_password()?;
+    let new = generate_password();
+    write_master_password(&new)?;
+    Ok((old, new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 固定盐和填充、按 plexus-cipher PBECipher 的步骤用 JCE（AES/CBC/PKCS5Padding）生成的密文，
+    // 与 `mvn --encrypt-master-password` / `--encrypt-password` 的输出格式相同
+    const MASTER_ENCRYPTED: &str = "{AQIDBAUGBwgHKWUQo2yrtwjf0DdofYAOpVpaWlpaWlo=}";
+    const SERVER_ENCRYPTED: &str = "{CQoLDA0ODxAHu430f4ytw5BZ6Zt26SohuaWlpaWlpaU=}";
+
+    #[test]
+    fn decrypts_plexus_cipher_output() {
+        let master = decrypt(MASTER_ENCRYPTED, MASTER_PASSPHRASE).unwrap();
+        assert_eq!(master, "master-secret");
+        assert_eq!(decrypt(SERVER_ENCRYPTED, &master).unwrap(), "p@ssw0rd-中文");
+        assert!(decrypt(SERVER_ENCRYPTED, "wrong").is_err());
+    }
+
+    #[test]
+    fn encrypts_with_random_salt_in_same_format() {
+        let first = encrypt("p@ssw0rd", "master-secret");
+        let second = encrypt("p@ssw0rd", "master-secret");
+        assert_ne!(first, second);
+        assert!(is_encrypted(&first));
+        let inner = unwrap_braces(&first).unwrap();
+        let all = base64::engine::general_purpose::STANDARD.decode(inner).unwrap();
+        assert_eq!(all.len() % CHUNK_SIZE, 0);
+        assert_eq!(decrypt(&first, "master-secret").unwrap(), "p@ssw0rd");
+    }
+}
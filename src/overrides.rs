@@ -0,0 +1,277 @@
+// overrides.rs
+// 环境变量和命令行参数对配置的临时覆盖，只在本次运行中生效，不会写回配置文件
+//
+// 优先级：默认值 → 用户配置 → 环境变量 → 命令行参数 → 管理员策略
+//
+//   MSETTINGS_URL            --url <地址>            settings.xml 下载地址
+//   MSETTINGS_INTERVAL       --interval <小时>       自动更新间隔
+//   MSETTINGS_TARGET         --target <路径>         第一个同步文件的目标路径
+//   MSETTINGS_MANIFEST_URL   --manifest-url <地址>   中央清单地址（空字符串表示不使用清单）
+
+use crate::config::{AppConfig, SyncProfile};
+use crate::ipc::CliCommand;
+use crate::{paths, policy, targets};
+
+/// 一项覆盖值及其来源说明，如 `环境变量 MSETTINGS_URL`
+#[derive(Debug, Clone)]
+pub struct Override<T> {
+    pub value: T,
+    pub source: String,
+}
+
+impl<T> Override<T> {
+    /// 界面中被覆盖项下方的说明
+    pub fn hint(&self) -> String {
+        format!("由{}临时指定，不会保存到配置文件", self.source)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub url: Option<Override<String>>,
+    pub interval_hours: Option<Override<u64>>,
+    pub target_path: Option<Override<String>>,
+    pub manifest_url: Option<Override<String>>,
+}
+
+pub fn parse_url(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.starts_with("http://") || value.starts_with("https://") {
+        Ok(value.to_string())
+    } else {
+        Err(format!("无效的下载地址: {}", value))
+    }
+}
+
+pub fn parse_interval(value: &str) -> Result<u64, String> {
+    match value.trim().parse::<u64>() {
+        Ok(hours) if (1..=168).contains(&hours) => Ok(hours),
+        _ => Err(format!("更新间隔应为 1 到 168 之间的小时数: {}", value)),
+    }
+}
+
+pub fn parse_target(value: &str) -> Result<String, String> {
+    targets::expand_path(value)?;
+    Ok(value.trim().to_string())
+}
+
+pub fn parse_manifest_url(value: &str) -> Result<String, String> {
+    if value.trim().is_empty() {
+        Ok(String::new())
+    } else {
+        parse_url(value)
+    }
+}
+
+fn from_env_var<T>(
+    name: &str,
+    parse: fn(&str) -> Result<T, String>,
+    warnings: &mut Vec<String>,
+) -> Option<Override<T>> {
+    let value = std::env::var(name).ok()?;
+    match parse(&value) {
+        Ok(value) => Some(Override {
+            value,
+            source: format!("环境变量 {}", name),
+        }),
+        Err(e) => {
+            log::warn!("Ignoring invalid {}: {}", name, value);
+            warnings.push(format!("已忽略环境变量 {}（{}）", name, e));
+            None
+        }
+    }
+}
+
+fn from_cli<T: Clone>(flag: &str, value: &Option<T>) -> Option<Override<T>> {
+    value.as_ref().map(|value| Override {
+        value: value.clone(),
+        source: format!("命令行 {}", flag),
+    })
+}
+
+impl Overrides {
+    /// 读取环境变量中的覆盖值，无效的值被忽略并返回提示
+    pub fn from_env() -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let overrides = Self {
+            url: from_env_var("MSETTINGS_URL", parse_url, &mut warnings),
+            interval_hours: from_env_var("MSETTINGS_INTERVAL", parse_interval, &mut warnings),
+            target_path: from_env_var("MSETTINGS_TARGET", parse_target, &mut warnings),
+            manifest_url: from_env_var("MSETTINGS_MANIFEST_URL", parse_manifest_url, &mut warnings),
+        };
+        (overrides, warnings)
+    }
+
+    /// 合并命令行参数（参数已在解析时校验），命令行优先于环境变量
+    pub fn merge_cli(&mut self, command: &CliCommand) {
+        if let Some(url) = from_cli("--url", &command.url) {
+            self.url = Some(url);
+        }
+        if let Some(interval) = from_cli("--interval", &command.interval_hours) {
+            self.interval_hours = Some(interval);
+        }
+        if let Some(target) = from_cli("--target", &command.target) {
+            self.target_path = Some(target);
+        }
+        if let Some(manifest_url) = from_cli("--manifest-url", &command.manifest_url) {
+            self.manifest_url = Some(manifest_url);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.url.is_none() && self.interval_hours.is_none() && self.target_path.is_none() && self.manifest_url.is_none()
+    }
+
+    /// 把覆盖值应用到配置中（所有环境）
+    pub fn apply(&self, config: &mut AppConfig) {
+        for profile in &mut config.profiles {
+            self.apply_to_profile(profile);
+        }
+    }
+
+    pub fn apply_to_profile(&self, profile: &mut SyncProfile) {
+        if let Some(url) = &self.url {
+            profile.url = url.value.clone();
+        }
+        if let Some(interval) = &self.interval_hours {
+            profile.interval_hours = interval.value;
+        }
+        if let Some(manifest_url) = &self.manifest_url {
+            profile.manifest_url = manifest_url.value.clone();
+        }
+        // 清单模式下目标列表由清单决定
+        if let Some(target_path) = &self.target_path {
+            if !profile.uses_manifest() {
+                if let Some(target) = profile.targets.first_mut() {
+                    target.path = target_path.value.clone();
+                }
+            }
+        }
+    }
+
+    /// 保存配置前把被覆盖的项还原为用户自己的值
+    pub fn restore_user_values(&self, to_save: &mut AppConfig, user: &AppConfig) {
+        let defaults = SyncProfile::default();
+        for profile in &mut to_save.profiles {
+            let original = user.profiles.iter().find(|p| p.id == profile.id);
+            if self.url.is_some() {
+                profile.url = original.map(|p| p.url.clone()).unwrap_or_default();
+            }
+            if self.interval_hours.is_some() {
+                profile.interval_hours = original.map_or(defaults.interval_hours, |p| p.interval_hours);
+            }
+            if self.manifest_url.is_some() {
+                profile.manifest_url = original.map(|p| p.manifest_url.clone()).unwrap_or_default();
+            }
+            if self.target_path.is_some() {
+                if let Some(target) = profile.targets.first_mut() {
+                    let original_path = original
+                        .and_then(|p| p.targets.iter().find(|t| t.id == target.id))
+                        .map(|t| t.path.clone());
+                    if let Some(path) = original_path {
+                        target.path = path;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 生效配置中每一项的值和来源，供诊断页面展示
+    pub fn diagnostics(&self, profile: &SyncProfile, user: &AppConfig) -> Vec<DiagnosticEntry> {
+        let policy = policy::current();
+        let defaults = SyncProfile::default();
+        let original = user.profiles.iter().find(|p| p.id == profile.id);
+        // 未被覆盖的项：与默认值相同视为默认值，否则来自用户配置
+        let base_source = |is_default: bool| {
+            if is_default {
+                "默认值".to_string()
+            } else {
+                "用户配置".to_string()
+            }
+        };
+
+        let url_source = if policy.locks_url() {
+            "管理员策略".to_string()
+        } else if let Some(url) = &self.url {
+            url.source.clone()
+        } else {
+            base_source(profile.url == defaults.url)
+        };
+
+        let interval_raised = self
+            .interval_hours
+            .as_ref()
+            .map_or(original.map_or(defaults.interval_hours, |p| p.interval_hours), |i| i.value)
+            < policy.min_interval();
+        let interval_source = if interval_raised {
+            "管理员策略".to_string()
+        } else if let Some(interval) = &self.interval_hours {
+            interval.source.clone()
+        } else {
+            base_source(profile.interval_hours == defaults.interval_hours)
+        };
+
+        let manifest_source = if policy.locks_manifest_url() {
+            "管理员策略".to_string()
+        } else if let Some(manifest_url) = &self.manifest_url {
+            manifest_url.source.clone()
+        } else {
+            base_source(profile.manifest_url == defaults.manifest_url)
+        };
+
+        let mut entries = vec![
+            DiagnosticEntry::new("环境", format!("{} ({})", profile.name, profile.id), "用户配置".to_string()),
+            DiagnosticEntry::new("下载地址", profile.url.clone(), url_source),
+            DiagnosticEntry::new("自动更新间隔", format!("{} 小时", profile.interval_hours), interval_source),
+            DiagnosticEntry::new("中央清单地址", profile.manifest_url.clone(), manifest_source),
+        ];
+
+        for (i, target) in profile.targets.iter().enumerate() {
+            let source = if profile.uses_manifest() {
+                "中央清单".to_string()
+            } else if let (0, Some(target_path)) = (i, &self.target_path) {
+                target_path.source.clone()
+            } else {
+                let default_path = defaults.targets.iter().find(|t| t.id == target.id).map(|t| &t.path);
+                base_source(default_path == Some(&target.path))
+            };
+            let value = match targets::expand_path(&target.path) {
+                Ok(expanded) => format!("{} → {}", target.path, expanded.display()),
+                Err(e) => format!("{}（{}）", target.path, e),
+            };
+            entries.push(DiagnosticEntry::new(&format!("目标路径 {}", target.name), value, source));
+        }
+
+        entries.push(DiagnosticEntry::new(
+            "配置文件",
+            paths::config_file().display().to_string(),
+            String::new(),
+        ));
+        entries.push(DiagnosticEntry::new(
+            "管理员策略文件",
+            match &policy.path {
+                Some(path) => path.display().to_string(),
+                None => "未启用".to_string(),
+            },
+            String::new(),
+        ));
+        entries
+    }
+}
+
+/// 诊断页面中的一行：配置项、生效值、来源
+pub struct DiagnosticEntry {
+    pub field: String,
+    pub value: String,
+    pub source: String,
+}
+
+impl DiagnosticEntry {
+    fn new(field: &str, value: String, source: String) -> Self {
+        Self {
+            field: field.to_string(),
+            value,
+            source,
+        }
+    }
+}
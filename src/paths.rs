@@ -0,0 +1,154 @@
+// paths.rs
+// 程序自身文件的存放位置
+//
+// Linux:   配置 $XDG_CONFIG_HOME/msettings，状态 $XDG_STATE_HOME/msettings，缓存 $XDG_CACHE_HOME/msettings
+// macOS:   配置和状态 ~/Library/Application Support/MSettings，缓存 ~/Library/Caches/MSettings
+// Windows: 配置 %APPDATA%\MSettings，状态和缓存 %LOCALAPPDATA%\MSettings
+//
+// 旧版本直接把 .msettings_* 文件放在主目录下，首次运行时迁移到新位置。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const APP_DIR_NAME: &str = "MSettings";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const APP_DIR_NAME: &str = "msettings";
+
+/// 用户主目录，HOME 未设置时由系统账户信息得到，最后才退回临时目录
+pub fn home_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(std::env::temp_dir)
+}
+
+/// 配置目录
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| home_dir().join(".config"))
+        .join(APP_DIR_NAME)
+}
+
+/// 状态目录：历史记录、备份、各环境最近一次成功的文件、进程间信号
+pub fn state_dir() -> PathBuf {
+    #[cfg(target_os = "macos")]
+    let base = dirs::data_dir();
+    #[cfg(target_os = "windows")]
+    let base = dirs::data_local_dir();
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let base = dirs::state_dir();
+
+    base.unwrap_or_else(|| home_dir().join(".local").join("state"))
+        .join(APP_DIR_NAME)
+}
+
+/// 缓存目录：下载内容
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| home_dir().join(".cache"))
+        .join(APP_DIR_NAME)
+}
+
+pub fn config_file() -> PathBuf {
+    config_dir().join("config.json")
+}
+
+pub fn history_file() -> PathBuf {
+    state_dir().join("history.json")
+}
+
+/// 每个环境保存最近一次成功下载文件的目录
+pub fn profile_data_dir(profile_id: &str) -> PathBuf {
+    state_dir().join("profiles").join(profile_id)
+}
+
+/// 某个目标文件的备份目录，按目标路径区分
+pub fn backup_dir(target_path: &Path) -> PathBuf {
+    let key: String = target_path
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    state_dir().join("backups").join(key.trim_matches('_'))
+}
+
+/// 下载内容的缓存目录
+pub fn download_cache_dir(profile_id: &str) -> PathBuf {
+    cache_dir().join("downloads").join(profile_id)
+}
+
+/// 运行日志目录
+pub fn log_dir() -> PathBuf {
+    state_dir().join("logs")
+}
+
+pub fn signal_file() -> PathBuf {
+    state_dir().join("show_signal")
+}
+
+pub fn commands_dir() -> PathBuf {
+    state_dir().join("commands")
+}
+
+// 移动文件或目录，跨文件系统时退回复制后删除
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            move_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir_all(from)
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+/// 把旧版本放在主目录下的文件迁移到新位置，已存在的新文件不会被覆盖
+pub fn migrate_legacy_files() {
+    let home = home_dir();
+
+    let mut moves = vec![
+        (home.join(".msettings_config.json"), config_file()),
+        (home.join(".msettings_profiles"), state_dir().join("profiles")),
+    ];
+
+    // 旧配置文件的修复/迁移备份
+    if let Ok(entries) = fs::read_dir(&home) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(suffix) = name.strip_prefix(".msettings_config.json.") {
+                moves.push((entry.path(), config_dir().join(format!("config.json.{}", suffix))));
+            }
+        }
+    }
+
+    for (from, to) in moves {
+        if !from.exists() || to.exists() {
+            continue;
+        }
+        match move_path(&from, &to) {
+            Ok(_) => log::info!("Migrated {} -> {}", from.display(), to.display()),
+            Err(e) => log::warn!("Failed to migrate {}: {}", from.display(), e),
+        }
+    }
+
+    // 旧版本把 settings.xml 的备份放在 ~/.m2 下，作为新备份目录中的第一份
+    let settings = home.join(".m2").join("settings.xml");
+    let old_backup = home.join(".m2").join("settings.xml.backup");
+    let new_backup = backup_dir(&settings).join("settings.xml.backup");
+    if old_backup.exists() && !new_backup.exists() {
+        if let Err(e) = move_path(&old_backup, &new_backup) {
+            log::warn!("Failed to migrate {}: {}", old_backup.display(), e);
+        }
+    }
+
+    // 进程间信号只在运行期间有意义，直接清理
+    let _ = fs::remove_file(home.join(".msettings_show_signal"));
+    let _ = fs::remove_dir_all(home.join(".msettings_commands"));
+}
@@ -0,0 +1,195 @@
+// permissions.rs
+// 含凭据的文件（主目录下的 settings.xml 等、备份、历史、配置）只允许当前用户读写，
+// 其他同步目标（如项目的 .mvn/maven.config）保持原有权限
+//
+// Unix 上为 0600；Windows 上用 icacls 去掉继承的权限，只授予当前用户完全控制。
+// `fs::File::create` 受 umask 影响、`fs::copy` 会沿用源文件的权限，所以写入后统一再收紧一次。
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::AppConfig;
+use crate::{mavensec, paths, targets};
+
+/// 写入文件并限制为仅当前用户可读写
+pub fn write_private(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // 文件已存在时 mode 不生效，先收紧再写入内容
+    restrict(path)?;
+    file.write_all(content.as_ref())
+}
+
+/// 复制文件，副本仅当前用户可读写
+pub fn copy_private(from: &Path, to: &Path) -> io::Result<()> {
+    let content = fs::read(from)?;
+    write_private(to, content)
+}
+
+/// 把已有文件的权限收紧为仅当前用户
+#[cfg(unix)]
+pub fn restrict(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(windows)]
+pub fn restrict(path: &Path) -> io::Result<()> {
+    let user = std::env::var("USERNAME").map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let status = std::process::Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r"])
+        .arg(format!("{}:F", user))
+        .stdout(std::process::Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, format!("icacls 退出码 {}", status)))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn restrict(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// 除当前用户外，其他人是否也能访问该文件
+#[cfg(unix)]
+fn is_too_open(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.permissions().mode() & 0o077 != 0)
+}
+
+/// Windows 上继承来的权限（icacls 输出中的 `(I)`）通常包含其他用户
+#[cfg(windows)]
+fn is_too_open(path: &Path) -> bool {
+    std::process::Command::new("icacls")
+        .arg(path)
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("(I)"))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn is_too_open(_path: &Path) -> bool {
+    false
+}
+
+/// 目录下的所有文件（递归）
+fn files_in(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => files_in(&path, files),
+            Ok(kind) if kind.is_file() => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// 保存凭据的文件名：settings.xml 和 gradle.properties 中有服务器密码，settings-security.xml 中有主密码
+const CREDENTIAL_FILES: [&str; 3] = ["settings.xml", "settings-security.xml", "gradle.properties"];
+
+/// 同步目标是否需要只允许当前用户读写：只限主目录下保存凭据的文件
+///
+/// 项目中的 `.mvn/maven.config`、`${MAVEN_HOME}/conf/settings.xml` 等其他用户也要读取的文件保持原有权限。
+pub fn is_private_target(path: &Path) -> bool {
+    let holds_credentials = path
+        .file_name()
+        .is_some_and(|name| CREDENTIAL_FILES.iter().any(|file| name == *file));
+    holds_credentials && path.starts_with(paths::home_dir())
+}
+
+/// 写入同步目标：保存凭据的每用户文件仅当前用户可读写，其他文件沿用原有权限（新文件按系统默认）
+pub fn write_target_file(path: &Path, content: impl AsRef<[u8]>) -> io::Result<()> {
+    if is_private_target(path) {
+        write_private(path, content)
+    } else {
+        fs::write(path, content)
+    }
+}
+
+/// 需要保护的文件：程序自身的配置、状态和缓存，settings-security.xml，以及保存凭据的每用户同步目标
+fn sensitive_files(config: &AppConfig) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in [paths::config_dir(), paths::state_dir(), paths::cache_dir()] {
+        files_in(&dir, &mut files);
+    }
+    files.push(mavensec::security_file());
+    files.extend(
+        config
+            .profiles
+            .iter()
+            .flat_map(|profile| &profile.targets)
+            .filter_map(|target| targets::expand_path(&target.path).ok())
+            .filter(|path| is_private_target(path)),
+    );
+    files.retain(|file| file.is_file());
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// 启动时检查：权限过宽的文件被收紧，返回给用户的提示
+pub fn fix_existing(config: &AppConfig) -> Option<String> {
+    let mut fixed = Vec::new();
+    let mut failed = Vec::new();
+    for file in sensitive_files(config).into_iter().filter(|file| is_too_open(file)) {
+        log::warn!("{} is accessible by other users, restricting permissions", file.display());
+        match restrict(&file) {
+            Ok(_) => fixed.push(file.display().to_string()),
+            Err(e) => {
+                log::warn!("Failed to restrict permissions of {}: {}", file.display(), e);
+                failed.push(file.display().to_string());
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    if !fixed.is_empty() {
+        lines.push(format!("以下文件其他用户也能读取，已改为仅当前用户可访问：\n{}", fixed.join("\n")));
+    }
+    if !failed.is_empty() {
+        lines.push(format!("以下文件其他用户也能读取，且无法修改权限，请手动处理：\n{}", failed.join("\n")));
+    }
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_per_user_credential_files_are_private() {
+        let home = paths::home_dir();
+        assert!(is_private_target(&home.join(".m2").join("settings.xml")));
+        assert!(is_private_target(&home.join(".m2").join("settings-security.xml")));
+        assert!(is_private_target(&home.join(".gradle").join("gradle.properties")));
+        assert!(!is_private_target(&home.join("project").join(".mvn").join("maven.config")));
+        assert!(!is_private_target(&home.join(".m2").join("toolchains.xml")));
+        assert!(!is_private_target(Path::new("/opt/maven/conf/settings.xml")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shared_targets_keep_their_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("msettings-perm-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("maven.config");
+        fs::write(&path, "-B").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_target_file(&path, "-B -T 4").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o644);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "-B -T 4");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
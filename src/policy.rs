@@ -0,0 +1,279 @@
+// policy.rs
+// 管理员下发的全机策略，锁定的配置项用户无法在界面中修改
+//
+// 位置：Linux /etc/msettings/policy.json，
+//       macOS /Library/Application Support/MSettings/policy.json，
+//       Windows C:\ProgramData\MSettings\policy.json。
+// 位置固定，不读取环境变量，避免用户指向自己的文件绕过锁定。
+//
+// {
+//   "source_url": "https://maven.example.com/settings.xml",
+//   "min_interval_hours": 4,
+//   "allowed_hosts": ["maven.example.com", "*.corp.example.com"],
+//   "https_only": true,
+//   "required_signature_keys": ["<base64 ed25519 公钥>"],
+//   "webhooks": [{ "url": "https://hooks.example.com/msettings", "format": "json" }],
+//   "reason": "由 IT 部门统一管理"
+// }
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use base64::Engine;
+use serde::Deserialize;
+
+use crate::config::{AppConfig, SyncProfile, Webhook};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// 固定的 settings.xml 下载地址
+    pub source_url: Option<String>,
+    /// 固定的中央清单地址
+    pub manifest_url: Option<String>,
+    /// 自动更新间隔的下限
+    pub min_interval_hours: Option<u64>,
+    /// 允许下载的主机（可用 `*.example.com`），为空表示不限制
+    pub allowed_hosts: Vec<String>,
+    /// 是否只允许 HTTPS，设置后用户不能修改；未设置时默认开启
+    pub https_only: Option<bool>,
+    /// 下载内容必须由其中一个公钥签名（`<地址>.sig`，base64 编码的 ed25519 签名）
+    pub required_signature_keys: Vec<String>,
+    /// 每次同步后都发送结果的 Webhook，用户不能删除
+    pub webhooks: Vec<Webhook>,
+    /// 展示给用户的锁定原因
+    pub reason: Option<String>,
+
+    /// 策略文件位置，未找到策略文件时为 None
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// 策略文件存在但无法解析，此时拒绝同步
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+fn policy_file_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    let path = PathBuf::from("C:\\ProgramData\\MSettings\\policy.json");
+    #[cfg(target_os = "macos")]
+    let path = PathBuf::from("/Library/Application Support/MSettings/policy.json");
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let path = PathBuf::from("/etc/msettings/policy.json");
+
+    path
+}
+
+fn load() -> Policy {
+    load_from(policy_file_path())
+}
+
+fn load_from(path: PathBuf) -> Policy {
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Policy::default(),
+    };
+
+    let mut policy = match serde_json::from_str::<Policy>(&content) {
+        Ok(policy) => policy,
+        Err(e) => {
+            log::warn!("Failed to parse policy file {}: {}", path.display(), e);
+            Policy {
+                error: Some(format!("管理员策略文件无效（{}）: {}", path.display(), e)),
+                ..Policy::default()
+            }
+        }
+    };
+    log::info!("Loaded administrator policy from {}", path.display());
+    policy.path = Some(path);
+    policy
+}
+
+/// 本次运行使用的策略，首次调用时读取
+pub fn current() -> &'static Policy {
+    static POLICY: OnceLock<Policy> = OnceLock::new();
+    POLICY.get_or_init(load)
+}
+
+impl Policy {
+    pub fn exists(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// 锁定原因，界面中显示在只读项旁边
+    pub fn reason(&self) -> String {
+        self.reason
+            .clone()
+            .unwrap_or_else(|| "由管理员策略统一设置".to_string())
+    }
+
+    pub fn locks_url(&self) -> bool {
+        self.source_url.is_some()
+    }
+
+    /// 固定下载地址时清单地址同样锁定（未指定清单时为空），否则清单可以引入其他来源
+    pub fn locks_manifest_url(&self) -> bool {
+        self.manifest_url.is_some() || self.source_url.is_some()
+    }
+
+    /// 固定下载地址时各文件不能再单独指定来源地址
+    pub fn locks_target_urls(&self) -> bool {
+        self.source_url.is_some()
+    }
+
+    pub fn min_interval(&self) -> u64 {
+        self.min_interval_hours.unwrap_or(1).clamp(1, 168)
+    }
+
+    /// 把策略锁定的值应用到配置中（所有环境）
+    pub fn apply(&self, config: &mut AppConfig) {
+        for profile in &mut config.profiles {
+            self.apply_to_profile(profile);
+        }
+    }
+
+    pub fn apply_to_profile(&self, profile: &mut SyncProfile) {
+        if let Some(url) = &self.source_url {
+            profile.url = url.clone();
+        }
+        if self.locks_target_urls() {
+            // 文件的来源地址优先于下载地址，锁定时一律清空
+            for target in &mut profile.targets {
+                target.url.clear();
+            }
+        }
+        if self.locks_manifest_url() {
+            profile.manifest_url = self.manifest_url.clone().unwrap_or_default();
+        }
+        profile.interval_hours = profile.interval_hours.max(self.min_interval());
+    }
+
+    /// 保存配置前把被策略锁定的项还原为用户自己的值，策略撤销后用户设置仍然保留
+    pub fn restore_user_values(&self, to_save: &mut AppConfig, user: &AppConfig) {
+        for profile in &mut to_save.profiles {
+            let original = user.profiles.iter().find(|p| p.id == profile.id);
+            if self.locks_url() {
+                profile.url = original.map(|p| p.url.clone()).unwrap_or_default();
+            }
+            if self.locks_manifest_url() {
+                profile.manifest_url = original.map(|p| p.manifest_url.clone()).unwrap_or_default();
+            }
+            if self.locks_target_urls() {
+                for target in &mut profile.targets {
+                    target.url = original
+                        .and_then(|p| p.targets.iter().find(|t| t.id == target.id))
+                        .map(|t| t.url.clone())
+                        .unwrap_or_default();
+                }
+            }
+            if let Some(original) = original {
+                // 只因下限被抬高的间隔保存用户原来的值
+                if original.interval_hours < self.min_interval() && profile.interval_hours == self.min_interval() {
+                    profile.interval_hours = original.interval_hours;
+                }
+            }
+        }
+    }
+
+    /// 策略文件本身有问题时拒绝同步
+    pub fn check_valid(&self) -> Result<(), String> {
+        match &self.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn requires_signature(&self) -> bool {
+        !self.required_signature_keys.is_empty()
+    }
+
+    /// 用策略中的任意一个公钥验证下载内容的签名
+    pub fn verify_signature(&self, content: &[u8], signature: &[u8]) -> Result<(), String> {
+        use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let signature_bytes = engine
+            .decode(String::from_utf8_lossy(signature).trim())
+            .map_err(|e| format!("签名不是有效的 base64: {}", e))?;
+        let signature = Signature::from_slice(&signature_bytes).map_err(|e| format!("签名格式错误: {}", e))?;
+
+        for key in &self.required_signature_keys {
+            let Ok(key_bytes) = engine.decode(key.trim()) else {
+                log::warn!("Ignoring invalid signature key in policy: {}", key);
+                continue;
+            };
+            let Ok(key_bytes) = <[u8; 32]>::try_from(key_bytes.as_slice()) else {
+                log::warn!("Ignoring signature key with wrong length in policy: {}", key);
+                continue;
+            };
+            let Ok(verifying_key) = VerifyingKey::from_bytes(&key_bytes) else {
+                continue;
+            };
+            if verifying_key.verify(content, &signature).is_ok() {
+                return Ok(());
+            }
+        }
+        Err("签名验证失败：内容未由管理员策略要求的密钥签名".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_policy_from_given_path() {
+        let dir = std::env::temp_dir().join(format!("msettings-policy-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("policy.json");
+
+        assert!(!load_from(path.clone()).exists());
+
+        std::fs::write(&path, r#"{"source_url": "https://maven.example.com/settings.xml", "min_interval_hours": 4}"#).unwrap();
+        let policy = load_from(path.clone());
+        assert!(policy.exists() && policy.locks_url());
+        assert_eq!(policy.min_interval(), 4);
+        assert!(policy.check_valid().is_ok());
+
+        // 无法解析的策略文件拒绝同步，而不是当作没有策略
+        std::fs::write(&path, "{").unwrap();
+        assert!(load_from(path).check_valid().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn target_url_cannot_override_locked_source() {
+        let policy = Policy {
+            source_url: Some("https://maven.example.com/settings.xml".to_string()),
+            ..Policy::default()
+        };
+        let mut user = AppConfig::default();
+        user.profiles[0].targets[0].url = "https://evil.example.com/settings.xml".to_string();
+        user.profiles[0].manifest_url = "https://evil.example.com/manifest.json".to_string();
+
+        let mut effective = user.clone();
+        policy.apply(&mut effective);
+        let profile = &effective.profiles[0];
+        assert!(policy.locks_target_urls() && policy.locks_manifest_url());
+        assert!(!profile.uses_manifest());
+        assert_eq!(profile.source_url(&profile.targets[0]), "https://maven.example.com/settings.xml");
+
+        // 保存时还原用户原来的值，策略撤销后仍然可用
+        policy.restore_user_values(&mut effective, &user);
+        assert_eq!(effective.profiles[0].targets[0].url, "https://evil.example.com/settings.xml");
+        assert_eq!(effective.profiles[0].manifest_url, "https://evil.example.com/manifest.json");
+    }
+
+    #[test]
+    fn manifest_lock_alone_keeps_target_urls() {
+        let policy = Policy {
+            manifest_url: Some("https://maven.example.com/manifest.json".to_string()),
+            ..Policy::default()
+        };
+        let mut profile = SyncProfile::default();
+        profile.targets[0].url = "https://mirror.example.com/settings.xml".to_string();
+        policy.apply_to_profile(&mut profile);
+        assert!(!policy.locks_target_urls());
+        assert_eq!(profile.manifest_url, "https://maven.example.com/manifest.json");
+        assert_eq!(profile.targets[0].url, "https://mirror.example.com/settings.xml");
+    }
+}
@@ -0,0 +1,399 @@
+// probe.rs
+// 写入 settings.xml 前检查其中的镜像和仓库地址是否可访问
+//
+// 解析 `<mirrors>`、`<profiles>` 下的 `<repositories>` 和 `<pluginRepositories>`，
+// 对每个地址的基础路径发 HEAD 请求（服务器不支持 HEAD 时改用 GET），并限制超时。
+// 服务器有响应即视为可访问，401/403/404 也算（私有仓库的根路径常常如此），5xx 和连接失败算不可访问。
+// 主镜像是 mirrorOf 覆盖 central 的镜像，没有时取第一个镜像。
+
+use std::time::{Duration, Instant};
+
+use crate::config::MirrorCheck;
+use crate::redact::redact;
+
+/// 每个地址的超时时间
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndpointKind {
+    Mirror,
+    Repository,
+    PluginRepository,
+}
+
+impl EndpointKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EndpointKind::Mirror => "镜像",
+            EndpointKind::Repository => "仓库",
+            EndpointKind::PluginRepository => "插件仓库",
+        }
+    }
+}
+
+/// settings.xml 中的一个远程地址
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub kind: EndpointKind,
+    pub id: String,
+    pub url: String,
+    /// 是否为主镜像
+    pub primary: bool,
+}
+
+/// 一个地址的检查结果，可访问时为 HTTP 状态码
+#[derive(Debug, Clone)]
+pub struct ProbeResult {
+    pub endpoint: Endpoint,
+    pub result: Result<u16, String>,
+    pub elapsed: Duration,
+}
+
+impl ProbeResult {
+    pub fn reachable(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_text(node: roxmltree::Node, name: &str) -> String {
+    child(node, name)
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn children<'a, 'input>(
+    node: Option<roxmltree::Node<'a, 'input>>,
+    name: &'a str,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> + 'a {
+    node.into_iter()
+        .flat_map(|node| node.children())
+        .filter(move |n| n.has_tag_name(name))
+}
+
+/// mirrorOf 是否覆盖 central，例如 `*`、`central`、`external:*`，排除 `!central`
+fn covers_central(mirror_of: &str) -> bool {
+    let patterns: Vec<&str> = mirror_of.split(',').map(str::trim).collect();
+    !patterns.contains(&"!central")
+        && patterns
+            .iter()
+            .any(|p| matches!(*p, "*" | "central" | "external:*" | "external:http:*"))
+}
+
+/// 可以探测的地址：http(s) 且不含未解析的 `${...}` 属性
+fn is_probeable(url: &str) -> bool {
+    (url.starts_with("http://") || url.starts_with("https://")) && !url.contains("${")
+}
+
+/// 列出 settings.xml 中需要检查的地址，相同地址只保留一次
+pub fn endpoints(content: &str) -> Result<Vec<Endpoint>, String> {
+    let doc = roxmltree::Document::parse(content).map_err(|e| format!("XML 格式错误: {}", e))?;
+    let root = doc.root_element();
+    let mut found = Vec::new();
+
+    let mirrors: Vec<_> = children(child(root, "mirrors"), "mirror").collect();
+    let primary = mirrors
+        .iter()
+        .position(|m| covers_central(&child_text(*m, "mirrorOf")))
+        .or_else(|| (!mirrors.is_empty()).then_some(0));
+    for (i, mirror) in mirrors.iter().enumerate() {
+        found.push(Endpoint {
+            kind: EndpointKind::Mirror,
+            id: child_text(*mirror, "id"),
+            url: child_text(*mirror, "url"),
+            primary: primary == Some(i),
+        });
+    }
+
+    for profile in children(child(root, "profiles"), "profile") {
+        for (list, item, kind) in [
+            ("repositories", "repository", EndpointKind::Repository),
+            ("pluginRepositories", "pluginRepository", EndpointKind::PluginRepository),
+        ] {
+            for repository in children(child(profile, list), item) {
+                found.push(Endpoint {
+                    kind,
+                    id: child_text(repository, "id"),
+                    url: child_text(repository, "url"),
+                    primary: false,
+                });
+            }
+        }
+    }
+
+    let mut endpoints: Vec<Endpoint> = Vec::new();
+    for endpoint in found.into_iter().filter(|e| is_probeable(&e.url)) {
+        if !endpoints.iter().any(|e| e.url == endpoint.url) {
+            endpoints.push(endpoint);
+        }
+    }
+    Ok(endpoints)
+}
+
+/// 地址的基础路径，保证以 `/` 结尾
+fn base_url(url: &str) -> String {
+    if url.ends_with('/') {
+        url.to_string()
+    } else {
+        format!("{}/", url)
+    }
+}
+
+fn describe_error(error: &reqwest::Error) -> String {
+    let reason = if error.is_timeout() {
+        "超时".to_string()
+    } else if error.is_connect() {
+        "无法连接".to_string()
+    } else {
+        "请求失败".to_string()
+    };
+    let mut detail = String::new();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        detail = cause.to_string();
+        source = cause.source();
+    }
+    if detail.is_empty() {
+        reason
+    } else {
+        format!("{}: {}", reason, detail)
+    }
+}
+
+/// 检查单个地址
+fn probe_url(client: &reqwest::blocking::Client, url: &str) -> Result<u16, String> {
+    let url = base_url(url);
+    let mut response = client.head(&url).send().map_err(|e| describe_error(&e))?;
+    if matches!(response.status().as_u16(), 405 | 501) {
+        response = client.get(&url).send().map_err(|e| describe_error(&e))?;
+    }
+    let status = response.status();
+    if status.is_server_error() {
+        Err(format!("HTTP 错误: {}", status))
+    } else {
+        Ok(status.as_u16())
+    }
+}
+
+/// 并行检查所有地址，结果与输入顺序一致
+pub fn probe_endpoints(endpoints: Vec<Endpoint>, timeout: Duration) -> Vec<ProbeResult> {
+    let client = match reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .connect_timeout(timeout)
+        .user_agent("AutoUpdateMavenSettings/1.0")
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            return endpoints
+                .into_iter()
+                .map(|endpoint| ProbeResult {
+                    endpoint,
+                    result: Err(e.to_string()),
+                    elapsed: Duration::ZERO,
+                })
+                .collect()
+        }
+    };
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = endpoints
+            .into_iter()
+            .map(|endpoint| {
+                let client = &client;
+                scope.spawn(move || {
+                    let start = Instant::now();
+                    let result = probe_url(client, &endpoint.url);
+                    ProbeResult {
+                        endpoint,
+                        result,
+                        elapsed: start.elapsed(),
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().ok())
+            .collect()
+    })
+}
+
+/// 检查 settings.xml 内容中的所有地址
+pub fn probe_settings(content: &[u8]) -> Result<Vec<ProbeResult>, String> {
+    let text = std::str::from_utf8(content).map_err(|_| "内容不是有效的 UTF-8 文本".to_string())?;
+    Ok(probe_endpoints(endpoints(text)?, PROBE_TIMEOUT))
+}
+
+fn describe(result: &ProbeResult) -> String {
+    let name = if result.endpoint.id.is_empty() {
+        redact(&result.endpoint.url)
+    } else {
+        result.endpoint.id.clone()
+    };
+    match &result.result {
+        Ok(_) => name,
+        Err(e) => format!("{} {}（{}）", result.endpoint.kind.label(), name, redact(e)),
+    }
+}
+
+/// 按检查方式得出结论：可以写入时返回写入历史的说明，应当拒绝时返回错误
+pub fn verdict(results: &[ProbeResult], mode: MirrorCheck) -> Result<String, String> {
+    if mode == MirrorCheck::Require {
+        if let Some(primary) = results.iter().find(|r| r.endpoint.primary && !r.reachable()) {
+            return Err(format!("主镜像不可访问，未写入: {}", describe(primary)));
+        }
+    }
+    if results.is_empty() {
+        return Ok(String::new());
+    }
+    let unreachable: Vec<String> = results.iter().filter(|r| !r.reachable()).map(describe).collect();
+    if unreachable.is_empty() {
+        Ok(format!("镜像检查: {} 个地址均可访问", results.len()))
+    } else {
+        Ok(format!(
+            "镜像检查: {}/{} 个地址不可访问 - {}",
+            unreachable.len(),
+            results.len(),
+            unreachable.join("；")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// 本地 HTTP 替身：对每个请求按方法返回固定状态码
+    fn stand_in(head_status: u16, get_status: u16) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut buf = [0u8; 2048];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                let status = if buf[..n].starts_with(b"HEAD") { head_status } else { get_status };
+                let _ = write!(stream, "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            }
+        });
+        format!("http://127.0.0.1:{}/maven2", port)
+    }
+
+    /// 没有服务监听的本地地址
+    fn closed_port() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        format!("http://127.0.0.1:{}/repo", port)
+    }
+
+    fn settings(mirror: &str, repository: &str) -> String {
+        format!(
+            r#"<settings>
+  <mirrors>
+    <mirror><id>internal</id><mirrorOf>internal-only</mirrorOf><url>{repository}</url></mirror>
+    <mirror><id>corp</id><mirrorOf>*,!snapshots</mirrorOf><url>{mirror}</url></mirror>
+  </mirrors>
+  <profiles>
+    <profile>
+      <repositories>
+        <repository><id>snapshots</id><url>{repository}</url></repository>
+        <repository><id>props</id><url>${{repo.url}}/x</url></repository>
+      </repositories>
+      <pluginRepositories>
+        <pluginRepository><id>plugins</id><url>https://plugins.example.com/m2</url></pluginRepository>
+      </pluginRepositories>
+    </profile>
+  </profiles>
+</settings>"#
+        )
+    }
+
+    fn endpoint(url: &str, primary: bool) -> Endpoint {
+        Endpoint {
+            kind: EndpointKind::Mirror,
+            id: "corp".to_string(),
+            url: url.to_string(),
+            primary,
+        }
+    }
+
+    #[test]
+    fn lists_mirrors_and_repositories() {
+        let list = endpoints(&settings("https://mirror.example.com/", "https://repo.example.com/r")).unwrap();
+        let summary: Vec<(EndpointKind, &str, bool)> =
+            list.iter().map(|e| (e.kind, e.id.as_str(), e.primary)).collect();
+        // 重复地址和含属性的地址被跳过，主镜像是覆盖 central 的那个
+        assert_eq!(
+            summary,
+            vec![
+                (EndpointKind::Mirror, "internal", false),
+                (EndpointKind::Mirror, "corp", true),
+                (EndpointKind::PluginRepository, "plugins", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn first_mirror_is_primary_when_none_covers_central() {
+        let xml = "<settings><mirrors><mirror><id>a</id><mirrorOf>*,!central</mirrorOf><url>https://a/</url></mirror></mirrors></settings>";
+        let list = endpoints(xml).unwrap();
+        assert!(list[0].primary);
+    }
+
+    #[test]
+    fn reachable_stand_in_passes() {
+        let url = stand_in(200, 200);
+        let results = probe_endpoints(vec![endpoint(&url, true)], Duration::from_secs(2));
+        assert_eq!(results[0].result, Ok(200));
+        assert_eq!(verdict(&results, MirrorCheck::Require).unwrap(), "镜像检查: 1 个地址均可访问");
+    }
+
+    #[test]
+    fn unauthorized_counts_as_reachable_and_head_falls_back_to_get() {
+        let url = stand_in(405, 401);
+        let results = probe_endpoints(vec![endpoint(&url, true)], Duration::from_secs(2));
+        assert_eq!(results[0].result, Ok(401));
+    }
+
+    #[test]
+    fn server_error_is_unreachable() {
+        let url = stand_in(503, 503);
+        let results = probe_endpoints(vec![endpoint(&url, false)], Duration::from_secs(2));
+        assert!(!results[0].reachable());
+    }
+
+    #[test]
+    fn unreachable_primary_is_refused_only_when_required() {
+        let ok = stand_in(200, 200);
+        let results = probe_endpoints(
+            vec![endpoint(&closed_port(), true), endpoint(&ok, false)],
+            Duration::from_secs(2),
+        );
+        assert!(!results[0].reachable());
+        assert!(results[1].reachable());
+
+        let refused = verdict(&results, MirrorCheck::Require).unwrap_err();
+        assert!(refused.starts_with("主镜像不可访问"), "{}", refused);
+        let reported = verdict(&results, MirrorCheck::Report).unwrap();
+        assert!(reported.starts_with("镜像检查: 1/2 个地址不可访问"), "{}", reported);
+    }
+
+    #[test]
+    fn unreachable_secondary_does_not_refuse() {
+        let ok = stand_in(200, 200);
+        let results = probe_endpoints(
+            vec![endpoint(&ok, true), endpoint(&closed_port(), false)],
+            Duration::from_secs(2),
+        );
+        assert!(verdict(&results, MirrorCheck::Require).is_ok());
+    }
+}
@@ -0,0 +1,298 @@
+// share.rs
+// 配置的导出与导入，方便新同事一次完成配置
+//
+// 导出为 JSON 文件（与配置文件格式相同），或 `msettings://import?data=<base64url>` 链接。
+// 导入时先与当前配置比较并列出变更，用户确认后才应用。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+
+use crate::config::{self, AppConfig, SyncProfile};
+use crate::{credentials, permissions};
+
+pub const LINK_PREFIX: &str = "msettings://import?";
+
+/// 待确认的导入
+pub struct PendingImport {
+    /// 来源说明（文件路径或“链接”）
+    pub source: String,
+    pub config: AppConfig,
+    pub changes: Vec<String>,
+}
+
+/// 导出的配置内容，不包含密钥时清空密码和令牌
+pub fn export_config(config: &AppConfig, include_secrets: bool) -> Result<String, String> {
+    let mut config = config.clone();
+    // 开机自启与本机相关，不随配置分享
+    config.auto_launch_enabled = false;
+    if include_secrets {
+        // 凭据用本机主密码加密，导出明文，导入时再用对方的主密码加密
+        credentials::decrypt_credentials_for_export(&mut config);
+    } else {
+        for profile in &mut config.profiles {
+            profile.auth = profile.auth.without_secrets();
+            for credential in &mut profile.server_credentials {
+                credential.password.clear();
+            }
+        }
+        // Webhook 地址中带有令牌
+        config.webhooks.clear();
+    }
+    serde_json::to_string_pretty(&config).map_err(|e| e.to_string())
+}
+
+pub fn export_link(config: &AppConfig, include_secrets: bool) -> Result<String, String> {
+    let json = export_config(config, include_secrets)?;
+    let data = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+    Ok(format!("{}data={}", LINK_PREFIX, data))
+}
+
+pub fn write_export(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    permissions::write_private(path, content).map_err(|e| format!("无法写入 {}: {}", path.display(), e))
+}
+
+/// 默认的导出位置
+pub fn default_export_path() -> PathBuf {
+    crate::paths::home_dir().join("msettings-config.json")
+}
+
+pub fn is_link(source: &str) -> bool {
+    source.trim().starts_with("msettings://")
+}
+
+fn decode_link(link: &str) -> Result<String, String> {
+    let query = link
+        .trim()
+        .strip_prefix(LINK_PREFIX)
+        .ok_or_else(|| format!("不支持的链接，应以 {} 开头", LINK_PREFIX))?;
+    let data = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("data="))
+        .ok_or_else(|| "链接中缺少 data 参数".to_string())?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(data.trim_end_matches('='))
+        .map_err(|e| format!("链接内容无效: {}", e))?;
+    String::from_utf8(bytes).map_err(|_| "链接内容不是有效的文本".to_string())
+}
+
+/// 从文件路径或 msettings:// 链接读取并检查要导入的配置
+pub fn load_import(source: &str, current: &AppConfig) -> Result<PendingImport, String> {
+    let (content, source_label) = if is_link(source) {
+        (decode_link(source)?, "链接".to_string())
+    } else {
+        let path = crate::targets::expand_path(source)?;
+        let content = fs::read_to_string(&path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+        (content, path.display().to_string())
+    };
+
+    let imported = config::parse_shared_config(&content)?;
+    let merged = merge(current, &imported);
+    Ok(PendingImport {
+        source: source_label,
+        changes: describe_changes(current, &merged),
+        config: merged,
+    })
+}
+
+/// 把导入的配置合并到当前用户配置：同 id 的环境被替换，其余环境保留
+fn merge(current: &AppConfig, imported: &AppConfig) -> AppConfig {
+    let mut merged = current.clone();
+    for profile in &imported.profiles {
+        let mut profile = profile.clone();
+        match merged.profiles.iter_mut().find(|p| p.id == profile.id) {
+            Some(existing) => {
+                profile.auth.fill_secrets_from(&existing.auth);
+                for credential in &mut profile.server_credentials {
+                    let kept = existing
+                        .server_credentials
+                        .iter()
+                        .find(|c| c.server_id == credential.server_id && c.username == credential.username);
+                    if let (true, Some(kept)) = (credential.password.is_empty(), kept) {
+                        credential.password = kept.password.clone();
+                    }
+                }
+                *existing = profile;
+            }
+            None => merged.profiles.push(profile),
+        }
+    }
+    merged.active_profile = imported.active_profile.clone();
+    merged.enable_scheduler = imported.enable_scheduler;
+    merged.minimize_to_background = imported.minimize_to_background;
+    merged.notifications = imported.notifications.clone();
+    // 不含密钥的导出没有 Webhook，此时保留本机的设置
+    if !imported.webhooks.is_empty() {
+        merged.webhooks = imported.webhooks.clone();
+    }
+    merged
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "开启"
+    } else {
+        "关闭"
+    }
+}
+
+fn or_empty(value: &str) -> &str {
+    if value.is_empty() {
+        "（空）"
+    } else {
+        value
+    }
+}
+
+fn describe_profile_changes(name: &str, old: &SyncProfile, new: &SyncProfile, changes: &mut Vec<String>) {
+    if old.name != new.name {
+        changes.push(format!("环境「{}」改名为「{}」", old.name, new.name));
+    }
+    if old.url != new.url {
+        changes.push(format!("环境「{}」下载地址: {} → {}", name, or_empty(&old.url), or_empty(&new.url)));
+    }
+    if old.interval_hours != new.interval_hours {
+        changes.push(format!(
+            "环境「{}」更新间隔: {} → {} 小时",
+            name, old.interval_hours, new.interval_hours
+        ));
+    }
+    if old.manifest_url != new.manifest_url {
+        changes.push(format!(
+            "环境「{}」中央清单地址: {} → {}",
+            name,
+            or_empty(&old.manifest_url),
+            or_empty(&new.manifest_url)
+        ));
+    }
+    // 密码和令牌不在预览中显示
+    if old.auth != new.auth {
+        changes.push(format!(
+            "环境「{}」下载认证: {} → {}",
+            name,
+            old.auth.label(),
+            new.auth.label()
+        ));
+    }
+    if old.server_credentials != new.server_credentials {
+        let ids = |profile: &SyncProfile| {
+            profile
+                .server_credentials
+                .iter()
+                .map(|c| c.server_id.clone())
+                .collect::<Vec<_>>()
+                .join("、")
+        };
+        changes.push(format!("环境「{}」服务器凭据: {} → {}", name, or_empty(&ids(old)), or_empty(&ids(new))));
+    }
+    if old.https_only != new.https_only {
+        let label = |value: Option<bool>| match value {
+            Some(true) => "开启",
+            Some(false) => "关闭",
+            None => "默认",
+        };
+        changes.push(format!("环境「{}」仅允许 HTTPS: {} → {}", name, label(old.https_only), label(new.https_only)));
+    }
+    if old.allowed_hosts != new.allowed_hosts {
+        changes.push(format!(
+            "环境「{}」允许的主机: {} → {}",
+            name,
+            or_empty(&old.allowed_hosts.join("、")),
+            or_empty(&new.allowed_hosts.join("、"))
+        ));
+    }
+    if old.gradle != new.gradle {
+        changes.push(format!("环境「{}」Gradle: {} → {}", name, old.gradle.label(), new.gradle.label()));
+    }
+    if old.keep_local != new.keep_local {
+        changes.push(format!(
+            "环境「{}」保留本机元素: {} → {}",
+            name,
+            or_empty(&old.keep_local.join("、")),
+            or_empty(&new.keep_local.join("、"))
+        ));
+    }
+    if old.transforms != new.transforms {
+        let list = |profile: &SyncProfile| {
+            profile
+                .transforms
+                .iter()
+                .map(|t| t.describe())
+                .collect::<Vec<_>>()
+                .join("；")
+        };
+        changes.push(format!("环境「{}」本地调整: {} ⇒ {}", name, or_empty(&list(old)), or_empty(&list(new))));
+    }
+    if old.targets != new.targets {
+        let list = |profile: &SyncProfile| {
+            profile
+                .targets
+                .iter()
+                .map(|t| format!("{} → {}", t.name, t.path))
+                .collect::<Vec<_>>()
+                .join("；")
+        };
+        changes.push(format!("环境「{}」同步文件: {} ⇒ {}", name, or_empty(&list(old)), or_empty(&list(new))));
+    }
+}
+
+/// 列出导入后与当前配置的差异
+pub fn describe_changes(current: &AppConfig, merged: &AppConfig) -> Vec<String> {
+    let mut changes = Vec::new();
+    for profile in &merged.profiles {
+        match current.profiles.iter().find(|p| p.id == profile.id) {
+            Some(old) => describe_profile_changes(&old.name, old, profile, &mut changes),
+            None => changes.push(format!(
+                "新增环境「{}」: 下载地址 {}，更新间隔 {} 小时",
+                profile.name,
+                or_empty(&profile.url),
+                profile.interval_hours
+            )),
+        }
+    }
+    if current.active_profile != merged.active_profile {
+        if let Some(profile) = merged.profiles.iter().find(|p| p.id == merged.active_profile) {
+            changes.push(format!("当前环境切换为「{}」", profile.name));
+        }
+    }
+    if current.enable_scheduler != merged.enable_scheduler {
+        changes.push(format!(
+            "定时任务: {} → {}",
+            on_off(current.enable_scheduler),
+            on_off(merged.enable_scheduler)
+        ));
+    }
+    if current.minimize_to_background != merged.minimize_to_background {
+        changes.push(format!(
+            "关闭窗口后台运行: {} → {}",
+            on_off(current.minimize_to_background),
+            on_off(merged.minimize_to_background)
+        ));
+    }
+    let events = [
+        ("更新失败", current.notifications.on_failure, merged.notifications.on_failure),
+        ("连续失败", current.notifications.on_repeated_failure, merged.notifications.on_repeated_failure),
+        ("内容变化", current.notifications.on_change, merged.notifications.on_change),
+    ];
+    for (event, old, new) in events {
+        if old != new {
+            changes.push(format!("{}通知: {} → {}", event, on_off(old), on_off(new)));
+        }
+    }
+    if current.webhooks != merged.webhooks {
+        let list = |config: &AppConfig| {
+            config
+                .webhooks
+                .iter()
+                .map(|w| format!("{}（{}）", w.display_host(), w.format.label()))
+                .collect::<Vec<_>>()
+                .join("、")
+        };
+        changes.push(format!("Webhook: {} → {}", or_empty(&list(current)), or_empty(&list(merged))));
+    }
+    changes
+}
@@ -0,0 +1,192 @@
+// statusserver.rs
+// 本机状态接口：只监听 127.0.0.1，供本机的监控代理采集
+//
+// 只支持 GET/HEAD，每个连接处理一个请求后关闭。
+// 拒绝非回环地址的连接，并检查 Host 头，防止网页通过 DNS 重绑定读取状态。
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// 按请求路径返回内容类型和响应体，未知路径返回 None
+pub type Handler = Arc<dyn Fn(&str) -> Option<(&'static str, String)> + Send + Sync>;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// 运行中的状态接口，释放时停止监听
+pub struct StatusServer {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StatusServer {
+    /// 在 127.0.0.1 的指定端口上开始监听，端口为 0 时由系统分配
+    pub fn start(port: u16, handler: Handler) -> Result<Self, String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .map_err(|e| format!("无法监听 127.0.0.1:{}: {}", port, e))?;
+        // 非阻塞监听，便于停止时退出线程
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, peer)) => handle_connection(stream, peer, port, &handler),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                    Err(e) => {
+                        log::warn!("Status endpoint failed to accept connection: {}", e);
+                        thread::sleep(POLL_INTERVAL);
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            port,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// 读取请求头，直到空行或超过上限
+fn read_head(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).ok()?;
+        if n == 0 || head.len() + n > MAX_REQUEST_HEAD {
+            return None;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8(head).ok()
+}
+
+/// Host 头只接受回环地址，端口必须与监听端口一致
+fn host_allowed(host: &str, port: u16) -> bool {
+    let (name, host_port) = match host.rsplit_once(':') {
+        Some((name, p)) if !name.ends_with(':') && !p.ends_with(']') => (name, p.parse::<u16>().ok()),
+        _ => (host, Some(80)),
+    };
+    matches!(name, "127.0.0.1" | "localhost" | "[::1]") && host_port == Some(port)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str, head_only: bool) {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    let _ = stream.write_all(header.as_bytes());
+    if !head_only {
+        let _ = stream.write_all(body.as_bytes());
+    }
+    let _ = stream.flush();
+}
+
+fn handle_connection(mut stream: TcpStream, peer: SocketAddr, port: u16, handler: &Handler) {
+    if !peer.ip().is_loopback() {
+        return;
+    }
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let Some(head) = read_head(&mut stream) else { return };
+
+    let mut lines = head.lines();
+    let mut request = lines.next().unwrap_or_default().split_whitespace();
+    let (method, target) = (request.next().unwrap_or_default(), request.next().unwrap_or_default());
+    let host = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.trim().to_string());
+
+    let text = "text/plain; charset=utf-8";
+    if !host.is_some_and(|host| host_allowed(&host, port)) {
+        respond(&mut stream, "403 Forbidden", text, "forbidden\n", false);
+        return;
+    }
+    let head_only = method == "HEAD";
+    if method != "GET" && !head_only {
+        respond(&mut stream, "405 Method Not Allowed", text, "method not allowed\n", false);
+        return;
+    }
+    let path = target.split('?').next().unwrap_or_default();
+    match handler(path) {
+        Some((content_type, body)) => respond(&mut stream, "200 OK", content_type, &body, head_only),
+        None => respond(&mut stream, "404 Not Found", text, "not found\n", head_only),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> StatusServer {
+        StatusServer::start(
+            0,
+            Arc::new(|path| (path == "/status").then(|| ("application/json", "{\"running\":false}".to_string()))),
+        )
+        .unwrap()
+    }
+
+    fn request(port: u16, raw: &str) -> String {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        stream.write_all(raw.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn serves_known_paths_and_rejects_others() {
+        let server = server();
+        let port = server.port();
+        let ok = request(port, &format!("GET /status?x=1 HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n", port));
+        assert!(ok.starts_with("HTTP/1.1 200 OK"));
+        assert!(ok.ends_with("{\"running\":false}"));
+        let missing = request(port, &format!("GET /other HTTP/1.1\r\nHost: localhost:{}\r\n\r\n", port));
+        assert!(missing.starts_with("HTTP/1.1 404"));
+        let post = request(port, &format!("POST /status HTTP/1.1\r\nHost: localhost:{}\r\n\r\n", port));
+        assert!(post.starts_with("HTTP/1.1 405"));
+    }
+
+    #[test]
+    fn rejects_foreign_host_header() {
+        let server = server();
+        let port = server.port();
+        let rebound = request(port, &format!("GET /status HTTP/1.1\r\nHost: evil.example.com:{}\r\n\r\n", port));
+        assert!(rebound.starts_with("HTTP/1.1 403"));
+        let missing = request(port, "GET /status HTTP/1.0\r\n\r\n");
+        assert!(missing.starts_with("HTTP/1.1 403"));
+    }
+
+    #[test]
+    fn host_check_handles_ipv6_and_ports() {
+        assert!(host_allowed("[::1]:9731", 9731));
+        assert!(host_allowed("localhost:9731", 9731));
+        assert!(!host_allowed("localhost:9732", 9731));
+        assert!(!host_allowed("[::1]", 9731));
+        assert!(host_allowed("localhost", 80));
+    }
+}
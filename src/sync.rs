@@ -0,0 +1,340 @@
+// sync.rs
+// 下载流程：按环境配置逐个下载目标文件，校验后写入
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::config::{GradleOutput, MirrorCheck, SourceAuth, SyncProfile, SyncTarget};
+use crate::allowlist::SourceRules;
+use crate::probe::{self, ProbeResult};
+use crate::{credentials, gradle, manifest, paths, permissions, policy, targets, toolchains, transform};
+
+/// 清单本身在历史记录中使用的目标 id
+pub const MANIFEST_TARGET_ID: &str = "manifest";
+/// 同步给 Gradle 的文件在历史记录中使用的目标 id
+pub const GRADLE_TARGET_ID: &str = "gradle";
+
+/// 单个目标文件的同步结果，成功时附带说明（可为空）
+pub struct TargetOutcome {
+    pub target_id: String,
+    pub target_name: String,
+    pub result: Result<String, String>,
+    /// settings.xml 中镜像和仓库地址的检查结果
+    pub probes: Vec<ProbeResult>,
+    /// 写入的内容与原文件不同
+    pub changed: bool,
+    /// 下载耗时，没有下载时为 None
+    pub download: Option<Duration>,
+}
+
+impl TargetOutcome {
+    /// 没有镜像检查、没有下载、内容未变的结果
+    pub fn new(target_id: &str, target_name: &str, result: Result<String, String>) -> Self {
+        Self {
+            target_id: target_id.to_string(),
+            target_name: target_name.to_string(),
+            result,
+            probes: Vec::new(),
+            changed: false,
+            download: None,
+        }
+    }
+}
+
+/// 一次同步的结果
+pub struct SyncRun {
+    pub outcomes: Vec<TargetOutcome>,
+    /// 清单模式下清单给出的目标列表，界面据此更新环境配置
+    pub manifest_targets: Option<Vec<SyncTarget>>,
+    pub recommended_interval_hours: Option<u64>,
+}
+
+/// 历史记录按 `环境/目标` 分开保存
+pub fn history_key(profile_id: &str, target_id: &str) -> String {
+    format!("{}/{}", profile_id, target_id)
+}
+
+/// 请求错误连同其原因一起显示，重定向被拒绝时原因才是关键信息
+fn request_error(error: reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+fn fetch(client: &reqwest::blocking::Client, rules: &SourceRules, url: &str, auth: &SourceAuth) -> Result<Vec<u8>, String> {
+    // 协议和主机的安全限制
+    rules.check(url)?;
+
+    let request = match auth {
+        SourceAuth::None => client.get(url),
+        SourceAuth::Basic { username, password } => client.get(url).basic_auth(username, Some(password)),
+        SourceAuth::Bearer { token } => client.get(url).bearer_auth(token),
+    };
+    let resp = request.send().map_err(request_error)?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP 错误: {}", resp.status()));
+    }
+    let content = resp.bytes().map_err(|e| e.to_string())?;
+    Ok(content.to_vec())
+}
+
+/// 把下载内容保存到缓存目录，便于排查校验失败的文件
+///
+/// settings.xml 中的明文密码加密后再缓存，无法加密时不缓存。
+fn cache_download(profile_id: &str, target: &SyncTarget, content: &[u8]) {
+    let content = if credentials::is_maven_settings(target) {
+        match credentials::protect_secrets(content) {
+            Ok(content) => content,
+            Err(e) => {
+                log::warn!("Skipped caching download {}: {}", target.name, e);
+                return;
+            }
+        }
+    } else {
+        content.to_vec()
+    };
+    let dir = paths::download_cache_dir(profile_id);
+    if let Err(e) = fs::create_dir_all(&dir).and_then(|_| permissions::write_private(&dir.join(&target.id), content)) {
+        log::warn!("Failed to cache download {}: {}", target.name, e);
+    }
+}
+
+/// 管理员策略要求签名时，下载 `<地址>.sig` 并验证
+fn verify_signature(
+    client: &reqwest::blocking::Client,
+    rules: &SourceRules,
+    url: &str,
+    auth: &SourceAuth,
+    content: &[u8],
+) -> Result<(), String> {
+    let policy = policy::current();
+    if !policy.requires_signature() {
+        return Ok(());
+    }
+    let signature = fetch(client, rules, &format!("{}.sig", url), auth).map_err(|e| format!("无法下载签名文件: {}", e))?;
+    policy.verify_signature(content, &signature)
+}
+
+/// 下载后的内容检查和本机调整：签名、校验和、格式、沿用本机元素、本地调整和本机 JDK，返回调整后的内容和说明
+fn prepare_content(
+    client: &reqwest::blocking::Client,
+    rules: &SourceRules,
+    profile: &SyncProfile,
+    target: &SyncTarget,
+    content: Vec<u8>,
+) -> Result<(Vec<u8>, Vec<String>), String> {
+    verify_signature(client, rules, profile.source_url(target), &profile.auth, &content)?;
+    if let Some(expected) = &target.sha256 {
+        let actual = targets::sha256_hex(&content);
+        if &actual != expected {
+            return Err(format!("校验和不匹配：期望 {}，实际 {}", expected, actual));
+        }
+    }
+    targets::validate(target, &content)?;
+    let mut details = Vec::new();
+    // 环境中的本地调整，最后沿用本机的 localRepository、offline 等元素，调整后的内容同样要通过校验
+    let content = if credentials::is_maven_settings(target) {
+        let adjusted = transform::adjust_bytes(&content, target, profile)?;
+        let content = adjusted.content.into_bytes();
+        targets::validate(target, &content)?;
+        for line in &adjusted.transform_log {
+            log::info!("Transform {}: {}", target.name, line);
+        }
+        for line in &adjusted.keep_log {
+            log::info!("Keep local {}: {}", target.name, line);
+        }
+        if !profile.transforms.is_empty() {
+            details.push(format!("本地调整: {}", adjusted.transform_log.join("；")));
+        }
+        details.push(adjusted.keep_log.join("；"));
+        content
+    } else {
+        content
+    };
+    // toolchains.xml 合并本机检测到的 JDK，下载的内容作为模板
+    let content = if target.merge_jdks {
+        let (content, log) = toolchains::merge_bytes(&content, &toolchains::detect())?;
+        targets::validate(target, &content)?;
+        for line in &log {
+            log::info!("Toolchains {}: {}", target.name, line);
+        }
+        if !log.is_empty() {
+            details.push(format!("本机 JDK: {}", log.join("；")));
+        }
+        content
+    } else {
+        content
+    };
+    Ok((content, details))
+}
+
+/// 写入目标文件并记录为最近一次成功的内容，返回写入的内容是否与原文件不同
+fn write_content(profile: &SyncProfile, target: &SyncTarget, content: Vec<u8>) -> Result<bool, String> {
+    let current = targets::expand_path(&target.path).ok().and_then(|path| fs::read(path).ok());
+    // settings.xml 合并本机凭据，写入的密码一律加密，未变的密码沿用现有文件中的密文
+    let content = if credentials::is_maven_settings(target) {
+        credentials::prepare_settings(&content, &profile.server_credentials, current.as_deref())?
+    } else {
+        content
+    };
+    let changed = current.as_deref() != Some(&content[..]);
+    targets::write_target(target, &content)?;
+    targets::store_last_good(&profile.id, target, &content);
+    Ok(changed)
+}
+
+fn sync_target(
+    client: &reqwest::blocking::Client,
+    rules: &SourceRules,
+    profile: &SyncProfile,
+    target: &SyncTarget,
+) -> TargetOutcome {
+    // 本地文件已与清单校验和一致，无需下载
+    if targets::is_up_to_date(target) {
+        return TargetOutcome::new(&target.id, &target.name, Ok("已是最新".to_string()));
+    }
+
+    let start = Instant::now();
+    let content = match fetch(client, rules, profile.source_url(target), &profile.auth) {
+        Ok(content) => content,
+        Err(e) => return TargetOutcome::new(&target.id, &target.name, Err(e)),
+    };
+    let mut outcome = TargetOutcome::new(&target.id, &target.name, Ok(String::new()));
+    outcome.download = Some(start.elapsed());
+    cache_download(&profile.id, target, &content);
+
+    outcome.result = prepare_content(client, rules, profile, target, content).and_then(|(content, mut details)| {
+        // 镜像和仓库地址不可访问时，按设置记录或拒绝写入
+        if credentials::is_maven_settings(target) && target.mirror_check != MirrorCheck::Off {
+            outcome.probes = probe::probe_settings(&content)?;
+            details.push(probe::verdict(&outcome.probes, target.mirror_check)?);
+        }
+        outcome.changed = write_content(profile, target, content)?;
+        details.retain(|detail| !detail.is_empty());
+        Ok(details.join("；"))
+    });
+    outcome
+}
+
+fn build_client(rules: &SourceRules) -> Result<reqwest::blocking::Client, String> {
+    // 添加超时和用户代理，重定向按同样的安全限制检查
+    reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .user_agent("AutoUpdateMavenSettings/1.0")
+        .redirect(rules.clone().redirect_policy())
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// 整体失败时的结果：清单模式记在清单下，否则记在每个目标下
+fn failed_run(profile: &SyncProfile, error: String) -> SyncRun {
+    let outcomes = if profile.uses_manifest() {
+        vec![TargetOutcome::new(MANIFEST_TARGET_ID, "清单", Err(error))]
+    } else {
+        profile
+            .targets
+            .iter()
+            .map(|target| TargetOutcome::new(&target.id, &target.name, Err(error.clone())))
+            .collect()
+    };
+    SyncRun {
+        outcomes,
+        manifest_targets: None,
+        recommended_interval_hours: None,
+    }
+}
+
+/// 同步环境下的所有目标文件，各目标互不影响
+///
+/// 配置了清单地址时，先下载清单，按清单中的文件列表与本机对账。
+pub fn sync_profile(profile: &SyncProfile) -> SyncRun {
+    let rules = SourceRules::for_profile(profile);
+    let client = match policy::current().check_valid().and_then(|_| build_client(&rules)) {
+        Ok(client) => client,
+        Err(e) => return failed_run(profile, e),
+    };
+
+    let mut run = SyncRun {
+        outcomes: Vec::new(),
+        manifest_targets: None,
+        recommended_interval_hours: None,
+    };
+    let mut profile = profile.clone();
+
+    if profile.uses_manifest() {
+        let manifest_url = profile.manifest_url.trim().to_string();
+        let start = Instant::now();
+        let fetched = fetch(&client, &rules, &manifest_url, &profile.auth);
+        let download = start.elapsed();
+        let parsed = fetched
+            .and_then(|content| {
+                verify_signature(&client, &rules, &manifest_url, &profile.auth, &content)?;
+                manifest::parse(&content)
+            })
+            .and_then(|manifest| {
+                manifest.check_app_version()?;
+                Ok((manifest.targets(&manifest_url)?, manifest.recommended_interval_hours))
+            });
+        match parsed {
+            Ok((targets, interval)) => {
+                let mut outcome = TargetOutcome::new(MANIFEST_TARGET_ID, "清单", Ok(format!("共 {} 个文件", targets.len())));
+                outcome.download = Some(download);
+                run.outcomes.push(outcome);
+                profile.targets = targets.clone();
+                run.manifest_targets = Some(targets);
+                run.recommended_interval_hours = interval;
+            }
+            Err(e) => return failed_run(&profile, e),
+        }
+    }
+
+    for target in &profile.targets {
+        run.outcomes.push(sync_target(&client, &rules, &profile, target));
+    }
+    if let Some(outcome) = sync_gradle(&profile) {
+        run.outcomes.push(outcome);
+    }
+    run
+}
+
+/// 按写入后的 settings.xml 更新 Gradle 的文件，未开启时返回 None
+fn sync_gradle(profile: &SyncProfile) -> Option<TargetOutcome> {
+    if profile.gradle == GradleOutput::Off {
+        return None;
+    }
+    let (result, changed) = match gradle::sync(profile) {
+        Ok((summary, changed)) => (Ok(summary), changed),
+        Err(e) => {
+            log::warn!("Failed to sync Gradle files: {}", e);
+            (Err(e), false)
+        }
+    };
+    let mut outcome = TargetOutcome::new(GRADLE_TARGET_ID, "Gradle", result);
+    outcome.changed = changed;
+    Some(outcome)
+}
+
+/// 应用环境下所有目标最近一次成功下载的内容
+pub fn apply_last_known_good(profile: &SyncProfile) -> Vec<(TargetOutcome, bool)> {
+    let mut outcomes: Vec<(TargetOutcome, bool)> = profile
+        .targets
+        .iter()
+        .map(|target| {
+            let result = targets::apply_last_good(&profile.id, target);
+            let applied = matches!(result, Ok(true));
+            (TargetOutcome::new(&target.id, &target.name, result.map(|_| String::new())), applied)
+        })
+        .collect();
+    // 切换环境后镜像可能不同，Gradle 的文件随之更新
+    if outcomes.iter().any(|(_, applied)| *applied) {
+        if let Some(outcome) = sync_gradle(profile) {
+            let applied = outcome.result.is_ok();
+            outcomes.push((outcome, applied));
+        }
+    }
+    outcomes
+}
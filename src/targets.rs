@@ -0,0 +1,257 @@
+// targets.rs
+// 同步目标文件：路径展开、内容校验、带轮换备份的写入
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::{SyncTarget, TargetValidation};
+use crate::{credentials, gradle, paths, permissions, redact};
+
+/// 内容的 SHA-256 校验和（小写十六进制）
+pub fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// 目标文件的当前内容是否已与期望的校验和一致
+pub fn is_up_to_date(target: &SyncTarget) -> bool {
+    let Some(expected) = &target.sha256 else {
+        return false;
+    };
+    expand_path(&target.path)
+        .ok()
+        .and_then(|path| fs::read(path).ok())
+        .is_some_and(|content| &sha256_hex(&content) == expected)
+}
+
+/// 展开目标路径中的 `~`、`${VAR}` 和 `$VAR`，只接受绝对路径
+pub fn expand_path(raw: &str) -> Result<PathBuf, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err("目标路径为空".to_string());
+    }
+
+    let mut expanded = String::new();
+    let mut rest = raw;
+    if let Some(stripped) = rest.strip_prefix('~') {
+        if stripped.is_empty() || stripped.starts_with('/') || stripped.starts_with('\\') {
+            expanded.push_str(&paths::home_dir().to_string_lossy());
+            rest = stripped;
+        }
+    }
+
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, remaining) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| format!("路径中的变量缺少右括号: {}", raw))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name.is_empty() {
+            return Err(format!("路径中的变量名为空: {}", raw));
+        }
+        let value = std::env::var(name).map_err(|_| format!("环境变量 {} 未设置", name))?;
+        expanded.push_str(&value);
+        rest = remaining;
+    }
+    expanded.push_str(rest);
+
+    let path = PathBuf::from(expanded);
+    if !path.is_absolute() {
+        return Err(format!("目标路径必须是绝对路径: {}", raw));
+    }
+    Ok(path)
+}
+
+/// 按目标的校验规则检查下载内容
+pub fn validate(target: &SyncTarget, content: &[u8]) -> Result<(), String> {
+    match &target.validation {
+        TargetValidation::None => Ok(()),
+        TargetValidation::NonEmpty => {
+            if content.iter().all(|b| b.is_ascii_whitespace()) {
+                Err("内容为空".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        TargetValidation::Xml { root } => {
+            let text = std::str::from_utf8(content).map_err(|_| "内容不是有效的 UTF-8 文本".to_string())?;
+            let doc = roxmltree::Document::parse(text).map_err(|e| format!("XML 格式错误: {}", e))?;
+            if let Some(root) = root {
+                let actual = doc.root_element().tag_name().name();
+                if actual != root {
+                    return Err(format!("根元素应为 <{}>，实际为 <{}>", root, actual));
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// 第 n 份备份的路径，保存在状态目录中，第一份沿用 `settings.xml.backup` 的命名
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let dir = paths::backup_dir(path);
+    if index <= 1 {
+        dir.join(format!("{}.backup", file_name))
+    } else {
+        dir.join(format!("{}.backup.{}", file_name, index))
+    }
+}
+
+/// 轮换备份：最旧的一份被丢弃，当前文件成为第一份备份
+///
+/// settings.xml 中的明文密码在备份前加密，gradle.properties 中生成的凭据不备份。
+fn rotate_backups(target: &SyncTarget, path: &Path) {
+    let backup_count = target.backup_count;
+    if backup_count == 0 || !path.exists() {
+        return;
+    }
+    if let Err(e) = fs::create_dir_all(paths::backup_dir(path)) {
+        log::warn!("Failed to create backup directory: {}", e);
+        return;
+    }
+    for index in (1..backup_count).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            let _ = fs::rename(&from, backup_path(path, index + 1));
+        }
+    }
+    let result = fs::read(path).and_then(|content| {
+        let content = if credentials::is_maven_settings(target) {
+            credentials::protect_for_backup(&content)
+        } else if gradle::is_properties(target) {
+            gradle::without_credentials(&content)
+        } else {
+            content
+        };
+        permissions::write_private(&backup_path(path, 1), content)
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to create backup: {}", e);
+    }
+}
+
+/// 备份现有文件并写入新内容
+pub fn write_target(target: &SyncTarget, content: &[u8]) -> Result<PathBuf, String> {
+    let path = expand_path(&target.path)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    rotate_backups(target, &path);
+
+    permissions::write_target_file(&path, content).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+// 最近一次成功下载的文件在环境目录下的位置
+fn last_good_path(profile_id: &str, target: &SyncTarget) -> PathBuf {
+    paths::profile_data_dir(profile_id).join(&target.id)
+}
+
+/// 记录某个环境下目标最近一次成功下载的内容，切换环境时使用
+pub fn store_last_good(profile_id: &str, target: &SyncTarget, content: &[u8]) {
+    let path = last_good_path(profile_id, target);
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| permissions::write_private(&path, content));
+    if let Err(e) = result {
+        log::warn!("Failed to store last known good {}: {}", target.name, e);
+    }
+    // 新内容写入后，之前保留的本地修改不再有效
+    let _ = fs::remove_file(kept_path(profile_id, target));
+}
+
+/// 应用某个目标最近一次成功下载的内容，没有记录时返回 false
+pub fn apply_last_good(profile_id: &str, target: &SyncTarget) -> Result<bool, String> {
+    let stored = last_good_path(profile_id, target);
+    if !stored.exists() {
+        return Ok(false);
+    }
+    let content = fs::read(&stored).map_err(|e| e.to_string())?;
+    write_target(target, &content)?;
+    Ok(true)
+}
+
+// 用户选择保留本地修改时记录的校验和
+fn kept_path(profile_id: &str, target: &SyncTarget) -> PathBuf {
+    paths::profile_data_dir(profile_id).join(format!("{}.kept", target.id))
+}
+
+/// 目标文件是否被本地修改：与最近一次成功写入的内容不一致，且不是用户选择保留的版本
+///
+/// 从未同步过的目标没有参照，不算修改。
+pub fn is_locally_modified(profile_id: &str, target: &SyncTarget) -> bool {
+    let Ok(stored) = fs::read(last_good_path(profile_id, target)) else {
+        return false;
+    };
+    let local = expand_path(&target.path)
+        .ok()
+        .and_then(|path| fs::read(path).ok())
+        .unwrap_or_default();
+    if local == stored {
+        return false;
+    }
+    let kept = fs::read_to_string(kept_path(profile_id, target)).unwrap_or_default();
+    kept.trim() != sha256_hex(&local)
+}
+
+/// 保留当前的本地修改，在文件再次变化前不再提示
+pub fn keep_local(profile_id: &str, target: &SyncTarget) -> Result<(), String> {
+    let path = expand_path(&target.path)?;
+    let local = fs::read(&path).unwrap_or_default();
+    permissions::write_private(&kept_path(profile_id, target), sha256_hex(&local)).map_err(|e| e.to_string())
+}
+
+/// 最近一次成功写入的内容与本地文件的差异（统一 diff 格式）
+pub fn diff_local(profile_id: &str, target: &SyncTarget) -> Result<String, String> {
+    let stored = fs::read(last_good_path(profile_id, target)).map_err(|e| e.to_string())?;
+    let path = expand_path(&target.path)?;
+    let local = fs::read(&path).unwrap_or_default();
+    let stored = String::from_utf8_lossy(&stored);
+    let local = String::from_utf8_lossy(&local);
+    let diff = similar::TextDiff::from_lines(stored.as_ref(), local.as_ref())
+        .unified_diff()
+        .context_radius(3)
+        .header("同步的版本", "本地文件")
+        .to_string();
+    // 逐行遮盖：密码被改过时仍能看到对应行的增删
+    Ok(redact::redact(&diff))
+}
+
+/// 本地文件相对最近一次同步版本的变更概要，如 `新增 2 行，删除 1 行`
+pub fn diff_summary(profile_id: &str, target: &SyncTarget) -> String {
+    let stored = fs::read(last_good_path(profile_id, target)).unwrap_or_default();
+    let local = expand_path(&target.path)
+        .ok()
+        .and_then(|path| fs::read(path).ok());
+    let Some(local) = local else {
+        return "文件已被删除".to_string();
+    };
+
+    let stored = String::from_utf8_lossy(&stored);
+    let local = String::from_utf8_lossy(&local);
+    let diff = similar::TextDiff::from_lines(stored.as_ref(), local.as_ref());
+    let (mut added, mut removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            similar::ChangeTag::Insert => added += 1,
+            similar::ChangeTag::Delete => removed += 1,
+            similar::ChangeTag::Equal => {}
+        }
+    }
+    format!("新增 {} 行，删除 {} 行", added, removed)
+}
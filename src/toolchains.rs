@@ -0,0 +1,313 @@
+// toolchains.rs
+// 检测本机安装的 JDK，生成或更新 ~/.m2/toolchains.xml
+//
+// 检测位置：JAVA_HOME、/usr/lib/jvm、/usr/java、SDKMAN、asdf、~/.jdks（IntelliJ 下载的 JDK），
+// macOS 的 JavaVirtualMachines，Windows 的 Program Files。每个 JDK 的版本和厂商取自其 `release` 文件。
+//
+// 合并时以现有内容（本机文件或下载的模板）为准：jdkHome 相同的条目只更新版本和厂商，
+// 新检测到的 JDK 追加到末尾，jdkHome 已不存在的 JDK 条目删除，其他类型的工具链保持不变。
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::SyncTarget;
+use crate::paths;
+use crate::targets;
+use crate::xmledit::{append_child, apply_edits, child, child_text, escape_xml, line_range, Edit};
+
+const EMPTY_TOOLCHAINS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<toolchains>\n</toolchains>\n";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jdk {
+    pub home: PathBuf,
+    /// `release` 中的 JAVA_VERSION，如 `17.0.9`、`1.8.0_392`
+    pub version: String,
+    /// `release` 中的 IMPLEMENTOR，如 `Eclipse Adoptium`
+    pub vendor: String,
+}
+
+/// 是否为 Maven 的 toolchains.xml
+pub fn is_toolchains(target: &SyncTarget) -> bool {
+    Path::new(target.path.trim())
+        .file_name()
+        .is_some_and(|name| name == "toolchains.xml")
+}
+
+/// 解析 `release` 文件中的 `KEY="value"` 行
+fn release_value(release: &str, key: &str) -> Option<String> {
+    release.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// 读取一个 JDK 目录，没有 `release` 文件或没有 javac（只是 JRE）时返回 None
+fn read_jdk(home: &Path) -> Option<Jdk> {
+    let release = fs::read_to_string(home.join("release")).ok()?;
+    let has_javac = home.join("bin").join("javac").exists() || home.join("bin").join("javac.exe").exists();
+    if !has_javac {
+        return None;
+    }
+    Some(Jdk {
+        home: home.to_path_buf(),
+        version: release_value(&release, "JAVA_VERSION").filter(|v| !v.is_empty())?,
+        vendor: release_value(&release, "IMPLEMENTOR")
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "unknown".to_string()),
+    })
+}
+
+/// 存放多个 JDK 的目录
+fn install_dirs() -> Vec<PathBuf> {
+    let home = paths::home_dir();
+    let env_dir = |name: &str, fallback: PathBuf| std::env::var_os(name).map(PathBuf::from).unwrap_or(fallback);
+    let mut dirs = vec![
+        env_dir("SDKMAN_DIR", home.join(".sdkman")).join("candidates").join("java"),
+        env_dir("ASDF_DATA_DIR", home.join(".asdf")).join("installs").join("java"),
+        home.join(".jdks"),
+    ];
+    if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/Library/Java/JavaVirtualMachines"));
+        dirs.push(home.join("Library").join("Java").join("JavaVirtualMachines"));
+    } else if cfg!(target_os = "windows") {
+        for base in ["ProgramFiles", "ProgramW6432"] {
+            if let Some(base) = std::env::var_os(base).map(PathBuf::from) {
+                for vendor in ["Java", "Eclipse Adoptium", "Microsoft", "Zulu", "Amazon Corretto", "BellSoft"] {
+                    dirs.push(base.join(vendor));
+                }
+            }
+        }
+    } else {
+        dirs.push(PathBuf::from("/usr/lib/jvm"));
+        dirs.push(PathBuf::from("/usr/java"));
+    }
+    dirs
+}
+
+/// 版本号按数字逐段比较，`17.0.10` 排在 `17.0.9` 之后
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |v: &str| -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.parse().unwrap_or(0))
+            .collect()
+    };
+    parts(a).cmp(&parts(b)).then_with(|| a.cmp(b))
+}
+
+/// 检测本机安装的 JDK，按版本排序，同一个 JDK 的多个链接（如 SDKMAN 的 current）只保留一个
+pub fn detect() -> Vec<Jdk> {
+    let mut homes: Vec<PathBuf> = std::env::var_os("JAVA_HOME").map(PathBuf::from).into_iter().collect();
+    for dir in install_dirs() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // macOS 的 JDK 在 Contents/Home 下
+            let mac_home = path.join("Contents").join("Home");
+            homes.push(if mac_home.is_dir() { mac_home } else { path });
+        }
+    }
+
+    let mut seen = Vec::new();
+    let mut jdks = Vec::new();
+    for home in homes {
+        let Some(jdk) = read_jdk(&home) else { continue };
+        let real = fs::canonicalize(&home).unwrap_or(home);
+        if seen.contains(&real) {
+            continue;
+        }
+        seen.push(real);
+        jdks.push(jdk);
+    }
+    jdks.sort_by(|a, b| compare_versions(&a.version, &b.version).then_with(|| a.vendor.cmp(&b.vendor)));
+    jdks
+}
+
+fn same_home(configured: &str, home: &Path) -> bool {
+    let configured = Path::new(configured.trim());
+    configured == home
+        || matches!(
+            (fs::canonicalize(configured), fs::canonicalize(home)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+fn toolchain_block(jdk: &Jdk) -> String {
+    format!(
+        "<toolchain>\n  <type>jdk</type>\n  <provides>\n    <version>{}</version>\n    <vendor>{}</vendor>\n  </provides>\n  <configuration>\n    <jdkHome>{}</jdkHome>\n  </configuration>\n</toolchain>",
+        escape_xml(&jdk.version),
+        escape_xml(&jdk.vendor),
+        escape_xml(&jdk.home.to_string_lossy())
+    )
+}
+
+/// 把检测到的 JDK 合并进 toolchains.xml，返回合并后的内容和修改说明，内容为空时新建
+pub fn merge(content: &str, jdks: &[Jdk]) -> Result<(String, Vec<String>), String> {
+    let content = if content.trim().is_empty() { EMPTY_TOOLCHAINS } else { content };
+    let doc = roxmltree::Document::parse(content).map_err(|e| format!("XML 格式错误: {}", e))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("toolchains") {
+        return Err(format!("根元素应为 <toolchains>，实际为 <{}>", root.tag_name().name()));
+    }
+
+    // 第一遍：更新已有条目，删除 jdkHome 不存在的条目
+    let mut edits = Vec::new();
+    let mut log = Vec::new();
+    let mut known = vec![false; jdks.len()];
+    for toolchain in root.children().filter(|n| n.has_tag_name("toolchain")) {
+        if child_text(toolchain, "type").as_deref() != Some("jdk") {
+            continue;
+        }
+        let Some(jdk_home) = child(toolchain, "configuration").and_then(|c| child_text(c, "jdkHome")) else {
+            continue;
+        };
+        match jdks.iter().position(|jdk| same_home(&jdk_home, &jdk.home)) {
+            Some(i) => {
+                known[i] = true;
+                let Some(provides) = child(toolchain, "provides") else { continue };
+                for (name, value) in [("version", &jdks[i].version), ("vendor", &jdks[i].vendor)] {
+                    let Some(node) = child(provides, name) else { continue };
+                    let current = node.text().unwrap_or_default().trim();
+                    if current != value {
+                        log.push(format!("更新 {} 的 {}: {} → {}", jdk_home, name, current, value));
+                        edits.push(Edit {
+                            range: node.range(),
+                            text: format!("<{}>{}</{}>", name, escape_xml(value), name),
+                        });
+                    }
+                }
+            }
+            // 含属性占位符的路径无法判断，保持不变
+            None if !jdk_home.contains("${") && !Path::new(&jdk_home).exists() => {
+                log.push(format!("删除不存在的 JDK: {}", jdk_home));
+                edits.push(Edit {
+                    range: line_range(content, toolchain),
+                    text: String::new(),
+                });
+            }
+            None => {}
+        }
+    }
+    let content = apply_edits(content, edits);
+
+    // 第二遍：追加新检测到的 JDK
+    let added: Vec<&Jdk> = jdks.iter().zip(&known).filter(|(_, known)| !**known).map(|(jdk, _)| jdk).collect();
+    if added.is_empty() {
+        return Ok((content, log));
+    }
+    let doc = roxmltree::Document::parse(&content).map_err(|e| format!("XML 格式错误: {}", e))?;
+    let blocks = added.iter().map(|jdk| toolchain_block(jdk)).collect::<Vec<_>>().join("\n");
+    let edit = append_child(&content, doc.root_element(), &blocks);
+    for jdk in &added {
+        log.push(format!("新增 JDK {}（{}）: {}", jdk.version, jdk.vendor, jdk.home.display()));
+    }
+    Ok((apply_edits(&content, vec![edit]), log))
+}
+
+/// 对下载的 toolchains.xml 合并本机 JDK
+pub fn merge_bytes(content: &[u8], jdks: &[Jdk]) -> Result<(Vec<u8>, Vec<String>), String> {
+    let text = std::str::from_utf8(content).map_err(|_| "内容不是有效的 UTF-8 文本".to_string())?;
+    let (result, log) = merge(text, jdks)?;
+    Ok((result.into_bytes(), log))
+}
+
+/// 用本机检测到的 JDK 生成或更新目标文件，没有变化时不写入
+pub fn generate(target: &SyncTarget) -> Result<Vec<String>, String> {
+    let path = targets::expand_path(&target.path)?;
+    let current = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("无法读取 {}: {}", path.display(), e)),
+    };
+    let (content, log) = merge(&current, &detect())?;
+    if content != current {
+        targets::validate(target, content.as_bytes())?;
+        targets::write_target(target, content.as_bytes())?;
+    }
+    Ok(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jdk(home: &str, version: &str) -> Jdk {
+        Jdk {
+            home: PathBuf::from(home),
+            version: version.to_string(),
+            vendor: "Eclipse Adoptium".to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_release_values() {
+        let release = "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.9\"\nJAVA_VERSION_DATE=\"2023-10-17\"\n";
+        assert_eq!(release_value(release, "JAVA_VERSION").as_deref(), Some("17.0.9"));
+        assert_eq!(release_value(release, "IMPLEMENTOR").as_deref(), Some("Eclipse Adoptium"));
+        assert_eq!(release_value(release, "OS_NAME"), None);
+    }
+
+    #[test]
+    fn orders_versions_numerically() {
+        assert_eq!(compare_versions("17.0.10", "17.0.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.8.0_392", "11.0.2"), Ordering::Less);
+    }
+
+    #[test]
+    fn creates_file_and_is_stable_on_second_run() {
+        let jdks = [jdk("/opt/jdk-17", "17.0.9"), jdk("/opt/jdk-21", "21.0.1")];
+        let (content, log) = merge("", &jdks).unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(content.contains("  <toolchain>\n    <type>jdk</type>"));
+        assert!(content.contains("<jdkHome>/opt/jdk-21</jdkHome>"));
+        roxmltree::Document::parse(&content).unwrap();
+
+        let (again, log) = merge(&content, &jdks).unwrap();
+        assert_eq!(again, content);
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn updates_versions_and_drops_missing_homes() {
+        let template = r#"<toolchains>
+  <toolchain>
+    <type>jdk</type>
+    <provides>
+      <version>17.0.8</version>
+      <vendor>Eclipse Adoptium</vendor>
+    </provides>
+    <configuration>
+      <jdkHome>/opt/jdk-17</jdkHome>
+    </configuration>
+  </toolchain>
+  <toolchain>
+    <type>jdk</type>
+    <provides>
+      <version>11</version>
+    </provides>
+    <configuration>
+      <jdkHome>/nonexistent/jdk-11</jdkHome>
+    </configuration>
+  </toolchain>
+  <toolchain>
+    <type>protobuf</type>
+    <configuration>
+      <protocPath>/nonexistent/protoc</protocPath>
+    </configuration>
+  </toolchain>
+</toolchains>
+"#;
+        let (content, log) = merge(template, &[jdk("/opt/jdk-17", "17.0.9")]).unwrap();
+        assert!(content.contains("<version>17.0.9</version>"));
+        assert!(!content.contains("jdk-11"));
+        assert!(content.contains("<type>protobuf</type>"));
+        assert_eq!(
+            log,
+            vec![
+                "更新 /opt/jdk-17 的 version: 17.0.8 → 17.0.9".to_string(),
+                "删除不存在的 JDK: /nonexistent/jdk-11".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,563 @@
+// transform.rs
+// 写入前对下载的 settings.xml 做本地调整：先执行规则（设置元素、按 id 删除、激活 profile、设置代理），
+// 最后沿用本机的 localRepository 等元素，用户要求保留的值不会被规则覆盖
+//
+// 规则按顺序执行，每条规则都基于上一条的结果重新解析；找不到要删除的项或已是目标值时跳过，不算错误。
+// 每条规则的执行结果都写入执行历史。
+
+use std::fs;
+
+use crate::config::{SyncProfile, SyncTarget, Transform, TRANSFORM_SECTIONS};
+use crate::redact::redact;
+use crate::xmledit::{append_child, apply_edits, child, child_text, escape_xml, indent_of, line_range, Edit};
+use crate::{paths, targets};
+
+/// 一条规则的执行结果：修改后的内容和说明
+struct Applied {
+    content: String,
+    note: &'static str,
+}
+
+fn parse(content: &str) -> Result<roxmltree::Document<'_>, String> {
+    let doc = roxmltree::Document::parse(content).map_err(|e| format!("XML 格式错误: {}", e))?;
+    if !doc.root_element().has_tag_name("settings") {
+        return Err("根元素不是 <settings>，无法应用本地调整".to_string());
+    }
+    Ok(doc)
+}
+
+fn edited(content: &str, edit: Edit) -> Applied {
+    Applied {
+        content: apply_edits(content, vec![edit]),
+        note: "已应用",
+    }
+}
+
+fn skipped(content: &str, note: &'static str) -> Applied {
+    Applied {
+        content: content.to_string(),
+        note,
+    }
+}
+
+/// 从路径的第 n 段开始构造嵌套元素，如 `a/b` = v 得到 `<a>\n  <b>v</b>\n</a>`
+fn nested_element(segments: &[&str], value: &str) -> String {
+    match segments {
+        [] => String::new(),
+        [last] => format!("<{}>{}</{}>", last, escape_xml(value), last),
+        [first, rest @ ..] => format!(
+            "<{}>\n  {}\n</{}>",
+            first,
+            nested_element(rest, value).replace('\n', "\n  "),
+            first
+        ),
+    }
+}
+
+fn set_element(content: &str, path: &str, value: &str) -> Result<Applied, String> {
+    let doc = parse(content)?;
+    let segments: Vec<&str> = path.trim().split('/').collect();
+    let mut node = doc.root_element();
+    for (i, segment) in segments.iter().enumerate() {
+        match child(node, segment) {
+            Some(found) => node = found,
+            None => return Ok(edited(content, append_child(content, node, &nested_element(&segments[i..], value)))),
+        }
+    }
+
+    if node.children().any(|n| n.is_element()) {
+        return Err(format!("{} 包含子元素，不能直接设置文本", path));
+    }
+    if node.text().unwrap_or_default().trim() == value {
+        return Ok(skipped(content, "已是该值，跳过"));
+    }
+    let name = node.tag_name().name();
+    Ok(edited(
+        content,
+        Edit {
+            range: node.range(),
+            text: format!("<{}>{}</{}>", name, escape_xml(value), name),
+        },
+    ))
+}
+
+fn remove_by_id(content: &str, section: &str, id: &str) -> Result<Applied, String> {
+    let doc = parse(content)?;
+    let item = TRANSFORM_SECTIONS
+        .iter()
+        .find(|(name, _)| *name == section)
+        .map(|(_, item)| *item)
+        .ok_or_else(|| format!("不支持从 {} 中删除", section))?;
+    let found = child(doc.root_element(), section).and_then(|list| {
+        list.children()
+            .find(|n| n.has_tag_name(item) && child_text(*n, "id").as_deref() == Some(id.trim()))
+    });
+    Ok(match found {
+        Some(node) => edited(
+            content,
+            Edit {
+                range: line_range(content, node),
+                text: String::new(),
+            },
+        ),
+        None => skipped(content, "未找到，跳过"),
+    })
+}
+
+fn add_active_profile(content: &str, id: &str) -> Result<Applied, String> {
+    let doc = parse(content)?;
+    let root = doc.root_element();
+    let element = format!("<activeProfile>{}</activeProfile>", escape_xml(id.trim()));
+    Ok(match child(root, "activeProfiles") {
+        Some(list) => {
+            let exists = list
+                .children()
+                .any(|n| n.has_tag_name("activeProfile") && n.text().unwrap_or_default().trim() == id.trim());
+            if exists {
+                skipped(content, "已激活，跳过")
+            } else {
+                edited(content, append_child(content, list, &element))
+            }
+        }
+        None => edited(
+            content,
+            append_child(content, root, &format!("<activeProfiles>\n  {}\n</activeProfiles>", element)),
+        ),
+    })
+}
+
+fn set_proxy(content: &str, transform: &Transform) -> Result<Applied, String> {
+    let Transform::SetProxy {
+        id,
+        protocol,
+        host,
+        port,
+        non_proxy_hosts,
+        active,
+    } = transform
+    else {
+        return Err("不是代理规则".to_string());
+    };
+    let doc = parse(content)?;
+    let root = doc.root_element();
+    let proxies = child(root, "proxies");
+    let existing = proxies.and_then(|list| {
+        list.children()
+            .find(|n| n.has_tag_name("proxy") && child_text(*n, "id").as_deref() == Some(id.trim()))
+    });
+
+    let mut lines = vec![
+        format!("<id>{}</id>", escape_xml(id.trim())),
+        format!("<active>{}</active>", active),
+        format!("<protocol>{}</protocol>", escape_xml(protocol.trim())),
+        format!("<host>{}</host>", escape_xml(host.trim())),
+        format!("<port>{}</port>", port),
+    ];
+    // 替换已有代理时保留其中的用户名和密码
+    if let Some(node) = existing {
+        for name in ["username", "password"] {
+            if let Some(kept) = child(node, name) {
+                lines.push(content[kept.range()].to_string());
+            }
+        }
+    }
+    if !non_proxy_hosts.trim().is_empty() {
+        lines.push(format!("<nonProxyHosts>{}</nonProxyHosts>", escape_xml(non_proxy_hosts.trim())));
+    }
+    let block = format!("<proxy>\n  {}\n</proxy>", lines.join("\n  "));
+
+    Ok(match (existing, proxies) {
+        (Some(node), _) => {
+            let indent = indent_of(content, node.range().start);
+            edited(
+                content,
+                Edit {
+                    range: node.range(),
+                    text: block.replace('\n', &format!("\n{}", indent)),
+                },
+            )
+        }
+        (None, Some(list)) => edited(content, append_child(content, list, &block)),
+        (None, None) => edited(
+            content,
+            append_child(content, root, &format!("<proxies>\n  {}\n</proxies>", block.replace('\n', "\n  "))),
+        ),
+    })
+}
+
+fn apply_one(content: &str, transform: &Transform) -> Result<Applied, String> {
+    match transform {
+        Transform::SetElement { path, value } => set_element(content, path, value),
+        Transform::RemoveById { section, id } => remove_by_id(content, section, id),
+        Transform::AddActiveProfile { id } => add_active_profile(content, id),
+        Transform::SetProxy { .. } => set_proxy(content, transform),
+    }
+}
+
+/// 按路径查找元素，路径相对于根元素
+fn find_path<'a, 'input>(root: roxmltree::Node<'a, 'input>, segments: &[&str]) -> Option<roxmltree::Node<'a, 'input>> {
+    segments.iter().try_fold(root, |node, segment| child(node, segment))
+}
+
+/// 沿用本机文件中的一个元素，返回修改后的内容和说明，两边一致时返回 None
+fn keep_one(content: &str, local: &roxmltree::Document, local_text: &str, path: &str) -> Result<Option<(String, String)>, String> {
+    let segments: Vec<&str> = path.trim().split('/').collect();
+    let doc = parse(content)?;
+    let kept = find_path(local.root_element(), &segments);
+    let downloaded = find_path(doc.root_element(), &segments);
+    // 元素原文，去掉其所在行的缩进，以便插入到新位置
+    let raw = |text: &str, node: roxmltree::Node| {
+        text[node.range()].replace(&format!("\n{}", indent_of(text, node.range().start)), "\n")
+    };
+
+    match (kept, downloaded) {
+        (None, None) => Ok(None),
+        (Some(kept), Some(downloaded)) if raw(local_text, kept) == raw(content, downloaded) => Ok(None),
+        (Some(kept), Some(downloaded)) => {
+            let indent = indent_of(content, downloaded.range().start);
+            let text = raw(local_text, kept).replace('\n', &format!("\n{}", indent));
+            let edit = Edit {
+                range: downloaded.range(),
+                text,
+            };
+            Ok(Some((apply_edits(content, vec![edit]), format!("保留本机的 {}", describe_node(kept, path)))))
+        }
+        (Some(kept), None) => {
+            let parent = find_path(doc.root_element(), &segments[..segments.len() - 1]);
+            let result = match parent {
+                Some(parent) => apply_edits(content, vec![append_child(content, parent, &raw(local_text, kept))]),
+                // 上级元素也不存在时只能按文本值创建
+                None if !kept.children().any(|n| n.is_element()) => {
+                    set_element(content, path, kept.text().unwrap_or_default().trim())?.content
+                }
+                None => return Err(format!("下载的文件中没有 {} 的上级元素，无法保留本机的值", path)),
+            };
+            Ok(Some((result, format!("保留本机的 {}", describe_node(kept, path)))))
+        }
+        (None, Some(downloaded)) => {
+            let edit = Edit {
+                range: line_range(content, downloaded),
+                text: String::new(),
+            };
+            Ok(Some((apply_edits(content, vec![edit]), format!("本机未设置 {}，删除下载的值", path))))
+        }
+    }
+}
+
+/// 说明中显示的元素值，含子元素时只显示路径
+fn describe_node(node: roxmltree::Node, path: &str) -> String {
+    if node.children().any(|n| n.is_element()) {
+        path.to_string()
+    } else {
+        format!("{} = {}", path, node.text().unwrap_or_default().trim())
+    }
+}
+
+/// 沿用本机 settings.xml 中的元素：本机有的写回下载的内容，本机没有的从下载的内容中删除
+///
+/// 本机文件不存在或无法解析时（例如首次同步）不做任何修改。
+pub fn keep_local(content: &str, local: &str, paths: &[String]) -> Result<(String, Vec<String>), String> {
+    let Ok(local_doc) = roxmltree::Document::parse(local) else {
+        return Ok((content.to_string(), Vec::new()));
+    };
+    let mut content = content.to_string();
+    let mut log = Vec::new();
+    for path in paths {
+        if let Some((result, note)) = keep_one(&content, &local_doc, local, path)? {
+            content = result;
+            log.push(note);
+        }
+    }
+    Ok((content, log))
+}
+
+/// 按顺序执行所有规则，返回调整后的内容和每条规则的执行说明
+pub fn apply(content: &str, transforms: &[Transform]) -> Result<(String, Vec<String>), String> {
+    let mut content = content.to_string();
+    let mut log = Vec::new();
+    for transform in transforms {
+        let applied =
+            apply_one(&content, transform).map_err(|e| format!("本地调整「{}」失败: {}", transform.describe(), e))?;
+        log.push(format!("{}（{}）", transform.describe(), applied.note));
+        content = applied.content;
+    }
+    Ok((content, log))
+}
+
+/// 本地调整的结果
+pub struct Adjusted {
+    pub content: String,
+    /// 每条规则的执行说明
+    pub transform_log: Vec<String>,
+    /// 沿用本机元素的说明
+    pub keep_log: Vec<String>,
+}
+
+/// 本地调整：先按顺序执行环境中的规则，最后沿用本机元素
+pub fn adjust(content: &str, local: &str, profile: &SyncProfile) -> Result<Adjusted, String> {
+    let (content, transform_log) = apply(content, &profile.transforms)?;
+    let (content, keep_log) = if local.is_empty() || profile.keep_local.is_empty() {
+        (content, Vec::new())
+    } else {
+        keep_local(&content, local, &profile.keep_local)?
+    };
+    Ok(Adjusted {
+        content,
+        transform_log,
+        keep_log,
+    })
+}
+
+/// 目标文件当前的内容，不存在或无法读取时为空
+fn read_local(target: &SyncTarget) -> String {
+    targets::expand_path(&target.path)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default()
+}
+
+/// 对下载内容做本地调整，本机元素取自目标文件当前的内容
+pub fn adjust_bytes(content: &[u8], target: &SyncTarget, profile: &SyncProfile) -> Result<Adjusted, String> {
+    let text = std::str::from_utf8(content).map_err(|_| "内容不是有效的 UTF-8 文本".to_string())?;
+    adjust(text, &read_local(target), profile)
+}
+
+/// 预览：对最近一次下载的内容（没有时用当前文件）沿用本机元素并执行规则，返回差异和执行说明
+pub fn preview(profile: &SyncProfile, target: &SyncTarget) -> Result<(String, Vec<String>), String> {
+    let cached = paths::download_cache_dir(&profile.id).join(&target.id);
+    let source = match fs::read(&cached) {
+        Ok(content) => content,
+        Err(_) => {
+            let path = targets::expand_path(&target.path)?;
+            fs::read(&path).map_err(|e| format!("还没有下载过 {}，也无法读取 {}: {}", target.name, path.display(), e))?
+        }
+    };
+    let before = String::from_utf8_lossy(&source).to_string();
+    let adjusted = adjust(&before, &read_local(target), profile)?;
+    let after = adjusted.content;
+    let mut log = adjusted.transform_log;
+    log.extend(adjusted.keep_log);
+    let diff = similar::TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .context_radius(3)
+        .header("下载的版本", "调整后")
+        .to_string();
+    Ok((redact(&diff), log))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOWNLOADED: &str = "<settings>\n  <localRepository>/opt/shared/m2</localRepository>\n  <offline>false</offline>\n  <mirrors>\n    <mirror>\n      <id>corp</id>\n    </mirror>\n  </mirrors>\n</settings>\n";
+
+    fn paths(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_local_value_over_downloaded() {
+        let local = "<settings>\n  <localRepository>/home/me/m2</localRepository>\n  <offline>false</offline>\n</settings>\n";
+        let (result, log) = keep_local(DOWNLOADED, local, &paths(&["localRepository", "offline"])).unwrap();
+        assert!(result.contains("  <localRepository>/home/me/m2</localRepository>\n"));
+        assert!(result.contains("<id>corp</id>"));
+        assert_eq!(log, vec!["保留本机的 localRepository = /home/me/m2".to_string()]);
+    }
+
+    #[test]
+    fn removes_downloaded_value_missing_locally() {
+        let local = "<settings>\n  <localRepository>/opt/shared/m2</localRepository>\n</settings>\n";
+        let (result, log) = keep_local(DOWNLOADED, local, &paths(&["localRepository", "offline"])).unwrap();
+        assert!(!result.contains("offline"));
+        assert!(result.contains("  <localRepository>/opt/shared/m2</localRepository>\n  <mirrors>"));
+        assert_eq!(log.len(), 1);
+    }
+
+    #[test]
+    fn adds_local_only_element() {
+        let local = "<settings>\n  <interactiveMode>false</interactiveMode>\n</settings>\n";
+        let (result, _) = keep_local(DOWNLOADED, local, &paths(&["interactiveMode"])).unwrap();
+        assert!(result.contains("  <interactiveMode>false</interactiveMode>\n</settings>"));
+    }
+
+    #[test]
+    fn kept_local_values_win_over_transforms() {
+        let local = "<settings>\n  <localRepository>/home/me/m2</localRepository>\n  <offline>true</offline>\n</settings>\n";
+        let profile = SyncProfile {
+            keep_local: paths(&["localRepository", "offline"]),
+            transforms: vec![
+                Transform::SetElement {
+                    path: "localRepository".to_string(),
+                    value: "/data/m2".to_string(),
+                },
+                Transform::AddActiveProfile { id: "ci".to_string() },
+            ],
+            ..SyncProfile::default()
+        };
+        let adjusted = adjust(DOWNLOADED, local, &profile).unwrap();
+        assert!(adjusted.content.contains("  <localRepository>/home/me/m2</localRepository>\n"));
+        assert!(!adjusted.content.contains("/data/m2"));
+        assert!(adjusted.content.contains("<offline>true</offline>"));
+        assert!(adjusted.content.contains("<activeProfile>ci</activeProfile>"));
+        assert_eq!(adjusted.transform_log.len(), 2);
+        assert_eq!(adjusted.keep_log.len(), 2);
+
+        // 本机没有该文件时只执行规则
+        let adjusted = adjust(DOWNLOADED, "", &profile).unwrap();
+        assert!(adjusted.content.contains("<localRepository>/data/m2</localRepository>"));
+        assert!(adjusted.keep_log.is_empty());
+    }
+
+    const SETTINGS: &str = "<settings>\n  <profiles/>\n  <mirrors>\n    <mirror>\n      <id>corp</id>\n      <url>https://repo.corp.example.com/maven</url>\n    </mirror>\n    <mirror>\n      <id>old</id>\n    </mirror>\n  </mirrors>\n</settings>\n";
+
+    /// 执行规则并确认结果仍是格式正确的 settings.xml
+    fn run(content: &str, transform: Transform) -> (String, &'static str) {
+        let applied = apply_one(content, &transform).unwrap();
+        parse(&applied.content).unwrap_or_else(|e| panic!("{}\n{}", e, applied.content));
+        (applied.content, applied.note)
+    }
+
+    fn proxy(id: &str, host: &str, port: u16) -> Transform {
+        Transform::SetProxy {
+            id: id.to_string(),
+            protocol: "http".to_string(),
+            host: host.to_string(),
+            port,
+            non_proxy_hosts: String::new(),
+            active: true,
+        }
+    }
+
+    #[test]
+    fn set_element_replaces_or_creates_missing_path() {
+        let set = |path: &str, value: &str| Transform::SetElement {
+            path: path.to_string(),
+            value: value.to_string(),
+        };
+        let (result, _) = run(SETTINGS, set("localRepository", "/data/m2"));
+        assert!(result.contains("  <localRepository>/data/m2</localRepository>\n</settings>"));
+        let (again, note) = run(&result, set("localRepository", "/data/m2"));
+        assert_eq!(again, result);
+        assert_eq!(note, "已是该值，跳过");
+
+        // 中间的元素不存在时一起创建，值被转义
+        let (nested, _) = run(SETTINGS, set("pluginGroups/pluginGroup", "a&b"));
+        assert!(nested.contains("  <pluginGroups>\n    <pluginGroup>a&amp;b</pluginGroup>\n  </pluginGroups>\n</settings>"));
+
+        assert!(apply_one(SETTINGS, &set("mirrors", "x")).is_err());
+    }
+
+    #[test]
+    fn set_element_expands_self_closing_parent() {
+        let (result, _) = run(
+            SETTINGS,
+            Transform::SetElement {
+                path: "profiles/profile/id".to_string(),
+                value: "local".to_string(),
+            },
+        );
+        assert!(result.contains("  <profiles>\n    <profile>\n      <id>local</id>\n    </profile>\n  </profiles>\n"));
+    }
+
+    #[test]
+    fn remove_by_id_deletes_whole_lines_and_skips_missing_ids() {
+        let remove = |section: &str, id: &str| Transform::RemoveById {
+            section: section.to_string(),
+            id: id.to_string(),
+        };
+        let (result, _) = run(SETTINGS, remove("mirrors", "old"));
+        assert!(!result.contains("old"));
+        assert!(result.contains("    </mirror>\n  </mirrors>"));
+
+        let (missing, note) = run(SETTINGS, remove("mirrors", "absent"));
+        assert_eq!(missing, SETTINGS);
+        assert_eq!(note, "未找到，跳过");
+        // 自闭合的列表中没有任何项
+        let (empty, note) = run(SETTINGS, remove("profiles", "local"));
+        assert_eq!(empty, SETTINGS);
+        assert_eq!(note, "未找到，跳过");
+        let (no_section, _) = run(SETTINGS, remove("servers", "nexus"));
+        assert_eq!(no_section, SETTINGS);
+
+        assert!(apply_one(SETTINGS, &remove("pluginGroups", "x")).is_err());
+    }
+
+    #[test]
+    fn add_active_profile_creates_section_once() {
+        let add = |id: &str| Transform::AddActiveProfile { id: id.to_string() };
+        let (result, _) = run(SETTINGS, add("jdk17"));
+        assert!(result.contains("  <activeProfiles>\n    <activeProfile>jdk17</activeProfile>\n  </activeProfiles>\n</settings>"));
+        let (again, note) = run(&result, add("jdk17"));
+        assert_eq!(again, result);
+        assert_eq!(note, "已激活，跳过");
+        let (second, _) = run(&result, add("ci"));
+        assert!(second.contains("<activeProfile>jdk17</activeProfile>\n    <activeProfile>ci</activeProfile>\n  </activeProfiles>"));
+
+        let self_closing = "<settings>\n  <activeProfiles/>\n</settings>\n";
+        let (expanded, _) = run(self_closing, add("ci"));
+        assert!(expanded.contains("  <activeProfiles>\n    <activeProfile>ci</activeProfile>\n  </activeProfiles>\n"));
+    }
+
+    #[test]
+    fn set_proxy_adds_new_proxy() {
+        let (result, _) = run(SETTINGS, proxy("corp", "proxy.corp.example.com", 3128));
+        let doc = parse(&result).unwrap();
+        let proxy_node = doc.descendants().find(|n| n.has_tag_name("proxy")).unwrap();
+        assert_eq!(child_text(proxy_node, "host").as_deref(), Some("proxy.corp.example.com"));
+        assert_eq!(child_text(proxy_node, "port").as_deref(), Some("3128"));
+        assert_eq!(child_text(proxy_node, "active").as_deref(), Some("true"));
+        assert!(result.contains("  <proxies>\n    <proxy>\n      <id>corp</id>\n"));
+    }
+
+    #[test]
+    fn set_proxy_replaces_same_id_and_keeps_credentials() {
+        let existing = "<settings>\n  <proxies>\n    <proxy>\n      <id>corp</id>\n      <host>old.example.com</host>\n      <port>8080</port>\n      <username>me</username>\n      <password>{abc}</password>\n    </proxy>\n    <proxy>\n      <id>other</id>\n    </proxy>\n  </proxies>\n</settings>\n";
+        let (result, _) = run(existing, proxy("corp", "new.example.com", 3128));
+        assert!(!result.contains("old.example.com"));
+        let doc = parse(&result).unwrap();
+        let proxies: Vec<_> = doc.descendants().filter(|n| n.has_tag_name("proxy")).collect();
+        assert_eq!(proxies.len(), 2);
+        assert_eq!(child_text(proxies[0], "host").as_deref(), Some("new.example.com"));
+        assert_eq!(child_text(proxies[0], "username").as_deref(), Some("me"));
+        assert_eq!(child_text(proxies[0], "password").as_deref(), Some("{abc}"));
+        assert_eq!(child_text(proxies[1], "id").as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn chained_rules_keep_document_well_formed() {
+        let transforms = vec![
+            Transform::RemoveById {
+                section: "mirrors".to_string(),
+                id: "corp".to_string(),
+            },
+            Transform::RemoveById {
+                section: "mirrors".to_string(),
+                id: "old".to_string(),
+            },
+            Transform::SetElement {
+                path: "mirrors/mirror/id".to_string(),
+                value: "new".to_string(),
+            },
+            proxy("corp", "proxy.example.com", 8080),
+            Transform::AddActiveProfile { id: "ci".to_string() },
+        ];
+        let (result, log) = apply(SETTINGS, &transforms).unwrap();
+        assert_eq!(log.len(), transforms.len());
+        let doc = parse(&result).unwrap();
+        let mirrors = child(doc.root_element(), "mirrors").unwrap();
+        assert_eq!(mirrors.children().filter(|n| n.is_element()).count(), 1);
+        assert!(apply(&result, &transforms[3..]).unwrap().0 == result);
+    }
+
+    #[test]
+    fn rejects_non_settings_document() {
+        assert!(apply("<toolchains/>", &[Transform::AddActiveProfile { id: "x".to_string() }]).is_err());
+    }
+
+    #[test]
+    fn unreadable_local_file_changes_nothing() {
+        let (result, log) = keep_local(DOWNLOADED, "not xml", &paths(&["localRepository"])).unwrap();
+        assert_eq!(result, DOWNLOADED);
+        assert!(log.is_empty());
+    }
+}
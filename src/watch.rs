@@ -0,0 +1,197 @@
+// watch.rs
+// 监听配置文件和同步目标文件的外部修改（Linux 上基于 inotify）
+//
+// 监听的是文件所在目录：编辑器保存时常常先写临时文件再改名，直接监听文件会丢失事件。
+// 同一文件的连续事件在后台线程中合并，安静一段时间后才唤醒界面处理，避免读到写了一半的内容。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// 最后一次事件之后等待多久才认为文件修改完成
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub struct FileWatcher {
+    watcher: Option<RecommendedWatcher>,
+    /// 关心的文件，与监听回调共享
+    files: Arc<Mutex<Vec<PathBuf>>>,
+    /// 正在监听的目录
+    dirs: Vec<PathBuf>,
+    /// 已修改完成、等待界面取走的文件
+    ready: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl FileWatcher {
+    /// 创建监听器，文件修改完成时唤醒界面
+    pub fn new(ctx: egui::Context) -> Self {
+        let files: Arc<Mutex<Vec<PathBuf>>> = Arc::default();
+        let ready: Arc<Mutex<Vec<PathBuf>>> = Arc::default();
+        let (sender, events) = mpsc::channel();
+
+        let wanted = Arc::clone(&files);
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            let Ok(event) = result else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            let Ok(wanted) = wanted.lock() else {
+                return;
+            };
+            for path in event.paths.into_iter().filter(|path| wanted.contains(path)) {
+                let _ = sender.send(path);
+            }
+        });
+        let watcher = match watcher {
+            Ok(watcher) => {
+                let ready = Arc::clone(&ready);
+                thread::spawn(move || debounce(events, ready, ctx));
+                Some(watcher)
+            }
+            Err(e) => {
+                log::warn!("Failed to create file watcher: {}", e);
+                None
+            }
+        };
+
+        Self {
+            watcher,
+            files,
+            dirs: Vec::new(),
+            ready,
+        }
+    }
+
+    /// 更新需要监听的文件列表，文件和所在目录都不变时不做任何事
+    ///
+    /// 目录尚不存在时先跳过，每次调用都会重新检查，目录创建后开始监听。
+    pub fn watch(&mut self, files: Vec<PathBuf>) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+
+        let mut dirs: Vec<PathBuf> = files
+            .iter()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        // 监听回调在事件线程中持有同一把锁，增减目录前必须先释放，否则会与事件线程互相等待
+        let unchanged = self.files.lock().map_or(true, |watched| *watched == files);
+        if unchanged && dirs == self.dirs {
+            return;
+        }
+
+        for dir in self.dirs.iter().filter(|dir| !dirs.contains(dir)) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in dirs.iter().filter(|dir| !self.dirs.contains(dir)) {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                log::warn!("Failed to watch {}: {}", dir.display(), e);
+            }
+        }
+
+        if let Ok(mut watched) = self.files.lock() {
+            *watched = files;
+        }
+        self.dirs = dirs;
+    }
+
+    /// 取出已修改完成的文件
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        let ready = match self.ready.lock() {
+            Ok(mut ready) => std::mem::take(&mut *ready),
+            Err(_) => return Vec::new(),
+        };
+        match self.files.lock() {
+            Ok(files) => ready.into_iter().filter(|path| files.contains(path)).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// 合并同一文件的连续事件，安静 DEBOUNCE 之后交给界面并唤醒界面；监听器释放后退出
+fn debounce(events: Receiver<PathBuf>, ready: Arc<Mutex<Vec<PathBuf>>>, ctx: egui::Context) {
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    loop {
+        let received = match pending.values().min() {
+            Some(first) => events.recv_timeout(DEBOUNCE.saturating_sub(first.elapsed())),
+            None => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(path) => {
+                pending.insert(path, Instant::now());
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        let done: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, last)| last.elapsed() >= DEBOUNCE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        if done.is_empty() {
+            continue;
+        }
+        for path in &done {
+            pending.remove(path);
+        }
+        if let Ok(mut ready) = ready.lock() {
+            for path in done {
+                if !ready.contains(&path) {
+                    ready.push(path);
+                }
+            }
+        }
+        ctx.request_repaint();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// 等待修改完成的文件，最多等几秒
+    fn wait_changed(watcher: &mut FileWatcher) -> Vec<PathBuf> {
+        for _ in 0..50 {
+            let changed = watcher.changed_files();
+            if !changed.is_empty() {
+                return changed;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Vec::new()
+    }
+
+    #[test]
+    fn starts_watching_directory_created_later() {
+        let root = std::env::temp_dir().join(format!("msettings-watch-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let root = root.canonicalize().unwrap();
+        let file = root.join("later").join("settings.xml");
+
+        let mut watcher = FileWatcher::new(egui::Context::default());
+        watcher.watch(vec![file.clone()]);
+        assert!(watcher.dirs.is_empty());
+
+        // 同样的文件列表，目录创建后开始监听
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        watcher.watch(vec![file.clone()]);
+        assert_eq!(watcher.dirs, vec![file.parent().unwrap().to_path_buf()]);
+
+        fs::write(&file, "<settings/>").unwrap();
+        fs::write(root.join("later").join("other.xml"), "<other/>").unwrap();
+        assert_eq!(wait_changed(&mut watcher), vec![file]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
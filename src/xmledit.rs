@@ -0,0 +1,161 @@
+// xmledit.rs
+// 保留格式的 XML 修改：roxmltree 只能读取，修改通过按元素的字节范围替换原文完成，
+// 其余内容（注释、格式）保持不变
+
+use std::ops::Range;
+
+pub struct Edit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+pub fn apply_edits(content: &str, mut edits: Vec<Edit>) -> String {
+    // 从后往前替换，前面的范围不受影响
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+    let mut result = content.to_string();
+    for edit in edits {
+        result.replace_range(edit.range, &edit.text);
+    }
+    result
+}
+
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 元素所在行的缩进，元素前面还有其他内容时返回空
+pub fn indent_of(content: &str, pos: usize) -> String {
+    let line_start = content[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &content[line_start..pos];
+    if prefix.chars().all(|c| c == ' ' || c == '\t') {
+        prefix.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// 结束标签前的位置（跳过结束标签前的空白），自闭合元素返回 None
+pub fn before_end_tag(content: &str, node: roxmltree::Node) -> Option<usize> {
+    let range = node.range();
+    let element = &content[range.clone()];
+    if element.ends_with("/>") {
+        return None;
+    }
+    let end_tag = element.rfind("</")?;
+    Some(range.start + element[..end_tag].trim_end().len())
+}
+
+/// 删除元素时连同它所在的整行（缩进和换行）一起删除
+pub fn line_range(content: &str, node: roxmltree::Node) -> Range<usize> {
+    let range = node.range();
+    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let only_whitespace_before = content[line_start..range.start].chars().all(|c| c == ' ' || c == '\t');
+    let rest = &content[range.end..];
+    let line_end = rest.find('\n').map_or(content.len(), |i| range.end + i + 1);
+    let only_whitespace_after = content[range.end..line_end].trim().is_empty();
+    if only_whitespace_before && only_whitespace_after {
+        line_start..line_end
+    } else {
+        range
+    }
+}
+
+pub fn child<'a, 'input>(node: roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+pub fn child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+    child(node, name).map(|n| n.text().unwrap_or_default().trim().to_string())
+}
+
+/// 在元素末尾（结束标签前）插入一个子元素，缩进比父元素多一级
+pub fn append_child(content: &str, parent: roxmltree::Node, element: &str) -> Edit {
+    let parent_indent = indent_of(content, parent.range().start);
+    let indent = format!("{}  ", parent_indent);
+    let element = element.replace('\n', &format!("\n{}", indent));
+    match before_end_tag(content, parent) {
+        Some(pos) => {
+            // 结束标签紧跟在后面时（如 `<a></a>`），结束标签换到新行
+            let closing = if content[pos..].starts_with("</") {
+                format!("\n{}", parent_indent)
+            } else {
+                String::new()
+            };
+            Edit {
+                range: pos..pos,
+                text: format!("\n{}{}{}", indent, element, closing),
+            }
+        }
+        // 自闭合的父元素展开后再插入
+        None => {
+            let name = parent.tag_name().name();
+            Edit {
+                range: parent.range(),
+                text: format!("<{}>\n{}{}\n{}</{}>", name, indent, element, parent_indent, name),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit_first(content: &str, name: &str, f: impl Fn(&str, roxmltree::Node) -> Edit) -> String {
+        let doc = roxmltree::Document::parse(content).unwrap();
+        let node = doc.descendants().find(|n| n.has_tag_name(name)).unwrap();
+        let result = apply_edits(content, vec![f(content, node)]);
+        roxmltree::Document::parse(&result).unwrap_or_else(|e| panic!("{}\n{}", e, result));
+        result
+    }
+
+    #[test]
+    fn append_child_expands_self_closing_and_empty_parents() {
+        let content = "<settings>\n  <profiles/>\n</settings>";
+        let result = edit_first(content, "profiles", |c, n| append_child(c, n, "<profile>\n  <id>a</id>\n</profile>"));
+        assert_eq!(
+            result,
+            "<settings>\n  <profiles>\n    <profile>\n      <id>a</id>\n    </profile>\n  </profiles>\n</settings>"
+        );
+
+        let content = "<settings>\n  <profiles></profiles>\n</settings>";
+        let result = edit_first(content, "profiles", |c, n| append_child(c, n, "<profile/>"));
+        assert_eq!(result, "<settings>\n  <profiles>\n    <profile/>\n  </profiles>\n</settings>");
+    }
+
+    #[test]
+    fn append_child_goes_after_last_child() {
+        let content = "<settings>\n  <mirrors>\n    <mirror/>\n  </mirrors>\n</settings>";
+        let result = edit_first(content, "mirrors", |c, n| append_child(c, n, "<mirror id=\"b\"/>"));
+        assert_eq!(result, "<settings>\n  <mirrors>\n    <mirror/>\n    <mirror id=\"b\"/>\n  </mirrors>\n</settings>");
+    }
+
+    #[test]
+    fn line_range_removes_whole_line_only_when_alone() {
+        let content = "<a>\n  <b/>\n  <c/>\n</a>";
+        let result = edit_first(content, "b", |c, n| Edit { range: line_range(c, n), text: String::new() });
+        assert_eq!(result, "<a>\n  <c/>\n</a>");
+
+        let content = "<a><b/><c/></a>";
+        let result = edit_first(content, "b", |c, n| Edit { range: line_range(c, n), text: String::new() });
+        assert_eq!(result, "<a><c/></a>");
+    }
+
+    #[test]
+    fn applies_multiple_edits_from_the_end() {
+        let content = "<a><b>1</b><c>2</c></a>";
+        let doc = roxmltree::Document::parse(content).unwrap();
+        let edits = ["b", "c"]
+            .iter()
+            .map(|name| {
+                let node = doc.descendants().find(|n| n.has_tag_name(*name)).unwrap();
+                Edit { range: node.range(), text: format!("<{0}>{1}</{0}>", name, escape_xml("<&>")) }
+            })
+            .collect();
+        assert_eq!(apply_edits(content, edits), "<a><b>&lt;&amp;&gt;</b><c>&lt;&amp;&gt;</c></a>");
+    }
+}